@@ -0,0 +1,320 @@
+//! Per-block audio feature extraction results.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// A single slice of extracted audio features, produced once per analysis
+/// block and consumed by [`crate::mapping::MappingDescriptor`].
+/// Every field is `#[serde(default)]` so a frame serialized in a
+/// self-describing format such as JSON before a field existed still
+/// deserializes: the missing field just comes back zeroed instead of
+/// failing the whole frame. This does not hold for the bincode payload that
+/// [`crate::recording::load_compressed`] reads, which carries no field
+/// names. See [`crate::recording::RecordingExport::schema_version`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnalysisFrame {
+    #[serde(default)]
+    pub timestamp: f64,
+    #[serde(default)]
+    pub rms: f32,
+    /// Pre-AGC RMS of the input block; equal to `rms` when AGC is disabled.
+    #[serde(default)]
+    pub input_rms: f32,
+    #[serde(default)]
+    pub beat_confidence: f32,
+    #[serde(default)]
+    pub low_band_energy: f32,
+    #[serde(default)]
+    pub mid_band_energy: f32,
+    #[serde(default)]
+    pub high_band_energy: f32,
+    /// `low_band_energy` exponentially smoothed across blocks. Equal to
+    /// `low_band_energy` unless [`crate::engine::AnalysisEngine::band_smoothing`]
+    /// is configured, in which case it trades responsiveness for reduced
+    /// block-to-block flicker in per-band visuals.
+    #[serde(default)]
+    pub low_band_energy_smoothed: f32,
+    /// `mid_band_energy` exponentially smoothed across blocks. See
+    /// [`Self::low_band_energy_smoothed`].
+    #[serde(default)]
+    pub mid_band_energy_smoothed: f32,
+    /// `high_band_energy` exponentially smoothed across blocks. See
+    /// [`Self::low_band_energy_smoothed`].
+    #[serde(default)]
+    pub high_band_energy_smoothed: f32,
+    /// `low_band_energy` run through attack/decay ballistics: rises
+    /// instantly to match a louder block, then falls gradually like a
+    /// classic bar-graph analyzer needle. Equal to `low_band_energy` unless
+    /// [`crate::engine::AnalysisEngine::band_ballistics`] is configured.
+    #[serde(default)]
+    pub low_band_energy_ballistic: f32,
+    /// `mid_band_energy` run through the same ballistics. See
+    /// [`Self::low_band_energy_ballistic`].
+    #[serde(default)]
+    pub mid_band_energy_ballistic: f32,
+    /// `high_band_energy` run through the same ballistics. See
+    /// [`Self::low_band_energy_ballistic`].
+    #[serde(default)]
+    pub high_band_energy_ballistic: f32,
+    /// Peak-hold marker for `low_band_energy_ballistic`: jumps to a new peak
+    /// immediately, lingers there, then falls at the same rate. Equal to
+    /// `low_band_energy` unless
+    /// [`crate::engine::AnalysisEngine::band_ballistics`] is configured.
+    #[serde(default)]
+    pub low_band_energy_peak: f32,
+    /// Peak-hold marker for `mid_band_energy_ballistic`. See
+    /// [`Self::low_band_energy_peak`].
+    #[serde(default)]
+    pub mid_band_energy_peak: f32,
+    /// Peak-hold marker for `high_band_energy_ballistic`. See
+    /// [`Self::low_band_energy_peak`].
+    #[serde(default)]
+    pub high_band_energy_peak: f32,
+    #[serde(default)]
+    pub harmonic_energy: f32,
+    #[serde(default)]
+    pub percussive_energy: f32,
+    /// 12-bin pitch-class energy distribution, indexed by MIDI pitch class
+    /// (0 = C, 9 = A, ...).
+    #[serde(default)]
+    pub chroma: [f32; 12],
+    /// Peak spectral magnitude over mean magnitude: high for a tonal peak,
+    /// close to 1 for broadband noise. See [`crate::spectral::FrequencyFeatures`].
+    #[serde(default)]
+    pub spectral_crest: f32,
+    /// Magnitude-weighted standard deviation of bin frequencies around the
+    /// spectral centroid, normalised into `0..1`.
+    #[serde(default)]
+    pub spectral_spread: f32,
+    /// Change in the magnitude spectrum since the previous block, averaged
+    /// across bins; positive-rectified unless another
+    /// [`crate::engine::FluxMode`] is configured. See
+    /// [`crate::engine::SpectralWhiteningConfig`].
+    #[serde(default)]
+    pub spectral_flux: f32,
+    /// `false` once `rms` has stayed below the engine's silence threshold for
+    /// at least its configured hold duration. See
+    /// [`crate::engine::SilenceGateConfig`].
+    #[serde(default)]
+    pub activity: bool,
+    /// 1-indexed position of the most recent beat within its bar. `0` until
+    /// the first beat is detected. See [`crate::engine::BarTrackerConfig`].
+    #[serde(default)]
+    pub beat_in_bar: u32,
+    /// `true` on the frame where a beat lands on the first position of a bar.
+    #[serde(default)]
+    pub is_downbeat: bool,
+    /// Magnitude spectrum rebinned onto a log-frequency grid. Empty unless
+    /// [`crate::engine::AnalysisEngine::log_spectrum`] is configured. See
+    /// [`crate::spectral::log_frequency_spectrum`].
+    #[serde(default)]
+    pub log_spectrum: Vec<f32>,
+    /// `true` while the engine's flux priming window is still suppressing
+    /// `spectral_flux` for this frame. See
+    /// [`crate::engine::FluxPrimingConfig`].
+    #[serde(default)]
+    pub warming_up: bool,
+    /// Continuous position within the current beat, in `0..1`, ramping
+    /// between beats and wrapping to `0` on each detected beat. `0` until a
+    /// tempo can be estimated. See [`crate::engine::AnalysisEngine::bar_tracker`].
+    #[serde(default)]
+    pub beat_phase: f32,
+    /// `rms` rescaled to dBFS (`20 * log10(rms)`), floored at the engine's
+    /// configured [`crate::engine::AnalysisEngine::rms_db_floor`] instead of
+    /// `-inf` for silence. Better suited to a VU-style meter than linear
+    /// `rms`, which compresses the perceptually interesting quiet range.
+    #[serde(default)]
+    pub rms_db: f32,
+    /// `rms` run through attack/release ballistics instead of reported raw:
+    /// snaps up immediately on a loud transient, then decays gradually
+    /// during quieter blocks that follow. Equal to `rms` unless
+    /// [`crate::engine::AnalysisEngine::rms_envelope`] is configured. Suited
+    /// to a VU-style meter, where raw per-block `rms` looks too jittery.
+    #[serde(default)]
+    pub rms_envelope: f32,
+    /// Left/right stereo balance in `-1.0..=1.0`, `-1.0` all the way left,
+    /// `1.0` all the way right. `0.0` (centered) unless the frame came from
+    /// [`crate::engine::AnalysisEngine::process_stereo_block`]. See
+    /// [`crate::stereo::balance`].
+    #[serde(default)]
+    pub balance: f32,
+    /// Named features contributed by registered
+    /// [`crate::engine::FeatureExtractor`]s, keyed by extractor-assigned
+    /// name. Empty unless extractors are registered. Values are also
+    /// reachable through [`Self::feature`] by name, alongside the built-in
+    /// features.
+    #[serde(default)]
+    pub extra: HashMap<String, f32>,
+}
+
+impl AnalysisFrame {
+    pub fn silent(timestamp: f64) -> Self {
+        Self {
+            timestamp,
+            rms: 0.0,
+            input_rms: 0.0,
+            beat_confidence: 0.0,
+            low_band_energy: 0.0,
+            mid_band_energy: 0.0,
+            high_band_energy: 0.0,
+            low_band_energy_smoothed: 0.0,
+            mid_band_energy_smoothed: 0.0,
+            high_band_energy_smoothed: 0.0,
+            low_band_energy_ballistic: 0.0,
+            mid_band_energy_ballistic: 0.0,
+            high_band_energy_ballistic: 0.0,
+            low_band_energy_peak: 0.0,
+            mid_band_energy_peak: 0.0,
+            high_band_energy_peak: 0.0,
+            harmonic_energy: 0.0,
+            percussive_energy: 0.0,
+            chroma: [0.0; 12],
+            spectral_crest: 0.0,
+            spectral_spread: 0.0,
+            spectral_flux: 0.0,
+            activity: false,
+            beat_in_bar: 0,
+            is_downbeat: false,
+            log_spectrum: Vec::new(),
+            warming_up: false,
+            beat_phase: 0.0,
+            rms_db: 0.0,
+            rms_envelope: 0.0,
+            balance: 0.0,
+            extra: HashMap::new(),
+        }
+    }
+
+    /// Look up a feature by its canonical name, as used in mapping sources
+    /// and expressions. Falls back to [`Self::extra`] for names contributed
+    /// by a registered [`crate::engine::FeatureExtractor`].
+    pub fn feature(&self, name: &str) -> Option<f32> {
+        match name {
+            "rms" => Some(self.rms),
+            "rms_db" => Some(self.rms_db),
+            "rms_envelope" => Some(self.rms_envelope),
+            "balance" => Some(self.balance),
+            "beat_confidence" => Some(self.beat_confidence),
+            "low_band_energy" => Some(self.low_band_energy),
+            "mid_band_energy" => Some(self.mid_band_energy),
+            "high_band_energy" => Some(self.high_band_energy),
+            "low_band_energy_smoothed" => Some(self.low_band_energy_smoothed),
+            "mid_band_energy_smoothed" => Some(self.mid_band_energy_smoothed),
+            "high_band_energy_smoothed" => Some(self.high_band_energy_smoothed),
+            "low_band_energy_ballistic" => Some(self.low_band_energy_ballistic),
+            "mid_band_energy_ballistic" => Some(self.mid_band_energy_ballistic),
+            "high_band_energy_ballistic" => Some(self.high_band_energy_ballistic),
+            "low_band_energy_peak" => Some(self.low_band_energy_peak),
+            "mid_band_energy_peak" => Some(self.mid_band_energy_peak),
+            "high_band_energy_peak" => Some(self.high_band_energy_peak),
+            "spectral_crest" => Some(self.spectral_crest),
+            "spectral_spread" => Some(self.spectral_spread),
+            "spectral_flux" => Some(self.spectral_flux),
+            _ => self.extra.get(name).copied(),
+        }
+    }
+
+    /// Whether any continuously-valued feature differs from `other` by more
+    /// than `epsilon`. Ignores discrete/structural fields (`timestamp`, the
+    /// beat/downbeat/activity flags, and the variable-length `log_spectrum`
+    /// and `extra` collections) that a threshold diff doesn't meaningfully
+    /// apply to. Intended for deduplicating near-identical consecutive
+    /// frames before they reach a downstream sink like MIDI/OSC.
+    pub fn significantly_differs(&self, other: &Self, epsilon: f32) -> bool {
+        let differs = |a: f32, b: f32| (a - b).abs() > epsilon;
+        differs(self.rms, other.rms)
+            || differs(self.rms_db, other.rms_db)
+            || differs(self.rms_envelope, other.rms_envelope)
+            || differs(self.balance, other.balance)
+            || differs(self.input_rms, other.input_rms)
+            || differs(self.beat_confidence, other.beat_confidence)
+            || differs(self.low_band_energy, other.low_band_energy)
+            || differs(self.mid_band_energy, other.mid_band_energy)
+            || differs(self.high_band_energy, other.high_band_energy)
+            || differs(self.low_band_energy_smoothed, other.low_band_energy_smoothed)
+            || differs(self.mid_band_energy_smoothed, other.mid_band_energy_smoothed)
+            || differs(self.high_band_energy_smoothed, other.high_band_energy_smoothed)
+            || differs(self.low_band_energy_ballistic, other.low_band_energy_ballistic)
+            || differs(self.mid_band_energy_ballistic, other.mid_band_energy_ballistic)
+            || differs(self.high_band_energy_ballistic, other.high_band_energy_ballistic)
+            || differs(self.low_band_energy_peak, other.low_band_energy_peak)
+            || differs(self.mid_band_energy_peak, other.mid_band_energy_peak)
+            || differs(self.high_band_energy_peak, other.high_band_energy_peak)
+            || differs(self.harmonic_energy, other.harmonic_energy)
+            || differs(self.percussive_energy, other.percussive_energy)
+            || differs(self.spectral_crest, other.spectral_crest)
+            || differs(self.spectral_spread, other.spectral_spread)
+            || differs(self.spectral_flux, other.spectral_flux)
+            || differs(self.beat_phase, other.beat_phase)
+            || self.chroma.iter().zip(other.chroma.iter()).any(|(a, b)| differs(*a, *b))
+    }
+
+    /// Overwrite a feature by its canonical name. Returns `false` for an
+    /// unknown name, leaving the frame untouched.
+    pub fn set_feature(&mut self, name: &str, value: f32) -> bool {
+        match name {
+            "rms" => self.rms = value,
+            "rms_db" => self.rms_db = value,
+            "rms_envelope" => self.rms_envelope = value,
+            "balance" => self.balance = value,
+            "beat_confidence" => self.beat_confidence = value,
+            "low_band_energy" => self.low_band_energy = value,
+            "mid_band_energy" => self.mid_band_energy = value,
+            "high_band_energy" => self.high_band_energy = value,
+            "low_band_energy_smoothed" => self.low_band_energy_smoothed = value,
+            "mid_band_energy_smoothed" => self.mid_band_energy_smoothed = value,
+            "high_band_energy_smoothed" => self.high_band_energy_smoothed = value,
+            "low_band_energy_ballistic" => self.low_band_energy_ballistic = value,
+            "mid_band_energy_ballistic" => self.mid_band_energy_ballistic = value,
+            "high_band_energy_ballistic" => self.high_band_energy_ballistic = value,
+            "low_band_energy_peak" => self.low_band_energy_peak = value,
+            "mid_band_energy_peak" => self.mid_band_energy_peak = value,
+            "high_band_energy_peak" => self.high_band_energy_peak = value,
+            "spectral_crest" => self.spectral_crest = value,
+            "spectral_spread" => self.spectral_spread = value,
+            "spectral_flux" => self.spectral_flux = value,
+            _ => return false,
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn minimal_json_missing_newer_fields_deserializes_with_defaults() {
+        let json = r#"{"timestamp": 1.5, "rms": 0.4}"#;
+        let frame: AnalysisFrame = serde_json::from_str(json).expect("missing fields should default, not fail");
+
+        assert_eq!(frame.timestamp, 1.5);
+        assert_eq!(frame.rms, 0.4);
+        assert_eq!(frame.spectral_flux, 0.0);
+        assert_eq!(frame.chroma, [0.0; 12]);
+        assert!(!frame.activity);
+        assert!(!frame.is_downbeat);
+    }
+
+    #[test]
+    fn significantly_differs_ignores_noise_below_epsilon_but_catches_a_real_change() {
+        let a = AnalysisFrame { rms: 0.500, ..AnalysisFrame::silent(0.0) };
+        let nearly_identical = AnalysisFrame { rms: 0.501, ..AnalysisFrame::silent(1.0) };
+        let changed = AnalysisFrame { rms: 0.9, ..AnalysisFrame::silent(2.0) };
+
+        assert!(!a.significantly_differs(&nearly_identical, 0.01));
+        assert!(a.significantly_differs(&changed, 0.01));
+    }
+
+    #[test]
+    fn feature_looks_up_a_known_field_by_name_and_none_for_an_unknown_one() {
+        let frame = AnalysisFrame {
+            spectral_flux: 0.42,
+            ..AnalysisFrame::silent(0.0)
+        };
+
+        assert_eq!(frame.feature("spectral_flux"), Some(0.42));
+        assert_eq!(frame.feature("nope"), None);
+    }
+}
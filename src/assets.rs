@@ -0,0 +1,315 @@
+//! Named mesh assets referenced by scenes.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AssetError {
+    NotFound(String),
+    Io { name: String, path: PathBuf, message: String },
+    InUse(String),
+    Malformed { name: String, message: String },
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssetError::NotFound(name) => write!(f, "asset `{name}` is not registered"),
+            AssetError::Io { name, path, message } => {
+                write!(f, "asset `{name}` at {path:?} could not be loaded: {message}")
+            }
+            AssetError::InUse(name) => write!(f, "asset `{name}` is still referenced by a handle"),
+            AssetError::Malformed { name, message } => {
+                write!(f, "asset `{name}` is malformed: {message}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for AssetError {}
+
+const STL_HEADER_LEN: usize = 80;
+const STL_TRIANGLE_LEN: usize = 50;
+
+/// Thin a binary STL's triangle list to at most `max_triangles`, keeping an
+/// even stride through the original order (always including the last
+/// triangle) rather than true quadric-error decimation: cheap, and close
+/// enough to preserve overall shape and bounding box for a well-distributed
+/// mesh. `None` if `bytes` isn't a well-formed binary STL.
+fn decimate_stl(bytes: &[u8], max_triangles: usize) -> Option<Vec<u8>> {
+    if bytes.len() < STL_HEADER_LEN + 4 {
+        return None;
+    }
+    let triangles_start = STL_HEADER_LEN + 4;
+    let count = u32::from_le_bytes(bytes[STL_HEADER_LEN..triangles_start].try_into().ok()?) as usize;
+    if bytes.len() < triangles_start + count * STL_TRIANGLE_LEN {
+        return None;
+    }
+    if max_triangles == 0 || count <= max_triangles {
+        return Some(bytes.to_vec());
+    }
+
+    let stride = count.div_ceil(max_triangles);
+    let mut kept: Vec<usize> = (0..count).step_by(stride).collect();
+    if kept.last() != Some(&(count - 1)) {
+        if kept.len() >= max_triangles {
+            kept.pop();
+        }
+        kept.push(count - 1);
+    }
+
+    let mut out = Vec::with_capacity(triangles_start + kept.len() * STL_TRIANGLE_LEN);
+    out.extend_from_slice(&bytes[..STL_HEADER_LEN]);
+    out.extend_from_slice(&(kept.len() as u32).to_le_bytes());
+    for index in kept {
+        let start = triangles_start + index * STL_TRIANGLE_LEN;
+        out.extend_from_slice(&bytes[start..start + STL_TRIANGLE_LEN]);
+    }
+    Some(out)
+}
+
+/// A lightweight reference-counted handle to a registered asset, returned by
+/// [`AssetStore::register_stl`] and [`AssetStore::load_stl`].
+///
+/// Holding a handle keeps the store's [`AssetStore::is_referenced`] check
+/// true for that asset and makes [`AssetStore::evict`] refuse to remove it.
+/// Handles are cheap to clone: cloning bumps the same reference count rather
+/// than reloading the mesh.
+#[derive(Debug, Clone)]
+pub struct AssetHandle {
+    name: Rc<str>,
+}
+
+impl AssetHandle {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl PartialEq for AssetHandle {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.name, &other.name) || self.name == other.name
+    }
+}
+
+/// Maps asset names to filesystem paths, loads them on demand, and tracks
+/// which ones are still referenced via an [`AssetHandle`].
+#[derive(Debug, Clone, Default)]
+pub struct AssetStore {
+    meshes: HashMap<String, PathBuf>,
+    handles: HashMap<String, Rc<str>>,
+}
+
+impl AssetStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_map(assets: HashMap<String, PathBuf>) -> Self {
+        Self { meshes: assets, handles: HashMap::new() }
+    }
+
+    pub fn register(&mut self, name: impl Into<String>, path: impl Into<PathBuf>) {
+        self.meshes.insert(name.into(), path.into());
+    }
+
+    pub fn resolve(&self, name: &str) -> Option<&Path> {
+        self.meshes.get(name).map(|p| p.as_path())
+    }
+
+    /// Load a registered mesh file's raw bytes.
+    pub fn load_mesh(&self, name: &str) -> Result<Vec<u8>, AssetError> {
+        let path = self
+            .resolve(name)
+            .ok_or_else(|| AssetError::NotFound(name.to_string()))?;
+        std::fs::read(path).map_err(|e| AssetError::Io {
+            name: name.to_string(),
+            path: path.to_path_buf(),
+            message: e.to_string(),
+        })
+    }
+
+    /// Load a registered binary STL mesh, decimated to at most
+    /// `max_triangles` triangles. See [`decimate_stl`]. Errors if the file
+    /// can't be read or isn't a well-formed binary STL.
+    pub fn lod(&self, name: &str, max_triangles: usize) -> Result<Vec<u8>, AssetError> {
+        let bytes = self.load_mesh(name)?;
+        decimate_stl(&bytes, max_triangles).ok_or_else(|| AssetError::Malformed {
+            name: name.to_string(),
+            message: "not a well-formed binary STL file".to_string(),
+        })
+    }
+
+    /// Register a mesh path and return a handle to it, so callers reference
+    /// the asset by handle instead of by raw name.
+    pub fn register_stl(&mut self, name: impl Into<String>, path: impl Into<PathBuf>) -> AssetHandle {
+        let name = name.into();
+        self.meshes.insert(name.clone(), path.into());
+        self.handle_for(&name)
+    }
+
+    /// Look up an already-registered mesh and return a handle to it.
+    pub fn load_stl(&mut self, name: &str) -> Result<AssetHandle, AssetError> {
+        if !self.meshes.contains_key(name) {
+            return Err(AssetError::NotFound(name.to_string()));
+        }
+        Ok(self.handle_for(name))
+    }
+
+    fn handle_for(&mut self, name: &str) -> AssetHandle {
+        let rc = self
+            .handles
+            .entry(name.to_string())
+            .or_insert_with(|| Rc::from(name))
+            .clone();
+        AssetHandle { name: rc }
+    }
+
+    /// Whether `name` currently has an outstanding [`AssetHandle`] besides
+    /// the store's own bookkeeping copy.
+    pub fn is_referenced(&self, name: &str) -> bool {
+        self.handles.get(name).is_some_and(|rc| Rc::strong_count(rc) > 1)
+    }
+
+    /// Remove a registered asset, refusing if a handle to it is still held.
+    pub fn evict(&mut self, name: &str) -> Result<(), AssetError> {
+        if self.is_referenced(name) {
+            return Err(AssetError::InUse(name.to_string()));
+        }
+        self.handles.remove(name);
+        self.meshes
+            .remove(name)
+            .ok_or_else(|| AssetError::NotFound(name.to_string()))?;
+        Ok(())
+    }
+
+    /// Drop a registered asset and any cached mesh handle, regardless of
+    /// whether it's referenced or even registered. Returns whether an asset
+    /// was actually removed. Unlike [`Self::evict`], this never errors: it's
+    /// meant for callers that just want stale assets gone (e.g. switching
+    /// projects) rather than a strict "was this in use" check.
+    pub fn unregister(&mut self, name: &str) -> bool {
+        self.handles.remove(name);
+        self.meshes.remove(name).is_some()
+    }
+
+    /// Registered asset names not referenced by any scene's `asset` field,
+    /// for surfacing stale assets left behind by a project switch.
+    pub fn audit(&self, scenes: &[crate::scene::SceneDescriptor]) -> Vec<String> {
+        let referenced: std::collections::HashSet<&str> = scenes
+            .iter()
+            .filter_map(|scene| scene.asset.as_deref())
+            .collect();
+        self.meshes
+            .keys()
+            .filter(|name| !referenced.contains(name.as_str()))
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A synthetic binary STL of `count` degenerate (single-point) triangles
+    /// along the x axis, with triangle 0 holding the global minimum x and
+    /// the last triangle holding the global maximum, so a stride-decimated
+    /// copy that always keeps the first and last triangle preserves the
+    /// bounding box exactly.
+    fn synthetic_stl(count: u32) -> Vec<u8> {
+        let mut bytes = vec![0u8; STL_HEADER_LEN];
+        bytes.extend_from_slice(&count.to_le_bytes());
+        for i in 0..count {
+            let x = if i == 0 {
+                -1000.0
+            } else if i == count - 1 {
+                1000.0
+            } else {
+                i as f32
+            };
+            bytes.extend_from_slice(&[0.0f32; 3].map(f32::to_le_bytes).concat()); // normal
+            for _ in 0..3 {
+                bytes.extend_from_slice(&x.to_le_bytes());
+                bytes.extend_from_slice(&0.0f32.to_le_bytes());
+                bytes.extend_from_slice(&0.0f32.to_le_bytes());
+            }
+            bytes.extend_from_slice(&0u16.to_le_bytes());
+        }
+        bytes
+    }
+
+    fn stl_triangle_count(bytes: &[u8]) -> u32 {
+        u32::from_le_bytes(bytes[STL_HEADER_LEN..STL_HEADER_LEN + 4].try_into().unwrap())
+    }
+
+    fn stl_x_bounds(bytes: &[u8]) -> (f32, f32) {
+        let count = stl_triangle_count(bytes) as usize;
+        let mut min = f32::INFINITY;
+        let mut max = f32::NEG_INFINITY;
+        for i in 0..count {
+            let start = STL_HEADER_LEN + 4 + i * STL_TRIANGLE_LEN + 12; // skip normal
+            let x = f32::from_le_bytes(bytes[start..start + 4].try_into().unwrap());
+            min = min.min(x);
+            max = max.max(x);
+        }
+        (min, max)
+    }
+
+    #[test]
+    fn lod_reduces_triangle_count_and_preserves_bounding_box() {
+        let path = std::env::temp_dir().join("music_visualiser_dense_mesh_test.stl");
+        std::fs::write(&path, synthetic_stl(100)).unwrap();
+
+        let mut store = AssetStore::new();
+        store.register("dense", &path);
+
+        let full = store.load_mesh("dense").expect("full mesh should load");
+        let decimated = store.lod("dense", 10).expect("well-formed STL should decimate");
+
+        assert_eq!(stl_triangle_count(&full), 100);
+        assert!(stl_triangle_count(&decimated) <= 11, "got {}", stl_triangle_count(&decimated));
+        assert!(stl_triangle_count(&decimated) < stl_triangle_count(&full));
+        assert_eq!(stl_x_bounds(&decimated), stl_x_bounds(&full));
+
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn handle_keeps_asset_referenced() {
+        let mut store = AssetStore::new();
+        let handle = store.register_stl("crystal", "crystal.stl");
+
+        assert!(store.is_referenced("crystal"));
+        assert_eq!(handle.name(), "crystal");
+        assert_eq!(store.evict("crystal"), Err(AssetError::InUse("crystal".to_string())));
+
+        drop(handle);
+        assert!(!store.is_referenced("crystal"));
+        assert!(store.evict("crystal").is_ok());
+    }
+
+    #[test]
+    fn audit_flags_only_the_unreferenced_asset_and_unregister_drops_it() {
+        use crate::scene::{SceneDescriptor, SceneKind};
+
+        let mut store = AssetStore::new();
+        store.register("used", "used.stl");
+        store.register("stale", "stale.stl");
+
+        let scenes = vec![SceneDescriptor::new(
+            "kaleidoscope",
+            SceneKind::Kaleidoscope { order: 6 },
+        )
+        .with_asset("used")];
+
+        let orphans = store.audit(&scenes);
+        assert_eq!(orphans, vec!["stale".to_string()]);
+
+        assert!(store.unregister("stale"));
+        assert!(!store.unregister("stale"));
+        assert!(store.audit(&scenes).is_empty());
+    }
+}
@@ -0,0 +1,543 @@
+//! Lock-free single-producer/single-consumer handoff between audio capture
+//! and analysis, so a capture callback never blocks on the analysis thread.
+
+use std::fmt;
+use std::sync::atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::sync::{Mutex, MutexGuard};
+
+use crate::analysis::AnalysisFrame;
+use crate::engine::AnalysisEngine;
+use crate::playback::AudioMode;
+use crate::timeline::PlaybackClock;
+
+/// How to combine an [`InputRouting`]'s selected source channels into the
+/// single analysis signal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelMix {
+    /// Add the selected channels together.
+    Sum,
+    /// Add the selected channels together and divide by how many there are.
+    Average,
+    /// Pass a single channel through unchanged.
+    Single,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputRoutingError {
+    ChannelOutOfRange { channel: usize, channel_count: usize },
+    NoSourceChannels,
+    SingleRequiresOneChannel { got: usize },
+}
+
+impl fmt::Display for InputRoutingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputRoutingError::ChannelOutOfRange { channel, channel_count } => write!(
+                f,
+                "source channel {channel} is out of range for a {channel_count}-channel input"
+            ),
+            InputRoutingError::NoSourceChannels => {
+                write!(f, "at least one source channel must be selected")
+            }
+            InputRoutingError::SingleRequiresOneChannel { got } => write!(
+                f,
+                "ChannelMix::Single requires exactly one source channel, got {got}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for InputRoutingError {}
+
+/// Selects and combines a subset of an interleaved multi-channel input's
+/// channels into the single signal that feeds analysis.
+///
+/// The default routing treats the input as already mono: one channel, taken
+/// as-is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputRouting {
+    channel_count: usize,
+    source_channels: Vec<usize>,
+    mix: ChannelMix,
+}
+
+impl Default for InputRouting {
+    fn default() -> Self {
+        Self {
+            channel_count: 1,
+            source_channels: vec![0],
+            mix: ChannelMix::Single,
+        }
+    }
+}
+
+impl InputRouting {
+    /// `channel_count` is how many channels each input frame has;
+    /// `source_channels` are the (0-indexed) channels to combine with
+    /// `mix`. Errors if any source channel is out of range, none are
+    /// given, or `mix` is [`ChannelMix::Single`] with more than one
+    /// source channel.
+    pub fn new(
+        channel_count: usize,
+        source_channels: Vec<usize>,
+        mix: ChannelMix,
+    ) -> Result<Self, InputRoutingError> {
+        if source_channels.is_empty() {
+            return Err(InputRoutingError::NoSourceChannels);
+        }
+        for &channel in &source_channels {
+            if channel >= channel_count {
+                return Err(InputRoutingError::ChannelOutOfRange { channel, channel_count });
+            }
+        }
+        if mix == ChannelMix::Single && source_channels.len() != 1 {
+            return Err(InputRoutingError::SingleRequiresOneChannel { got: source_channels.len() });
+        }
+        Ok(Self { channel_count, source_channels, mix })
+    }
+
+    /// Convenience constructor for picking a single channel out of a
+    /// multi-channel input.
+    pub fn single(channel_count: usize, channel: usize) -> Result<Self, InputRoutingError> {
+        Self::new(channel_count, vec![channel], ChannelMix::Single)
+    }
+
+    pub fn channel_count(&self) -> usize {
+        self.channel_count
+    }
+
+    /// Downmix an interleaved buffer of this routing's `channel_count` into
+    /// mono samples, one per complete frame. A trailing partial frame is
+    /// dropped.
+    pub fn route(&self, interleaved: &[f32]) -> Vec<f32> {
+        interleaved
+            .chunks_exact(self.channel_count)
+            .map(|frame| match self.mix {
+                ChannelMix::Sum => self.source_channels.iter().map(|&c| frame[c]).sum(),
+                ChannelMix::Average => {
+                    let sum: f32 = self.source_channels.iter().map(|&c| frame[c]).sum();
+                    sum / self.source_channels.len() as f32
+                }
+                ChannelMix::Single => frame[self.source_channels[0]],
+            })
+            .collect()
+    }
+}
+
+/// Capture-side tuning knobs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioConfig {
+    pub ring_buffer_capacity: usize,
+    /// How to downmix a multi-channel input before it reaches analysis.
+    pub input_routing: InputRouting,
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        Self {
+            ring_buffer_capacity: 4096,
+            input_routing: InputRouting::default(),
+        }
+    }
+}
+
+/// Fixed-capacity SPSC ring buffer of samples. The producer (capture
+/// callback) calls [`Self::push`]/[`Self::push_slice`] without ever
+/// blocking, dropping and counting overflowed samples instead. The
+/// consumer calls [`Self::drain_into`] to fill fixed-size blocks for
+/// [`crate::engine::AnalysisEngine::process_block`].
+pub struct SampleRingBuffer {
+    slots: Vec<AtomicU32>,
+    capacity: usize,
+    write_pos: AtomicUsize,
+    read_pos: AtomicUsize,
+    overflow_count: AtomicU64,
+    underflow_count: AtomicU64,
+}
+
+impl SampleRingBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            slots: (0..capacity).map(|_| AtomicU32::new(0)).collect(),
+            capacity,
+            write_pos: AtomicUsize::new(0),
+            read_pos: AtomicUsize::new(0),
+            overflow_count: AtomicU64::new(0),
+            underflow_count: AtomicU64::new(0),
+        }
+    }
+
+    pub fn with_config(config: AudioConfig) -> Self {
+        Self::new(config.ring_buffer_capacity)
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn overflow_count(&self) -> u64 {
+        self.overflow_count.load(Ordering::Relaxed)
+    }
+
+    pub fn underflow_count(&self) -> u64 {
+        self.underflow_count.load(Ordering::Relaxed)
+    }
+
+    /// Push one sample. Drops it and counts an overflow instead of blocking
+    /// if the buffer is full.
+    pub fn push(&self, sample: f32) {
+        let write = self.write_pos.load(Ordering::Relaxed);
+        let read = self.read_pos.load(Ordering::Acquire);
+        if write.wrapping_sub(read) >= self.capacity {
+            self.overflow_count.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        let idx = write % self.capacity;
+        self.slots[idx].store(sample.to_bits(), Ordering::Relaxed);
+        self.write_pos.store(write.wrapping_add(1), Ordering::Release);
+    }
+
+    pub fn push_slice(&self, samples: &[f32]) {
+        for &sample in samples {
+            self.push(sample);
+        }
+    }
+
+    /// Drain up to `block.len()` samples into `block`, returning how many
+    /// were actually available. Counts an underflow if fewer were ready
+    /// than requested.
+    pub fn drain_into(&self, block: &mut [f32]) -> usize {
+        let write = self.write_pos.load(Ordering::Acquire);
+        let mut read = self.read_pos.load(Ordering::Relaxed);
+        let available = write.wrapping_sub(read).min(self.capacity);
+        let to_read = available.min(block.len());
+
+        for slot in block.iter_mut().take(to_read) {
+            let idx = read % self.capacity;
+            *slot = f32::from_bits(self.slots[idx].load(Ordering::Relaxed));
+            read = read.wrapping_add(1);
+        }
+        self.read_pos.store(read, Ordering::Release);
+
+        if to_read < block.len() {
+            self.underflow_count.fetch_add(1, Ordering::Relaxed);
+        }
+        to_read
+    }
+}
+
+/// Orchestrates capture -> ring buffer -> analysis: a capture thread calls
+/// [`Self::push_samples`] without blocking, while a render thread reads the
+/// engine/clock through [`Self::lock_analysis`]/[`Self::lock_clock`].
+///
+/// A panic on one thread while holding `analysis` or `clock` would normally
+/// poison the mutex permanently. For a long-running installation that's
+/// worse than losing one frame, so both lock helpers recover a poisoned
+/// mutex by resetting its contents to a clean state and continuing.
+pub struct AudioEngine {
+    ring_buffer: SampleRingBuffer,
+    analysis: Mutex<AnalysisEngine>,
+    clock: Mutex<PlaybackClock>,
+    block_size: usize,
+    input_routing: InputRouting,
+    subscribers: Mutex<Vec<SyncSender<AnalysisFrame>>>,
+    mode: Mutex<AudioMode>,
+    capturing: Mutex<bool>,
+}
+
+/// Error returned by [`AudioEngine::set_mode`].
+#[derive(Debug)]
+pub enum AudioModeError {
+    /// A capture stream is still marked active via
+    /// [`AudioEngine::set_capturing`]; stop it before switching modes.
+    CaptureActive,
+}
+
+impl fmt::Display for AudioModeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AudioModeError::CaptureActive => {
+                write!(f, "cannot switch audio mode while a capture stream is active")
+            }
+        }
+    }
+}
+
+impl std::error::Error for AudioModeError {}
+
+impl AudioEngine {
+    pub fn new(analysis: AnalysisEngine, config: AudioConfig, block_size: usize) -> Self {
+        Self {
+            input_routing: config.input_routing.clone(),
+            ring_buffer: SampleRingBuffer::with_config(config),
+            analysis: Mutex::new(analysis),
+            clock: Mutex::new(PlaybackClock::new()),
+            block_size,
+            subscribers: Mutex::new(Vec::new()),
+            mode: Mutex::new(AudioMode::Live),
+            capturing: Mutex::new(false),
+        }
+    }
+
+    pub fn mode(&self) -> AudioMode {
+        match self.mode.lock() {
+            Ok(guard) => guard.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+
+    /// Mark whether a capture stream is currently feeding this engine.
+    /// Drivers should call this around starting/stopping capture so
+    /// [`Self::set_mode`] can refuse to reconfigure a live stream out from
+    /// under itself.
+    pub fn set_capturing(&self, capturing: bool) {
+        match self.capturing.lock() {
+            Ok(mut guard) => *guard = capturing,
+            Err(poisoned) => *poisoned.into_inner() = capturing,
+        }
+    }
+
+    pub fn is_capturing(&self) -> bool {
+        match self.capturing.lock() {
+            Ok(guard) => *guard,
+            Err(poisoned) => *poisoned.into_inner(),
+        }
+    }
+
+    /// Switch how this engine is being fed, resetting the analysis engine's
+    /// adaptive state (see [`AnalysisEngine::reset_adaptive_state`]) while
+    /// preserving its configuration, the attached [`PlaybackClock`], and any
+    /// subscriptions. Errors if a capture stream is still marked active via
+    /// [`Self::set_capturing`]; stop it first.
+    pub fn set_mode(&self, mode: AudioMode) -> Result<(), AudioModeError> {
+        if self.is_capturing() {
+            return Err(AudioModeError::CaptureActive);
+        }
+
+        match self.mode.lock() {
+            Ok(mut guard) => *guard = mode,
+            Err(poisoned) => *poisoned.into_inner() = mode,
+        }
+        self.lock_analysis().reset_adaptive_state();
+        Ok(())
+    }
+
+    /// Subscribe to every [`AnalysisFrame`] this engine produces, via a
+    /// bounded channel holding up to `capacity` frames. If a subscriber
+    /// falls behind and its buffer fills, further frames are dropped for
+    /// it rather than blocking the analysis path.
+    pub fn subscribe(&self, capacity: usize) -> Receiver<AnalysisFrame> {
+        let (tx, rx) = sync_channel(capacity);
+        self.subscribers().push(tx);
+        rx
+    }
+
+    fn subscribers(&self) -> MutexGuard<'_, Vec<SyncSender<AnalysisFrame>>> {
+        match self.subscribers.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                eprintln!("warning: subscribers mutex was poisoned, recovering");
+                let mut guard = poisoned.into_inner();
+                guard.clear();
+                guard
+            }
+        }
+    }
+
+    /// Fan a frame out to every live subscriber, dropping it for any whose
+    /// buffer is full and pruning any whose receiver has been dropped.
+    fn broadcast(&self, frame: &AnalysisFrame) {
+        self.subscribers().retain(|tx| match tx.try_send(frame.clone()) {
+            Ok(()) | Err(TrySendError::Full(_)) => true,
+            Err(TrySendError::Disconnected(_)) => false,
+        });
+    }
+
+    pub fn input_routing(&self) -> &InputRouting {
+        &self.input_routing
+    }
+
+    pub fn ring_buffer(&self) -> &SampleRingBuffer {
+        &self.ring_buffer
+    }
+
+    pub fn lock_analysis(&self) -> MutexGuard<'_, AnalysisEngine> {
+        match self.analysis.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                eprintln!("warning: analysis mutex was poisoned, recovering");
+                let mut guard = poisoned.into_inner();
+                *guard = AnalysisEngine::new(guard.sample_rate());
+                guard
+            }
+        }
+    }
+
+    pub fn lock_clock(&self) -> MutexGuard<'_, PlaybackClock> {
+        match self.clock.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                eprintln!("warning: clock mutex was poisoned, recovering");
+                let mut guard = poisoned.into_inner();
+                *guard = PlaybackClock::new();
+                guard
+            }
+        }
+    }
+
+    /// Route interleaved samples down to mono per [`Self::input_routing`],
+    /// push them into the ring buffer, then drain and analyse every full
+    /// block that's ready, returning the frames produced.
+    ///
+    /// With the default single-channel routing this is a no-op downmix, so
+    /// existing callers passing already-mono samples are unaffected.
+    pub fn push_samples(&self, samples: &[f32]) -> Vec<AnalysisFrame> {
+        self.process_interleaved(samples)
+    }
+
+    /// Same as [`Self::push_samples`], named for callers passing raw
+    /// multi-channel interleaved frames matching [`Self::input_routing`]'s
+    /// channel count.
+    pub fn process_interleaved(&self, interleaved: &[f32]) -> Vec<AnalysisFrame> {
+        let mono = self.input_routing.route(interleaved);
+        self.ring_buffer.push_slice(&mono);
+
+        let mut block = vec![0.0f32; self.block_size];
+        let mut frames = Vec::new();
+        while self.ring_buffer.drain_into(&mut block) == self.block_size {
+            let frame = self.lock_analysis().process_block(&block);
+            self.broadcast(&frame);
+            frames.push(frame);
+        }
+        frames
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn overflow_counter_increments_instead_of_blocking() {
+        let buffer = Arc::new(SampleRingBuffer::new(8));
+
+        let producer = {
+            let buffer = Arc::clone(&buffer);
+            thread::spawn(move || {
+                for i in 0..32 {
+                    buffer.push(i as f32);
+                }
+            })
+        };
+        producer.join().expect("producer thread should not panic or block");
+
+        assert!(buffer.overflow_count() > 0, "expected some samples to overflow");
+    }
+
+    #[test]
+    fn drain_returns_only_whats_been_written_and_counts_underflow() {
+        let buffer = SampleRingBuffer::new(16);
+        buffer.push_slice(&[1.0, 2.0, 3.0]);
+
+        let mut block = [0.0f32; 5];
+        let read = buffer.drain_into(&mut block);
+
+        assert_eq!(read, 3);
+        assert_eq!(&block[..3], &[1.0, 2.0, 3.0]);
+        assert_eq!(buffer.underflow_count(), 1);
+    }
+
+    #[test]
+    fn push_samples_recovers_after_poisoned_analysis_mutex() {
+        let engine = Arc::new(AudioEngine::new(
+            AnalysisEngine::new(48_000),
+            AudioConfig::default(),
+            64,
+        ));
+
+        let result = {
+            let engine = Arc::clone(&engine);
+            std::panic::catch_unwind(move || {
+                let _guard = engine.lock_analysis();
+                panic!("simulated analysis panic while holding the lock");
+            })
+        };
+        assert!(result.is_err(), "the panic should have poisoned the mutex");
+
+        // Recovers instead of staying permanently poisoned.
+        engine.push_samples(&vec![0.1_f32; 64]);
+    }
+
+    #[test]
+    fn subscribers_all_receive_a_pushed_frame() {
+        let engine = AudioEngine::new(AnalysisEngine::new(48_000), AudioConfig::default(), 64);
+        let recorder = engine.subscribe(4);
+        let renderer = engine.subscribe(4);
+
+        engine.push_samples(&vec![0.1_f32; 64]);
+
+        let recorder_frame = recorder.try_recv().expect("recorder should have received a frame");
+        let renderer_frame = renderer.try_recv().expect("renderer should have received a frame");
+        assert_eq!(recorder_frame, renderer_frame);
+    }
+
+    #[test]
+    fn set_mode_switches_mode_and_preserves_the_attached_clock() {
+        let engine = AudioEngine::new(AnalysisEngine::new(48_000), AudioConfig::default(), 64);
+        engine.lock_clock().advance(1.0);
+
+        engine.set_mode(AudioMode::FilePlayback { path: "track.wav".into() }).expect("no capture active");
+
+        assert_eq!(
+            engine.mode(),
+            AudioMode::FilePlayback { path: "track.wav".into() }
+        );
+        assert_eq!(engine.lock_clock().time(), 1.0);
+    }
+
+    #[test]
+    fn set_mode_refuses_while_a_capture_stream_is_marked_active() {
+        let engine = AudioEngine::new(AnalysisEngine::new(48_000), AudioConfig::default(), 64);
+        engine.set_capturing(true);
+
+        let result = engine.set_mode(AudioMode::FilePlayback { path: "track.wav".into() });
+
+        assert!(matches!(result, Err(AudioModeError::CaptureActive)));
+        assert_eq!(engine.mode(), AudioMode::Live);
+    }
+
+    #[test]
+    fn routing_rejects_out_of_range_channel() {
+        assert_eq!(
+            InputRouting::new(4, vec![0, 5], ChannelMix::Average),
+            Err(InputRoutingError::ChannelOutOfRange { channel: 5, channel_count: 4 })
+        );
+    }
+
+    #[test]
+    fn routes_four_channel_input_to_average_of_two_selected_channels() {
+        let routing = InputRouting::new(4, vec![0, 2], ChannelMix::Average).unwrap();
+        // Two frames of 4 channels each: (ch0, ch1, ch2, ch3).
+        let interleaved = [1.0, 100.0, 3.0, 100.0, 5.0, 100.0, 7.0, 100.0];
+
+        let mono = routing.route(&interleaved);
+
+        assert_eq!(mono, vec![(1.0 + 3.0) / 2.0, (5.0 + 7.0) / 2.0]);
+    }
+
+    #[test]
+    fn process_interleaved_analyses_the_routed_signal() {
+        let routing = InputRouting::new(4, vec![0, 2], ChannelMix::Average).unwrap();
+        let config = AudioConfig { ring_buffer_capacity: 64, input_routing: routing.clone() };
+        let engine = AudioEngine::new(AnalysisEngine::new(48_000), config, 2);
+
+        let interleaved = [1.0, 100.0, 3.0, 100.0, 5.0, 100.0, 7.0, 100.0];
+        let frames = engine.process_interleaved(&interleaved);
+
+        assert_eq!(frames.len(), 1);
+        assert_eq!(engine.input_routing(), &routing);
+    }
+}
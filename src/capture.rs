@@ -0,0 +1,298 @@
+//! Keeps a live capture stream feeding [`crate::audio::AudioEngine`] across
+//! device failures: for a 24/7 installation, a capture device disconnecting
+//! (or its driver thread dying) shouldn't silently stop the visualiser.
+
+use std::fmt;
+
+use crate::audio::AudioEngine;
+use crate::timeline::{InstantTimeSource, TimeSource};
+
+/// Why a [`CaptureSupervisor`]'s device factory failed to open a device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CaptureError {
+    /// The factory couldn't open a device at all, e.g. it's busy, unplugged,
+    /// or permission was denied. Carries the driver's own message.
+    Open(String),
+}
+
+impl fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaptureError::Open(reason) => write!(f, "capture device could not be opened: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for CaptureError {}
+
+/// A source of raw capture blocks, abstracting over the real OS device so
+/// [`CaptureSupervisor`] can be driven by a mock in tests. `read_block`
+/// returning `None` signals the device has failed or disconnected.
+pub trait CaptureDevice: Send {
+    fn read_block(&mut self) -> Option<Vec<f32>>;
+}
+
+/// [`CaptureSupervisor`]'s view of its own capture device, exposed for
+/// diagnostics and UI status indicators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureHealth {
+    /// The device is open and successfully producing blocks.
+    Healthy,
+    /// The device failed; reopen attempts are ongoing with exponential
+    /// backoff.
+    Reconnecting,
+    /// Reopen attempts have reached [`CaptureSupervisorConfig::max_attempts`]
+    /// with no success. [`CaptureSupervisor::poll`] stops trying.
+    Failed,
+}
+
+/// Backoff tuning for [`CaptureSupervisor`]'s reopen attempts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CaptureSupervisorConfig {
+    pub initial_backoff_secs: f64,
+    pub max_backoff_secs: f64,
+    /// Reopen attempts before giving up and reporting
+    /// [`CaptureHealth::Failed`]. `0` means unlimited.
+    pub max_attempts: u32,
+}
+
+impl Default for CaptureSupervisorConfig {
+    fn default() -> Self {
+        Self {
+            initial_backoff_secs: 0.5,
+            max_backoff_secs: 30.0,
+            max_attempts: 0,
+        }
+    }
+}
+
+/// Wraps a device factory around a [`CaptureDevice`], reopening it with
+/// exponential backoff whenever [`CaptureDevice::read_block`] reports
+/// failure, and forwarding every successfully read block to an
+/// [`AudioEngine`] via [`AudioEngine::push_samples`].
+///
+/// Generic over [`TimeSource`] the same way [`crate::render_loop::RenderLoop`]
+/// is, so backoff timing can be driven by [`crate::timeline::MockTimeSource`]
+/// in tests instead of sleeping for real.
+pub struct CaptureSupervisor<S: TimeSource = InstantTimeSource> {
+    source: S,
+    factory: Box<dyn FnMut() -> Result<Box<dyn CaptureDevice>, CaptureError> + Send>,
+    config: CaptureSupervisorConfig,
+    device: Option<Box<dyn CaptureDevice>>,
+    health: CaptureHealth,
+    backoff_secs: f64,
+    attempts: u32,
+    next_attempt_at: f64,
+}
+
+impl CaptureSupervisor<InstantTimeSource> {
+    pub fn new(
+        factory: impl FnMut() -> Result<Box<dyn CaptureDevice>, CaptureError> + Send + 'static,
+        config: CaptureSupervisorConfig,
+    ) -> Self {
+        Self::with_source(InstantTimeSource::new(), factory, config)
+    }
+}
+
+impl<S: TimeSource> CaptureSupervisor<S> {
+    pub fn with_source(
+        source: S,
+        factory: impl FnMut() -> Result<Box<dyn CaptureDevice>, CaptureError> + Send + 'static,
+        config: CaptureSupervisorConfig,
+    ) -> Self {
+        let backoff_secs = config.initial_backoff_secs;
+        Self {
+            source,
+            factory: Box::new(factory),
+            config,
+            device: None,
+            health: CaptureHealth::Reconnecting,
+            backoff_secs,
+            attempts: 0,
+            next_attempt_at: 0.0,
+        }
+    }
+
+    pub fn health(&self) -> CaptureHealth {
+        self.health
+    }
+
+    /// Try to make progress: reopen a failed device if its backoff has
+    /// elapsed, or read a block from a healthy one and push it into `engine`.
+    /// Call as often as convenient, e.g. once per capture-thread iteration.
+    pub fn poll(&mut self, engine: &AudioEngine) {
+        if self.device.is_none() {
+            self.try_reopen();
+            return;
+        }
+
+        let block = self.device.as_mut().and_then(|device| device.read_block());
+        match block {
+            Some(samples) => {
+                engine.push_samples(&samples);
+            }
+            None => {
+                eprintln!("warning: capture device stopped producing blocks, reconnecting");
+                self.device = None;
+                self.health = CaptureHealth::Reconnecting;
+                self.backoff_secs = self.config.initial_backoff_secs;
+                self.attempts = 0;
+                self.next_attempt_at = self.source.now();
+            }
+        }
+    }
+
+    fn try_reopen(&mut self) {
+        if self.health == CaptureHealth::Failed {
+            return;
+        }
+        if self.source.now() < self.next_attempt_at {
+            return;
+        }
+
+        match (self.factory)() {
+            Ok(device) => {
+                self.device = Some(device);
+                self.health = CaptureHealth::Healthy;
+                self.backoff_secs = self.config.initial_backoff_secs;
+                self.attempts = 0;
+            }
+            Err(reason) => {
+                self.attempts += 1;
+                eprintln!("warning: capture device reopen failed ({reason}), retrying in {}s", self.backoff_secs);
+                if self.config.max_attempts != 0 && self.attempts >= self.config.max_attempts {
+                    self.health = CaptureHealth::Failed;
+                    return;
+                }
+                self.health = CaptureHealth::Reconnecting;
+                self.next_attempt_at = self.source.now() + self.backoff_secs;
+                self.backoff_secs = (self.backoff_secs * 2.0).min(self.config.max_backoff_secs);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::AnalysisEngine;
+    use crate::timeline::MockTimeSource;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    struct MockDevice {
+        fails_after: u32,
+        reads: u32,
+    }
+
+    impl CaptureDevice for MockDevice {
+        fn read_block(&mut self) -> Option<Vec<f32>> {
+            self.reads += 1;
+            if self.reads > self.fails_after {
+                None
+            } else {
+                Some(vec![0.0; 64])
+            }
+        }
+    }
+
+    #[test]
+    fn device_failure_reconnects_and_returns_to_healthy() {
+        let source = MockTimeSource::new();
+        let attempts = Arc::new(AtomicU32::new(0));
+        let attempts_clone = attempts.clone();
+
+        let engine = AudioEngine::new(
+            AnalysisEngine::new(48_000),
+            crate::audio::AudioConfig::default(),
+            64,
+        );
+
+        let mut supervisor = CaptureSupervisor::with_source(
+            &source,
+            move || {
+                let n = attempts_clone.fetch_add(1, Ordering::SeqCst);
+                if n == 1 {
+                    // second open (the reconnect attempt) fails once, then succeeds
+                    Err(CaptureError::Open("device busy".to_string()))
+                } else {
+                    Ok(Box::new(MockDevice { fails_after: 1, reads: 0 }) as Box<dyn CaptureDevice>)
+                }
+            },
+            CaptureSupervisorConfig::default(),
+        );
+
+        // First open succeeds.
+        supervisor.poll(&engine);
+        assert_eq!(supervisor.health(), CaptureHealth::Healthy);
+
+        // One good read, then the device fails on its second read.
+        supervisor.poll(&engine);
+        supervisor.poll(&engine);
+        assert_eq!(supervisor.health(), CaptureHealth::Reconnecting);
+
+        // The first reopen attempt runs immediately and fails: still reconnecting.
+        supervisor.poll(&engine);
+        assert_eq!(supervisor.health(), CaptureHealth::Reconnecting);
+
+        // The failing reopen attempt above consumed factory call #1 (Err).
+        // Advance past backoff so the next attempt (#2, Ok) can run.
+        source.advance(1.0);
+        supervisor.poll(&engine);
+        assert_eq!(supervisor.health(), CaptureHealth::Healthy);
+    }
+
+    #[test]
+    fn factory_failure_is_a_matchable_typed_open_error() {
+        let source = MockTimeSource::new();
+        let mut supervisor = CaptureSupervisor::with_source(
+            &source,
+            || Err(CaptureError::Open("permission denied".to_string())),
+            CaptureSupervisorConfig::default(),
+        );
+
+        let engine = AudioEngine::new(
+            AnalysisEngine::new(48_000),
+            crate::audio::AudioConfig::default(),
+            64,
+        );
+
+        supervisor.poll(&engine);
+        match (supervisor.factory)() {
+            Err(CaptureError::Open(reason)) => assert_eq!(reason, "permission denied"),
+            Ok(_) => panic!("expected a typed CaptureError::Open"),
+        }
+    }
+
+    #[test]
+    fn exhausting_max_attempts_reports_failed() {
+        let source = MockTimeSource::new();
+        let mut supervisor = CaptureSupervisor::with_source(
+            &source,
+            || Err(CaptureError::Open("no device present".to_string())),
+            CaptureSupervisorConfig {
+                initial_backoff_secs: 0.1,
+                max_backoff_secs: 1.0,
+                max_attempts: 2,
+            },
+        );
+
+        let engine = AudioEngine::new(
+            AnalysisEngine::new(48_000),
+            crate::audio::AudioConfig::default(),
+            64,
+        );
+
+        supervisor.poll(&engine);
+        assert_eq!(supervisor.health(), CaptureHealth::Reconnecting);
+
+        source.advance(1.0);
+        supervisor.poll(&engine);
+        assert_eq!(supervisor.health(), CaptureHealth::Failed);
+
+        // Once failed, polling again must not retry.
+        source.advance(100.0);
+        supervisor.poll(&engine);
+        assert_eq!(supervisor.health(), CaptureHealth::Failed);
+    }
+}
@@ -0,0 +1,205 @@
+//! Top-level application configuration: scenes, mappings, and assets.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::analysis::AnalysisFrame;
+use crate::assets::AssetStore;
+use crate::mapping::MappingDescriptor;
+use crate::scene::SceneDescriptor;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    DuplicateSceneName(String),
+    UnknownMappingTarget { mapping_source: String, target: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::DuplicateSceneName(name) => {
+                write!(f, "duplicate scene name `{name}`")
+            }
+            ConfigError::UnknownMappingTarget { mapping_source, target } => write!(
+                f,
+                "mapping from `{mapping_source}` targets unknown parameter `{target}`"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Result of checking one item during [`AppConfig::preflight`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreflightItem {
+    pub label: String,
+    pub error: Option<String>,
+}
+
+impl PreflightItem {
+    pub fn ok(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Per-item ok/error report produced by [`AppConfig::preflight`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PreflightReport {
+    pub items: Vec<PreflightItem>,
+}
+
+impl PreflightReport {
+    pub fn all_ok(&self) -> bool {
+        self.items.iter().all(PreflightItem::ok)
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &PreflightItem> {
+        self.items.iter().filter(|item| !item.ok())
+    }
+}
+
+/// A complete show: the scenes it runs, the mappings driving them, and the
+/// mesh assets they reference.
+#[derive(Debug, Clone, Default)]
+pub struct AppConfig {
+    pub scenes: Vec<SceneDescriptor>,
+    pub mappings: Vec<MappingDescriptor>,
+    pub assets: HashMap<String, PathBuf>,
+}
+
+impl AppConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A JSON Schema describing this type's on-disk shape, for editor
+    /// completion and validation of hand-edited preset files. See
+    /// [`crate::schema`].
+    pub fn json_schema() -> String {
+        crate::schema::app_config_schema_string()
+    }
+
+    /// Structural checks that don't require touching the filesystem: no
+    /// duplicate scene names, and every mapping targets a parameter some
+    /// scene actually publishes.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut seen = std::collections::HashSet::new();
+        for scene in &self.scenes {
+            if !seen.insert(scene.name.as_str()) {
+                return Err(ConfigError::DuplicateSceneName(scene.name.clone()));
+            }
+        }
+
+        let known_targets: std::collections::HashSet<&str> = self
+            .scenes
+            .iter()
+            .flat_map(|scene| scene.parameter_names())
+            .collect();
+
+        for mapping in &self.mappings {
+            if !known_targets.contains(mapping.target.as_str()) {
+                return Err(ConfigError::UnknownMappingTarget {
+                    mapping_source: mapping.source.clone(),
+                    target: mapping.target.clone(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Confirm a config is fully wired without opening an audio device: run
+    /// [`Self::validate`], resolve every scene's asset through an
+    /// `AssetStore`, and check that each mapping's `source` names a feature
+    /// of a zeroed frame. A mapping with an `expression` is reported ok
+    /// without checking the expression's feature names, and `sources`
+    /// weights are not checked.
+    pub fn preflight(&self) -> Result<PreflightReport, ConfigError> {
+        self.validate()?;
+
+        let store = AssetStore::from_map(self.assets.clone());
+        let mut items = Vec::new();
+
+        for scene in &self.scenes {
+            if let Some(asset) = &scene.asset {
+                let label = format!("scene `{}` asset `{asset}`", scene.name);
+                let error = store.load_mesh(asset).err().map(|e| e.to_string());
+                items.push(PreflightItem { label, error });
+            }
+        }
+
+        let zeroed = AnalysisFrame::silent(0.0);
+        for mapping in &self.mappings {
+            let label = format!("mapping `{}` -> `{}`", mapping.source, mapping.target);
+            let error = if mapping.expression.is_none() && zeroed.feature(&mapping.source).is_none() {
+                Some(format!("unknown source feature `{}`", mapping.source))
+            } else {
+                None
+            };
+            items.push(PreflightItem { label, error });
+        }
+
+        Ok(PreflightReport { items })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::SceneKind;
+
+    fn sample_config(mesh_path: PathBuf) -> AppConfig {
+        let mut assets = HashMap::new();
+        assets.insert("crystal".to_string(), mesh_path);
+
+        AppConfig {
+            scenes: vec![SceneDescriptor::new(
+                "particles",
+                SceneKind::Particles {
+                    emission: 0.5,
+                    tone: 0.5,
+                    seed: 0,
+                },
+            )
+            .with_asset("crystal")],
+            mappings: vec![MappingDescriptor::new("rms", "particles.emission")],
+            assets,
+        }
+    }
+
+    #[test]
+    fn valid_config_preflights_all_ok() {
+        let mesh_path = std::env::temp_dir().join("music_visualiser_preflight_ok.stl");
+        std::fs::write(&mesh_path, b"solid test\nendsolid test\n").unwrap();
+
+        let config = sample_config(mesh_path.clone());
+        let report = config.preflight().expect("structurally valid config");
+        assert!(report.all_ok(), "{report:?}");
+
+        std::fs::remove_file(mesh_path).ok();
+    }
+
+    #[test]
+    fn json_schema_parses_and_covers_scenes_and_mappings() {
+        let schema: serde_json::Value =
+            serde_json::from_str(&AppConfig::json_schema()).expect("schema should be valid JSON");
+
+        let properties = schema.get("properties").expect("schema should describe properties");
+        assert!(properties.get("scenes").is_some());
+        assert!(properties.get("mappings").is_some());
+    }
+
+    #[test]
+    fn missing_mesh_produces_targeted_failure() {
+        let mesh_path = std::env::temp_dir().join("music_visualiser_preflight_missing.stl");
+        std::fs::remove_file(&mesh_path).ok();
+
+        let config = sample_config(mesh_path);
+        let report = config.preflight().expect("structurally valid config");
+        assert!(!report.all_ok());
+        assert_eq!(report.failures().count(), 1);
+        assert!(report.failures().next().unwrap().label.contains("crystal"));
+    }
+}
@@ -0,0 +1,2700 @@
+//! Turns raw audio blocks into [`AnalysisFrame`]s.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::AnalysisFrame;
+use crate::spectral::{self, SpectralAnalyzer, SpectralError};
+use crate::stereo;
+
+const DEFAULT_FFT_SIZE: usize = 1024;
+/// Default floor for [`AnalysisFrame::rms_db`]: quiet enough that no real
+/// signal above the noise floor sits below it, while still being a finite
+/// number a VU meter can render.
+const DEFAULT_RMS_DB_FLOOR: f32 = -90.0;
+const DEFAULT_TUNING_REFERENCE_HZ: f32 = spectral::DEFAULT_TUNING_REFERENCE_HZ;
+
+/// Hook for injecting externally computed features into [`AnalysisFrame`]
+/// without forking the crate. Registered via
+/// [`AnalysisEngine::register_extractor`], an extractor runs once per block
+/// after all built-in features are finalised, and its output is merged into
+/// [`AnalysisFrame::extra`] by name — from there it's reachable through
+/// [`AnalysisFrame::feature`] like any built-in feature.
+/// `Send` because [`AnalysisEngine`] is driven from behind a
+/// [`std::sync::Mutex`] shared across threads by [`crate::audio::AudioEngine`].
+pub trait FeatureExtractor: Send {
+    /// `spectrum` is the block's magnitude spectrum; `samples` is the raw
+    /// (post-sanitisation) time-domain block.
+    fn extract(&mut self, spectrum: &[f32], samples: &[f32]) -> HashMap<String, f32>;
+}
+
+/// Below this many samples, [`AnalysisEngine::process_block_buffered`] holds
+/// the block internally rather than handing it to [`AnalysisEngine::process_block`].
+const MIN_BUFFERED_BLOCK_SAMPLES: usize = 2;
+
+/// Callback type registered via [`AnalysisEngine::set_frame_hook`].
+type FrameHook = Box<dyn FnMut(&AnalysisFrame) + Send>;
+
+/// Canonical per-block features eligible for calibration.
+const CALIBRATED_FEATURES: &[&str] = &[
+    "rms",
+    "beat_confidence",
+    "low_band_energy",
+    "mid_band_energy",
+    "high_band_energy",
+];
+
+/// Tracks observed min/max per feature during a calibration warm-up, then
+/// rescales subsequent frames into those bounds.
+#[derive(Debug, Clone, Default)]
+struct CalibrationState {
+    remaining_secs: f32,
+    bounds: HashMap<String, (f32, f32)>,
+}
+
+/// Tracks the running average magnitude spectrum during
+/// [`AnalysisEngine::learn_noise_profile`]'s warm-up, then holds the
+/// finished profile subtracted by [`AnalysisEngine::noise_gate_enabled`].
+#[derive(Debug, Clone, Default)]
+struct NoiseProfileState {
+    remaining_secs: f32,
+    sum: Vec<f32>,
+    blocks_seen: u32,
+    profile: Option<Vec<f32>>,
+}
+
+/// Analysis window applied before each block's FFT.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WindowFunction {
+    #[default]
+    Hann,
+}
+
+/// How [`AnalysisEngine::compute_spectral_flux`] accumulates the bin-by-bin
+/// change in the magnitude spectrum since the previous block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FluxMode {
+    /// Only positive change counts, standard for onset detection: a spectrum
+    /// that's merely losing energy shouldn't register as an onset.
+    #[default]
+    PositiveL1,
+    /// Absolute change, so a spectrum that's fading out registers the same
+    /// as one growing louder — useful for measuring overall spectral change
+    /// rather than onsets specifically.
+    L1,
+    /// Root-mean-square change, penalising a few large bin changes more than
+    /// the same total change spread evenly across bins.
+    L2,
+}
+
+/// Strategy used to estimate tempo and beat onsets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TempoEstimator {
+    /// The onset-ratio detector used by [`BarTrackerConfig`].
+    #[default]
+    OnsetRatio,
+}
+
+/// Error returned by [`AnalysisEngineBuilder::build`] when the requested
+/// configuration can't be satisfied.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EngineError {
+    InvalidFftSize(usize),
+    InvalidSampleRate(u32),
+    InvalidBandCrossovers { low_hz: f32, high_hz: f32 },
+}
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EngineError::InvalidFftSize(size) => {
+                write!(f, "fft_size {size} is not a power of two")
+            }
+            EngineError::InvalidSampleRate(rate) => write!(f, "sample_rate {rate} must be > 0"),
+            EngineError::InvalidBandCrossovers { low_hz, high_hz } => write!(
+                f,
+                "band crossovers must satisfy 0 < low ({low_hz}) < high ({high_hz})"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for EngineError {}
+
+/// Chainable configuration for [`AnalysisEngine`], validated once in
+/// [`Self::build`] rather than field-by-field at each setter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnalysisEngineBuilder {
+    sample_rate: u32,
+    fft_size: usize,
+    window: WindowFunction,
+    band_crossovers: (f32, f32),
+    tempo_estimator: TempoEstimator,
+    flux_mode: FluxMode,
+    rms_db_floor: f32,
+    enable_agc: bool,
+    pad_to_efficient_fft_size: bool,
+    tuning_reference_hz: f32,
+}
+
+impl AnalysisEngineBuilder {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            fft_size: DEFAULT_FFT_SIZE,
+            window: WindowFunction::default(),
+            band_crossovers: (250.0, 2_000.0),
+            tempo_estimator: TempoEstimator::default(),
+            flux_mode: FluxMode::default(),
+            rms_db_floor: DEFAULT_RMS_DB_FLOOR,
+            enable_agc: false,
+            pad_to_efficient_fft_size: false,
+            tuning_reference_hz: DEFAULT_TUNING_REFERENCE_HZ,
+        }
+    }
+
+    pub fn sample_rate(mut self, sample_rate: u32) -> Self {
+        self.sample_rate = sample_rate;
+        self
+    }
+
+    pub fn fft_size(mut self, fft_size: usize) -> Self {
+        self.fft_size = fft_size;
+        self
+    }
+
+    pub fn window(mut self, window: WindowFunction) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Set the low/high crossover frequencies (Hz) separating the low, mid,
+    /// and high energy bands.
+    pub fn bands(mut self, low_hz: f32, high_hz: f32) -> Self {
+        self.band_crossovers = (low_hz, high_hz);
+        self
+    }
+
+    pub fn tempo_estimator(mut self, tempo_estimator: TempoEstimator) -> Self {
+        self.tempo_estimator = tempo_estimator;
+        self
+    }
+
+    pub fn flux_mode(mut self, flux_mode: FluxMode) -> Self {
+        self.flux_mode = flux_mode;
+        self
+    }
+
+    /// Floor, in dB, that [`AnalysisFrame::rms_db`] reports for silence
+    /// instead of `-inf`. Defaults to [`DEFAULT_RMS_DB_FLOOR`].
+    pub fn rms_db_floor(mut self, rms_db_floor: f32) -> Self {
+        self.rms_db_floor = rms_db_floor;
+        self
+    }
+
+    pub fn enable_agc(mut self, enable_agc: bool) -> Self {
+        self.enable_agc = enable_agc;
+        self
+    }
+
+    /// When set, a `fft_size` that isn't a power of two is zero-padded up to
+    /// the next one instead of making [`Self::build`] fail. `realfft` copes
+    /// with any size, but a non-power-of-two size (worst case, a large
+    /// prime) can be dramatically slower to plan and run, so the default is
+    /// to reject it outright rather than silently eat the cost. A padded
+    /// size changes `bin_hz` (`sample_rate / fft_size`); every feature that
+    /// derives frequencies from the spectrum already reads the size back off
+    /// [`crate::spectral::SpectralAnalyzer::fft_size`], so this stays
+    /// consistent automatically.
+    pub fn pad_to_efficient_fft_size(mut self, pad_to_efficient_fft_size: bool) -> Self {
+        self.pad_to_efficient_fft_size = pad_to_efficient_fft_size;
+        self
+    }
+
+    /// Reference tuning frequency (Hz) that chroma binning treats as
+    /// concert A. Defaults to [`DEFAULT_TUNING_REFERENCE_HZ`]; not every
+    /// track uses standard A440 tuning (baroque and some electronic music
+    /// use 432 or 442), and binning against the wrong reference smears
+    /// energy across adjacent pitch classes instead of concentrating it in
+    /// one.
+    pub fn tuning_reference_hz(mut self, tuning_reference_hz: f32) -> Self {
+        self.tuning_reference_hz = tuning_reference_hz;
+        self
+    }
+
+    pub fn build(self) -> Result<AnalysisEngine, EngineError> {
+        if self.sample_rate == 0 {
+            return Err(EngineError::InvalidSampleRate(self.sample_rate));
+        }
+        let fft_size = if self.fft_size >= 2 && !self.fft_size.is_power_of_two() && self.pad_to_efficient_fft_size {
+            let padded = self.fft_size.next_power_of_two();
+            eprintln!(
+                "warning: fft_size {} is not a power of two, padding to {padded}",
+                self.fft_size
+            );
+            padded
+        } else {
+            self.fft_size
+        };
+        if fft_size < 2 || !fft_size.is_power_of_two() {
+            return Err(EngineError::InvalidFftSize(fft_size));
+        }
+        let (low_hz, high_hz) = self.band_crossovers;
+        let nyquist = self.sample_rate as f32 / 2.0;
+        if !(low_hz > 0.0 && low_hz < high_hz && high_hz < nyquist) {
+            return Err(EngineError::InvalidBandCrossovers { low_hz, high_hz });
+        }
+
+        let mut engine = AnalysisEngine::new(self.sample_rate);
+        engine.window = self.window;
+        engine.band_crossovers = self.band_crossovers;
+        engine.tempo_estimator = self.tempo_estimator;
+        engine.flux_mode = self.flux_mode;
+        engine.rms_db_floor = self.rms_db_floor;
+        engine.tuning_reference_hz = self.tuning_reference_hz;
+        engine.spectral = SpectralAnalyzer::new(fft_size);
+        if self.enable_agc {
+            engine.agc = Some(AgcConfig::default());
+        }
+        Ok(engine)
+    }
+}
+
+/// Automatic gain control settings: tracks a slow-moving RMS envelope and
+/// scales incoming blocks toward `target_rms` before feature extraction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AgcConfig {
+    pub target_rms: f32,
+    /// Envelope follower coefficient used while the block is louder than the
+    /// envelope, in `0..1`.
+    pub attack: f32,
+    /// Envelope follower coefficient used while the block is quieter than
+    /// the envelope, in `0..1`.
+    pub release: f32,
+    /// Upper bound on the applied gain, to avoid amplifying near-silence.
+    pub max_gain: f32,
+}
+
+impl Default for AgcConfig {
+    fn default() -> Self {
+        Self {
+            target_rms: 0.2,
+            attack: 0.5,
+            release: 0.05,
+            max_gain: 8.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+struct AgcState {
+    envelope: f32,
+}
+
+/// Attack/release ballistics for [`AnalysisFrame::rms_envelope`]: a VU-meter
+/// look where the reading snaps up immediately on a transient but decays
+/// slowly afterward, unlike raw per-block `rms`. See [`AnalysisEngine::rms_envelope`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RmsEnvelopeConfig {
+    /// Coefficient applied while the block's `rms` is above the envelope, in
+    /// `0..1`. Close to `1.0` for a near-instant rise on transients.
+    pub attack: f32,
+    /// Coefficient applied while the block's `rms` is below the envelope, in
+    /// `0..1`. Close to `0.0` for a slow, gravity-like fall.
+    pub release: f32,
+}
+
+impl Default for RmsEnvelopeConfig {
+    fn default() -> Self {
+        Self { attack: 0.9, release: 0.05 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+struct RmsEnvelopeState {
+    envelope: f32,
+}
+
+/// Attack/decay ballistics applied to the band energy vector, giving a
+/// bar-graph spectrum visual the classic instant-rise, gravity-fall look
+/// instead of tracking raw per-block energy directly. See
+/// [`AnalysisFrame::low_band_energy_ballistic`] and its siblings, and
+/// [`AnalysisFrame::low_band_energy_peak`] and its siblings for the
+/// peak-hold markers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BandBallisticsConfig {
+    /// Amount a ballistic level or peak-hold marker falls per second once
+    /// the raw energy drops below it, e.g. `2.0` falls from full scale to
+    /// zero in half a second.
+    pub fall_rate: f32,
+    /// How long a peak-hold marker lingers at its peak before it starts
+    /// falling too, at `fall_rate`. `0.0` makes it fall immediately, same as
+    /// the ballistic level.
+    pub peak_hold_secs: f32,
+}
+
+impl Default for BandBallisticsConfig {
+    fn default() -> Self {
+        Self { fall_rate: 2.0, peak_hold_secs: 1.0 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+struct BandBallisticsState {
+    levels: [f32; 3],
+    peaks: [f32; 3],
+    peak_ages: [f32; 3],
+}
+
+/// Silence / activity gate settings: suppresses reactive output during
+/// quiet stretches between songs instead of chasing noise-floor energy.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SilenceGateConfig {
+    /// RMS below this level counts as quiet.
+    pub threshold: f32,
+    /// How long the block must stay quiet before `activity` flips to false.
+    pub hold_secs: f32,
+}
+
+impl Default for SilenceGateConfig {
+    fn default() -> Self {
+        Self {
+            threshold: 0.01,
+            hold_secs: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+struct SilenceGateState {
+    quiet_for: f32,
+}
+
+/// Groups detected beats into bars so scenes can key off downbeats instead
+/// of every beat.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BarTrackerConfig {
+    /// Beats per bar.
+    pub time_signature: u32,
+    /// A block counts as a beat onset once its RMS exceeds the previous
+    /// block's by this factor.
+    pub onset_ratio: f32,
+    /// Minimum time in seconds between counted beats, so a burst of loud
+    /// blocks can't register faster than this implies. `0.0` (the default)
+    /// applies no cap. Since this effectively caps detectable BPM, raise it
+    /// to reject spurious double-triggers on a slow genre, or keep it low
+    /// to track a fast one.
+    pub min_beat_interval: f32,
+}
+
+impl Default for BarTrackerConfig {
+    fn default() -> Self {
+        Self {
+            time_signature: 4,
+            onset_ratio: 1.5,
+            min_beat_interval: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+struct BarTrackerState {
+    previous_rms: f32,
+    beat_count: u32,
+    time_since_last_beat: f32,
+    /// Seconds between the two most recent detected beats, used to estimate
+    /// [`AnalysisFrame::beat_phase`]. `None` until a second beat lands.
+    last_beat_interval: Option<f32>,
+}
+
+/// Runs a long-window FFT for low-frequency band energy alongside a
+/// short-window FFT for higher bands, trading the single FFT's one-size-
+/// fits-all time/frequency tradeoff for one tuned per band.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DualResolutionConfig {
+    pub long_fft_size: usize,
+    pub short_fft_size: usize,
+    /// Not read by band energy computation: the split between the long and
+    /// short FFT is the engine's low band crossover (see
+    /// [`AnalysisEngine::band_crossovers`]), with the low band taken from the
+    /// long FFT and the mid/high bands from the short one.
+    pub crossover_hz: f32,
+}
+
+/// Single-pole pre-emphasis high-pass settings: `y[n] = x[n] - coefficient *
+/// x[n-1]`, boosting high frequencies that a bass-heavy signal would
+/// otherwise mask in the spectral centroid and band energies.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PreEmphasisConfig {
+    pub coefficient: f32,
+}
+
+impl Default for PreEmphasisConfig {
+    fn default() -> Self {
+        Self { coefficient: 0.95 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+struct PreEmphasisState {
+    previous_sample: f32,
+}
+
+/// Spectral whitening settings: divides each magnitude-spectrum bin by a
+/// slowly-adapting per-bin running max before computing spectral flux, so
+/// flux reflects relative change rather than absolute loudness. Only the
+/// flux computation is whitened; band energies always use the raw spectrum.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpectralWhiteningConfig {
+    /// Per-block retention of each bin's running max, in `0..1`. Lower
+    /// values forget a loud section's peak faster, making quiet onsets
+    /// after it resurface sooner.
+    pub decay: f32,
+}
+
+impl Default for SpectralWhiteningConfig {
+    fn default() -> Self {
+        Self { decay: 0.9 }
+    }
+}
+
+/// Baseline subtraction for spectral flux: tracks a moving average of
+/// recent (scalar, already whitened if applicable) flux values and
+/// subtracts it before the final zero-floor, so a sustained low flux
+/// (e.g. a held chord) settles near zero and only above-baseline change
+/// registers. Distinct from [`SpectralWhiteningConfig`], which operates
+/// per-bin on the spectrum rather than on the scalar flux value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpectralFluxBaselineConfig {
+    /// Number of recent flux values averaged to form the baseline. Larger
+    /// windows track slower drift; smaller windows adapt faster but risk
+    /// absorbing genuine onsets into the baseline.
+    pub window: usize,
+}
+
+impl Default for SpectralFluxBaselineConfig {
+    fn default() -> Self {
+        Self { window: 30 }
+    }
+}
+
+/// Log-frequency (constant-Q-like) rebinning of the magnitude spectrum,
+/// exposed on [`AnalysisFrame::log_spectrum`] instead of feeding
+/// centroid/energy features directly. See
+/// [`crate::spectral::log_frequency_spectrum`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LogSpectrumConfig {
+    pub bins_per_octave: u32,
+    /// Lowest frequency (Hz) the grid covers; bins below it are dropped.
+    pub min_hz: f32,
+}
+
+impl Default for LogSpectrumConfig {
+    fn default() -> Self {
+        Self {
+            bins_per_octave: 12,
+            min_hz: 27.5, // A0
+        }
+    }
+}
+
+/// Retains a rolling window of magnitude spectra for a waterfall
+/// spectrogram visual, which would otherwise be discarded every block. See
+/// [`AnalysisEngine::spectrogram`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpectrogramConfig {
+    /// Number of most recent magnitude columns to retain.
+    pub depth: usize,
+}
+
+impl Default for SpectrogramConfig {
+    fn default() -> Self {
+        Self { depth: 64 }
+    }
+}
+
+/// Suppresses reported [`AnalysisFrame::spectral_flux`] (flagging the frame
+/// [`AnalysisFrame::warming_up`] instead) for the first few blocks, so the
+/// cold-start jump from having no prior spectrum to compare against isn't
+/// reported as a spurious onset.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FluxPrimingConfig {
+    /// Number of leading blocks to flag as warming up.
+    pub warmup_blocks: u32,
+}
+
+impl Default for FluxPrimingConfig {
+    fn default() -> Self {
+        Self { warmup_blocks: 1 }
+    }
+}
+
+/// Exponential smoothing of the band energy vector across blocks, trading
+/// responsiveness for reduced block-to-block flicker in per-band visuals —
+/// a cheaper alternative to overlapping analysis windows. See
+/// [`AnalysisFrame::low_band_energy_smoothed`] and its siblings.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BandSmoothingConfig {
+    /// Exponential retention factor in `0..1`. `0.0` disables smoothing
+    /// (the smoothed value always equals the raw one); values closer to
+    /// `1.0` retain more of the previous block at the cost of lag.
+    pub coefficient: f32,
+}
+
+impl Default for BandSmoothingConfig {
+    fn default() -> Self {
+        Self { coefficient: 0.5 }
+    }
+}
+
+fn rms(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    let sum_sq: f32 = samples.iter().map(|s| s * s).sum();
+    (sum_sq / samples.len() as f32).sqrt()
+}
+
+/// Converts linear `rms` to dBFS, floored at `floor_db` instead of `-inf`
+/// for silence.
+fn rms_to_db(rms: f32, floor_db: f32) -> f32 {
+    if rms <= 0.0 {
+        floor_db
+    } else {
+        (20.0 * rms.log10()).max(floor_db)
+    }
+}
+
+/// Logs that an analysis field went non-finite, routed through
+/// `tracing::warn!` when the `tracing` feature is enabled (the default) so a
+/// subscriber can filter, sample, or rate-limit it instead of it flooding
+/// stderr unconditionally on the real-time analysis thread. Falls back to a
+/// plain `eprintln!` only when that feature is off, since there's then no
+/// other way to surface it.
+fn warn_non_finite(field: &str, substituted: &str) {
+    #[cfg(feature = "tracing")]
+    tracing::warn!(field, "analysis frame field was non-finite, substituting {substituted}");
+    #[cfg(not(feature = "tracing"))]
+    eprintln!("warning: analysis frame field `{field}` was non-finite, substituting {substituted}");
+}
+
+/// Last line of defense against a non-finite value reaching an emitted
+/// [`AnalysisFrame`]: substitutes `0.0` and logs, since a `NaN` parameter
+/// would otherwise freeze every scene driven by it.
+fn finite_or_warn(value: f32, field: &str) -> f32 {
+    if value.is_finite() {
+        value
+    } else {
+        warn_non_finite(field, "0.0");
+        0.0
+    }
+}
+
+/// Sanitizes one channel of a running EMA after blending, recovering with
+/// `raw` (this block's pre-smoothing input) instead of `0.0`. Unlike
+/// [`finite_or_warn`], the substituted value is fed back into the next
+/// block's blend: without this, an EMA that goes non-finite once would stay
+/// poisoned forever, since every later block blends against the stored
+/// value.
+fn recover_ema(value: f32, raw: f32, field: &str) -> f32 {
+    if value.is_finite() {
+        value
+    } else {
+        warn_non_finite(field, "its raw pre-smoothing value");
+        raw
+    }
+}
+
+/// A serializable snapshot of [`AnalysisEngine`]'s adaptive state, produced
+/// by [`AnalysisEngine::export_state`] and restored with
+/// [`AnalysisEngine::import_state`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnalysisEngineState {
+    agc_state: AgcState,
+    silence_gate_state: SilenceGateState,
+    bar_tracker_state: BarTrackerState,
+    pre_emphasis_state: PreEmphasisState,
+    whitening_running_max: Option<Vec<f32>>,
+    previous_flux_spectrum: Option<Vec<f32>>,
+    flux_baseline_history: std::collections::VecDeque<f32>,
+    smoothed_bands: (f32, f32, f32),
+    rms_envelope_state: RmsEnvelopeState,
+    band_ballistics_state: BandBallisticsState,
+}
+
+/// Converts successive raw audio blocks into [`AnalysisFrame`]s, advancing
+/// an internal timestamp by `block_len / sample_rate` each call.
+pub struct AnalysisEngine {
+    sample_rate: u32,
+    pub agc: Option<AgcConfig>,
+    agc_state: AgcState,
+    pub silence_gate: Option<SilenceGateConfig>,
+    silence_gate_state: SilenceGateState,
+    pub bar_tracker: Option<BarTrackerConfig>,
+    bar_tracker_state: BarTrackerState,
+    /// Pre-emphasis applied to the signal fed to spectral analysis. Raw
+    /// time-domain features such as `rms` always use the un-emphasised
+    /// signal.
+    pub pre_emphasis: Option<PreEmphasisConfig>,
+    pre_emphasis_state: PreEmphasisState,
+    pub spectral_whitening: Option<SpectralWhiteningConfig>,
+    whitening_running_max: Option<Vec<f32>>,
+    previous_flux_spectrum: Option<Vec<f32>>,
+    pub spectral_flux_baseline: Option<SpectralFluxBaselineConfig>,
+    flux_baseline_history: std::collections::VecDeque<f32>,
+    pub log_spectrum: Option<LogSpectrumConfig>,
+    pub flux_priming: Option<FluxPrimingConfig>,
+    flux_priming_blocks_seen: u32,
+    pub spectrogram: Option<SpectrogramConfig>,
+    spectrogram_history: std::collections::VecDeque<Vec<f32>>,
+    pub band_smoothing: Option<BandSmoothingConfig>,
+    smoothed_bands: (f32, f32, f32),
+    pub rms_envelope: Option<RmsEnvelopeConfig>,
+    rms_envelope_state: RmsEnvelopeState,
+    pub band_ballistics: Option<BandBallisticsConfig>,
+    band_ballistics_state: BandBallisticsState,
+    window: WindowFunction,
+    band_crossovers: (f32, f32),
+    tempo_estimator: TempoEstimator,
+    flux_mode: FluxMode,
+    rms_db_floor: f32,
+    tuning_reference_hz: f32,
+    spectral: SpectralAnalyzer,
+    dual_resolution: Option<DualResolutionConfig>,
+    long_spectral: Option<SpectralAnalyzer>,
+    short_spectral: Option<SpectralAnalyzer>,
+    calibration: Option<CalibrationState>,
+    noise_profile: Option<NoiseProfileState>,
+    /// Subtract the learned noise-floor profile (see
+    /// [`Self::learn_noise_profile`]) from the magnitude spectrum before
+    /// band energies are computed, flooring each bin at zero. A no-op until
+    /// a profile has finished learning. Off by default.
+    pub noise_gate_enabled: bool,
+    timestamp: f64,
+    /// Count of input samples replaced by [`Self::sanitize_samples`] because
+    /// they were `NaN` or infinite.
+    non_finite_input_samples: u64,
+    /// Samples accumulated by [`Self::process_block_buffered`] that were too
+    /// few to process on their own.
+    pending_samples: Vec<f32>,
+    /// Target interval between frames emitted by
+    /// [`Self::process_block_hopped`], in seconds, independent of the
+    /// caller's audio callback size. `None` analyses every block
+    /// immediately, same as [`Self::process_block`].
+    pub hop_interval: Option<f32>,
+    /// Samples accumulated by [`Self::process_block_hopped`] towards the
+    /// next `hop_interval`-sized block.
+    hop_pending_samples: Vec<f32>,
+    /// Registered via [`Self::register_extractor`]. Not cloned: see the
+    /// manual [`Clone`] impl below.
+    extractors: Vec<Box<dyn FeatureExtractor>>,
+    /// Registered via [`Self::set_frame_hook`]. Not cloned: see the manual
+    /// [`Clone`] impl below.
+    frame_hook: Option<FrameHook>,
+    /// Blocks processed so far, recorded on [`Self::process_block`]'s
+    /// tracing span. Only exists when the `tracing` feature is enabled.
+    #[cfg(feature = "tracing")]
+    block_index: u64,
+}
+
+/// Manual because `Box<dyn FeatureExtractor>` isn't [`Clone`]; a cloned
+/// engine starts with no extractors registered, same as a fresh
+/// [`AnalysisEngine::new`].
+impl Clone for AnalysisEngine {
+    fn clone(&self) -> Self {
+        Self {
+            sample_rate: self.sample_rate,
+            agc: self.agc,
+            agc_state: self.agc_state,
+            silence_gate: self.silence_gate,
+            silence_gate_state: self.silence_gate_state,
+            bar_tracker: self.bar_tracker,
+            bar_tracker_state: self.bar_tracker_state,
+            pre_emphasis: self.pre_emphasis,
+            pre_emphasis_state: self.pre_emphasis_state,
+            spectral_whitening: self.spectral_whitening,
+            whitening_running_max: self.whitening_running_max.clone(),
+            previous_flux_spectrum: self.previous_flux_spectrum.clone(),
+            spectral_flux_baseline: self.spectral_flux_baseline,
+            flux_baseline_history: self.flux_baseline_history.clone(),
+            log_spectrum: self.log_spectrum,
+            flux_priming: self.flux_priming,
+            flux_priming_blocks_seen: self.flux_priming_blocks_seen,
+            spectrogram: self.spectrogram,
+            spectrogram_history: self.spectrogram_history.clone(),
+            band_smoothing: self.band_smoothing,
+            smoothed_bands: self.smoothed_bands,
+            rms_envelope: self.rms_envelope,
+            rms_envelope_state: self.rms_envelope_state,
+            band_ballistics: self.band_ballistics,
+            band_ballistics_state: self.band_ballistics_state,
+            window: self.window,
+            band_crossovers: self.band_crossovers,
+            tempo_estimator: self.tempo_estimator,
+            flux_mode: self.flux_mode,
+            rms_db_floor: self.rms_db_floor,
+            tuning_reference_hz: self.tuning_reference_hz,
+            spectral: self.spectral.clone(),
+            dual_resolution: self.dual_resolution,
+            long_spectral: self.long_spectral.clone(),
+            short_spectral: self.short_spectral.clone(),
+            calibration: self.calibration.clone(),
+            noise_profile: self.noise_profile.clone(),
+            noise_gate_enabled: self.noise_gate_enabled,
+            timestamp: self.timestamp,
+            non_finite_input_samples: self.non_finite_input_samples,
+            pending_samples: self.pending_samples.clone(),
+            hop_interval: self.hop_interval,
+            hop_pending_samples: self.hop_pending_samples.clone(),
+            extractors: Vec::new(),
+            frame_hook: None,
+            #[cfg(feature = "tracing")]
+            block_index: self.block_index,
+        }
+    }
+}
+
+impl AnalysisEngine {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            agc: None,
+            agc_state: AgcState::default(),
+            silence_gate: None,
+            silence_gate_state: SilenceGateState::default(),
+            bar_tracker: None,
+            bar_tracker_state: BarTrackerState::default(),
+            pre_emphasis: None,
+            pre_emphasis_state: PreEmphasisState::default(),
+            spectral_whitening: None,
+            whitening_running_max: None,
+            previous_flux_spectrum: None,
+            spectral_flux_baseline: None,
+            flux_baseline_history: std::collections::VecDeque::new(),
+            log_spectrum: None,
+            flux_priming: None,
+            flux_priming_blocks_seen: 0,
+            spectrogram: None,
+            spectrogram_history: std::collections::VecDeque::new(),
+            band_smoothing: None,
+            smoothed_bands: (0.0, 0.0, 0.0),
+            rms_envelope: None,
+            rms_envelope_state: RmsEnvelopeState::default(),
+            band_ballistics: None,
+            band_ballistics_state: BandBallisticsState::default(),
+            window: WindowFunction::default(),
+            band_crossovers: (250.0, 2_000.0),
+            tempo_estimator: TempoEstimator::default(),
+            flux_mode: FluxMode::default(),
+            rms_db_floor: DEFAULT_RMS_DB_FLOOR,
+            tuning_reference_hz: DEFAULT_TUNING_REFERENCE_HZ,
+            spectral: SpectralAnalyzer::new(DEFAULT_FFT_SIZE),
+            dual_resolution: None,
+            long_spectral: None,
+            short_spectral: None,
+            calibration: None,
+            noise_profile: None,
+            noise_gate_enabled: false,
+            timestamp: 0.0,
+            non_finite_input_samples: 0,
+            pending_samples: Vec::new(),
+            hop_interval: None,
+            hop_pending_samples: Vec::new(),
+            extractors: Vec::new(),
+            frame_hook: None,
+            #[cfg(feature = "tracing")]
+            block_index: 0,
+        }
+    }
+
+    /// Start building an engine with validated settings. See
+    /// [`AnalysisEngineBuilder`].
+    pub fn builder(sample_rate: u32) -> AnalysisEngineBuilder {
+        AnalysisEngineBuilder::new(sample_rate)
+    }
+
+    /// Register a [`FeatureExtractor`], run once per block after all
+    /// built-in features are finalised. Extractors run in registration
+    /// order; a later extractor overwrites an earlier one's value for the
+    /// same feature name.
+    pub fn register_extractor(&mut self, extractor: impl FeatureExtractor + 'static) {
+        self.extractors.push(Box::new(extractor));
+    }
+
+    /// Register a callback invoked synchronously in [`Self::process_block`]
+    /// with each frame just after it's built, before the frame is returned
+    /// to the caller. Useful for a zero-copy consumer (e.g. a live meter)
+    /// that wants every frame without going through
+    /// [`crate::history::AnalysisHandle`] or a channel. Replaces any
+    /// previously registered hook.
+    ///
+    /// The hook must not call back into `process_block` (or
+    /// `process_block_buffered`/`process_block_hopped`) on this engine:
+    /// nothing guards against that re-entrancy, and doing so will recurse.
+    pub fn set_frame_hook(&mut self, hook: impl FnMut(&AnalysisFrame) + Send + 'static) {
+        self.frame_hook = Some(Box::new(hook));
+    }
+
+    /// Remove any hook registered via [`Self::set_frame_hook`].
+    pub fn clear_frame_hook(&mut self) {
+        self.frame_hook = None;
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn window(&self) -> WindowFunction {
+        self.window
+    }
+
+    pub fn band_crossovers(&self) -> (f32, f32) {
+        self.band_crossovers
+    }
+
+    /// Reconfigure the low/high crossover frequencies (Hz) separating the
+    /// low, mid, and high energy bands without rebuilding the engine, e.g.
+    /// to retune the split for a different genre mid-session. Errors with
+    /// the same [`EngineError::InvalidBandCrossovers`] check
+    /// [`AnalysisEngineBuilder::build`] applies at construction time.
+    pub fn set_band_crossovers(&mut self, low_hz: f32, high_hz: f32) -> Result<(), EngineError> {
+        let nyquist = self.sample_rate as f32 / 2.0;
+        if !(low_hz > 0.0 && low_hz < high_hz && high_hz < nyquist) {
+            return Err(EngineError::InvalidBandCrossovers { low_hz, high_hz });
+        }
+        self.band_crossovers = (low_hz, high_hz);
+        Ok(())
+    }
+
+    pub fn flux_mode(&self) -> FluxMode {
+        self.flux_mode
+    }
+
+    pub fn rms_db_floor(&self) -> f32 {
+        self.rms_db_floor
+    }
+
+    pub fn tuning_reference_hz(&self) -> f32 {
+        self.tuning_reference_hz
+    }
+
+    pub fn tempo_estimator(&self) -> TempoEstimator {
+        self.tempo_estimator
+    }
+
+    pub fn fft_size(&self) -> usize {
+        self.spectral.fft_size()
+    }
+
+    pub fn dual_resolution(&self) -> Option<DualResolutionConfig> {
+        self.dual_resolution
+    }
+
+    /// Running count of input samples that were `NaN` or infinite and got
+    /// replaced with `0.0` by [`Self::process_block`].
+    pub fn non_finite_input_samples(&self) -> u64 {
+        self.non_finite_input_samples
+    }
+
+    /// Snapshot every adaptive, block-to-block piece of state (AGC envelope,
+    /// silence-gate hold timer, beat/bar tracking, pre-emphasis history,
+    /// spectral whitening and flux baseline history) so it can be restored
+    /// with [`Self::import_state`]. Useful for looping file playback, where
+    /// resetting this state at the loop point would otherwise cause a flux
+    /// spike and a tempo glitch; also serializable to save alongside a
+    /// recording.
+    pub fn export_state(&self) -> AnalysisEngineState {
+        AnalysisEngineState {
+            agc_state: self.agc_state,
+            silence_gate_state: self.silence_gate_state,
+            bar_tracker_state: self.bar_tracker_state,
+            pre_emphasis_state: self.pre_emphasis_state,
+            whitening_running_max: self.whitening_running_max.clone(),
+            previous_flux_spectrum: self.previous_flux_spectrum.clone(),
+            flux_baseline_history: self.flux_baseline_history.clone(),
+            smoothed_bands: self.smoothed_bands,
+            rms_envelope_state: self.rms_envelope_state,
+            band_ballistics_state: self.band_ballistics_state,
+        }
+    }
+
+    /// Restore adaptive state previously captured with [`Self::export_state`].
+    /// Configuration (`agc`, `bar_tracker`, and so on) is untouched; only the
+    /// running state each feeds on is replaced.
+    pub fn import_state(&mut self, state: AnalysisEngineState) {
+        self.agc_state = state.agc_state;
+        self.silence_gate_state = state.silence_gate_state;
+        self.bar_tracker_state = state.bar_tracker_state;
+        self.pre_emphasis_state = state.pre_emphasis_state;
+        self.whitening_running_max = state.whitening_running_max;
+        self.previous_flux_spectrum = state.previous_flux_spectrum;
+        self.flux_baseline_history = state.flux_baseline_history;
+        self.smoothed_bands = state.smoothed_bands;
+        self.rms_envelope_state = state.rms_envelope_state;
+        self.band_ballistics_state = state.band_ballistics_state;
+    }
+
+    /// Reset every adaptive/running field back to a freshly-constructed
+    /// engine's, without touching configuration (`agc`, `bar_tracker`, and so
+    /// on). Useful when reconfiguring an engine in place, e.g.
+    /// [`crate::audio::AudioEngine::set_mode`], where callers expect the same
+    /// clean slate as constructing a new engine but without losing the
+    /// engine's identity or configuration. A learned noise-floor profile and
+    /// calibration bounds are kept.
+    pub fn reset_adaptive_state(&mut self) {
+        self.import_state(AnalysisEngineState {
+            agc_state: AgcState::default(),
+            silence_gate_state: SilenceGateState::default(),
+            bar_tracker_state: BarTrackerState::default(),
+            pre_emphasis_state: PreEmphasisState::default(),
+            whitening_running_max: None,
+            previous_flux_spectrum: None,
+            flux_baseline_history: std::collections::VecDeque::new(),
+            smoothed_bands: (0.0, 0.0, 0.0),
+            rms_envelope_state: RmsEnvelopeState::default(),
+            band_ballistics_state: BandBallisticsState::default(),
+        });
+        self.flux_priming_blocks_seen = 0;
+        self.spectrogram_history.clear();
+        self.pending_samples.clear();
+        self.hop_pending_samples.clear();
+        self.timestamp = 0.0;
+    }
+
+    /// Replace any non-finite sample with `0.0` before analysis, so a
+    /// denormalized read from a flaky driver can't propagate into every
+    /// downstream feature. Counts replacements into
+    /// [`Self::non_finite_input_samples`].
+    fn sanitize_samples(&mut self, samples: &[f32]) -> Vec<f32> {
+        samples
+            .iter()
+            .map(|&sample| {
+                if sample.is_finite() {
+                    sample
+                } else {
+                    self.non_finite_input_samples += 1;
+                    0.0
+                }
+            })
+            .collect()
+    }
+
+    /// Enable or disable the dual-resolution FFT path. The long/short FFT
+    /// plans are built here and cached for reuse, rather than replanned on
+    /// every block; calling this again with the same size reuses the
+    /// existing plan instead of re-planning it. Unlike [`AnalysisEngineBuilder::fft_size`],
+    /// which is validated once in [`AnalysisEngineBuilder::build`], these
+    /// sizes come from a config set after construction, so this validates
+    /// them itself and reports [`EngineError::InvalidFftSize`] instead of
+    /// letting `realfft` panic on a degenerate size.
+    pub fn set_dual_resolution(&mut self, config: Option<DualResolutionConfig>) -> Result<(), EngineError> {
+        let long_spectral = match config {
+            Some(c) => Some(Self::cached_spectral(self.long_spectral.as_ref(), c.long_fft_size)?),
+            None => None,
+        };
+        let short_spectral = match config {
+            Some(c) => Some(Self::cached_spectral(self.short_spectral.as_ref(), c.short_fft_size)?),
+            None => None,
+        };
+        self.long_spectral = long_spectral;
+        self.short_spectral = short_spectral;
+        self.dual_resolution = config;
+        Ok(())
+    }
+
+    /// Reuses `existing` if it's already planned for `fft_size`, otherwise
+    /// plans a new one. See [`Self::set_dual_resolution`].
+    fn cached_spectral(existing: Option<&SpectralAnalyzer>, fft_size: usize) -> Result<SpectralAnalyzer, EngineError> {
+        if let Some(existing) = existing {
+            if existing.fft_size() == fft_size {
+                return Ok(existing.clone());
+            }
+        }
+        SpectralAnalyzer::try_new(fft_size).map_err(|SpectralError::InvalidFftSize(size)| EngineError::InvalidFftSize(size))
+    }
+
+    /// Update the silence-gate hold timer for a block and report whether the
+    /// engine currently considers itself active.
+    fn update_activity(&mut self, cfg: SilenceGateConfig, input_rms: f32, block_secs: f32) -> bool {
+        if input_rms < cfg.threshold {
+            self.silence_gate_state.quiet_for += block_secs;
+        } else {
+            self.silence_gate_state.quiet_for = 0.0;
+        }
+        self.silence_gate_state.quiet_for < cfg.hold_secs
+    }
+
+    /// Start (or restart) a calibration warm-up: for `duration_secs` of
+    /// incoming blocks, record each calibrated feature's observed min/max
+    /// instead of leaving it raw. Once the warm-up elapses, subsequent
+    /// frames have those features rescaled into `0..1` against the bounds
+    /// seen during warm-up.
+    pub fn begin_calibration(&mut self, duration_secs: f32) {
+        self.calibration = Some(CalibrationState {
+            remaining_secs: duration_secs,
+            bounds: HashMap::new(),
+        });
+    }
+
+    /// Per-feature `(min, max)` observed during the current calibration's
+    /// warm-up. `None` if calibration hasn't been started.
+    pub fn calibration_bounds(&self) -> Option<&HashMap<String, (f32, f32)>> {
+        self.calibration.as_ref().map(|cal| &cal.bounds)
+    }
+
+    /// During warm-up, widen each calibrated feature's bounds from `frame`.
+    /// Once warm-up has elapsed, rescale those features in place instead.
+    fn apply_calibration(&mut self, frame: &mut AnalysisFrame, block_secs: f32) {
+        let Some(cal) = &mut self.calibration else {
+            return;
+        };
+
+        if cal.remaining_secs > 1e-6 {
+            for &name in CALIBRATED_FEATURES {
+                if let Some(value) = frame.feature(name) {
+                    let bounds = cal.bounds.entry(name.to_string()).or_insert((value, value));
+                    bounds.0 = bounds.0.min(value);
+                    bounds.1 = bounds.1.max(value);
+                }
+            }
+            cal.remaining_secs -= block_secs;
+            return;
+        }
+
+        for &name in CALIBRATED_FEATURES {
+            let Some(&(min, max)) = cal.bounds.get(name) else {
+                continue;
+            };
+            if let Some(value) = frame.feature(name) {
+                let normalised = if max > min {
+                    ((value - min) / (max - min)).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                frame.set_feature(name, normalised);
+            }
+        }
+    }
+
+    /// Start (or restart) learning a noise-floor profile: for
+    /// `duration_secs` of incoming blocks, average the magnitude spectrum;
+    /// once the warm-up elapses the average becomes the profile subtracted
+    /// by [`Self::noise_gate_enabled`]. Capture this during a quiet moment
+    /// (e.g. room hum with no programme audio) so the profile reflects only
+    /// the noise, not the material it'll later be applied to. Only the
+    /// single-resolution FFT path (see [`Self::set_dual_resolution`]) is
+    /// gated.
+    pub fn learn_noise_profile(&mut self, duration_secs: f32) {
+        self.noise_profile = Some(NoiseProfileState {
+            remaining_secs: duration_secs,
+            ..NoiseProfileState::default()
+        });
+    }
+
+    /// The learned noise-floor profile, once [`Self::learn_noise_profile`]'s
+    /// warm-up has elapsed. `None` while still learning, or if never
+    /// started.
+    pub fn noise_profile(&self) -> Option<&[f32]> {
+        self.noise_profile.as_ref()?.profile.as_deref()
+    }
+
+    /// During [`Self::learn_noise_profile`]'s warm-up, average `spectrum`
+    /// into the running noise-floor profile. No-op once a profile has
+    /// already finished learning.
+    fn update_noise_profile(&mut self, spectrum: &[f32], block_secs: f32) {
+        let Some(state) = &mut self.noise_profile else {
+            return;
+        };
+        if state.profile.is_some() {
+            return;
+        }
+
+        if state.sum.len() != spectrum.len() {
+            state.sum = vec![0.0; spectrum.len()];
+        }
+        for (sum, &magnitude) in state.sum.iter_mut().zip(spectrum) {
+            *sum += magnitude;
+        }
+        state.blocks_seen += 1;
+        state.remaining_secs -= block_secs;
+
+        if state.remaining_secs <= 1e-6 {
+            let blocks = state.blocks_seen.max(1) as f32;
+            state.profile = Some(state.sum.iter().map(|&sum| sum / blocks).collect());
+        }
+    }
+
+    /// Subtract the learned noise-floor profile from `spectrum`, flooring
+    /// each bin at zero. A no-op if gating is disabled, no profile has
+    /// finished learning yet, or `spectrum`'s length doesn't match the
+    /// profile's (as when the dual-resolution short/long FFT paths are
+    /// active).
+    fn apply_noise_gate(&self, mut spectrum: Vec<f32>) -> Vec<f32> {
+        if !self.noise_gate_enabled {
+            return spectrum;
+        }
+        let Some(profile) = self.noise_profile.as_ref().and_then(|state| state.profile.as_deref()) else {
+            return spectrum;
+        };
+        if profile.len() != spectrum.len() {
+            return spectrum;
+        }
+
+        for (bin, &floor) in spectrum.iter_mut().zip(profile) {
+            *bin = (*bin - floor).max(0.0);
+        }
+        spectrum
+    }
+
+    /// Compute `(low, mid, high)` band energy for a block, using the
+    /// dual-resolution FFT path when configured: a long window for the low
+    /// band, a short window for mid/high.
+    fn band_energies(&mut self, analysed: &[f32]) -> (f32, f32, f32) {
+        let (low_hz, high_hz) = self.band_crossovers;
+        let nyquist = self.sample_rate as f32 / 2.0;
+
+        match self.dual_resolution {
+            Some(cfg) => {
+                let long = self.long_spectral.as_ref().expect("set by set_dual_resolution");
+                let short = self.short_spectral.as_ref().expect("set by set_dual_resolution");
+                let long_spectrum = long.magnitude_spectrum(analysed);
+                let short_spectrum = short.magnitude_spectrum(analysed);
+                let low = spectral::band_energy(&long_spectrum, self.sample_rate, cfg.long_fft_size, 0.0, low_hz);
+                let mid = spectral::band_energy(&short_spectrum, self.sample_rate, cfg.short_fft_size, low_hz, high_hz);
+                let high = spectral::band_energy(&short_spectrum, self.sample_rate, cfg.short_fft_size, high_hz, nyquist);
+                (low, mid, high)
+            }
+            None => {
+                let block_secs = analysed.len() as f32 / self.sample_rate as f32;
+                let spectrum = self.spectral.magnitude_spectrum(analysed);
+                self.update_noise_profile(&spectrum, block_secs);
+                let spectrum = self.apply_noise_gate(spectrum);
+                let fft_size = self.spectral.fft_size();
+                let low = spectral::band_energy(&spectrum, self.sample_rate, fft_size, 0.0, low_hz);
+                let mid = spectral::band_energy(&spectrum, self.sample_rate, fft_size, low_hz, high_hz);
+                let high = spectral::band_energy(&spectrum, self.sample_rate, fft_size, high_hz, nyquist);
+                (low, mid, high)
+            }
+        }
+    }
+
+    /// Advance each band's ballistic level and peak-hold marker for one
+    /// block: both rise instantly to match a louder raw energy, and fall at
+    /// `cfg.fall_rate` per second otherwise, the peak additionally lingering
+    /// at its high-water mark for `cfg.peak_hold_secs` before it starts
+    /// falling too. Returns `(levels, peaks)`, each `[low, mid, high]`.
+    fn update_band_ballistics(&mut self, cfg: BandBallisticsConfig, raw: [f32; 3], block_secs: f32) -> ([f32; 3], [f32; 3]) {
+        let fall = cfg.fall_rate * block_secs;
+        for (i, &raw) in raw.iter().enumerate() {
+            let level = self.band_ballistics_state.levels[i];
+            self.band_ballistics_state.levels[i] = if raw >= level { raw } else { (level - fall).max(raw) };
+
+            let peak = self.band_ballistics_state.peaks[i];
+            if raw >= peak {
+                self.band_ballistics_state.peaks[i] = raw;
+                self.band_ballistics_state.peak_ages[i] = 0.0;
+            } else {
+                self.band_ballistics_state.peak_ages[i] += block_secs;
+                if self.band_ballistics_state.peak_ages[i] >= cfg.peak_hold_secs {
+                    self.band_ballistics_state.peaks[i] = (peak - fall).max(raw);
+                }
+            }
+        }
+        (self.band_ballistics_state.levels, self.band_ballistics_state.peaks)
+    }
+
+    /// Detect a beat onset (a block louder than the previous one by
+    /// `onset_ratio`, and at least `min_beat_interval` seconds since the
+    /// last counted beat) and, if one landed, advance the bar position.
+    /// Returns `(beat_confidence, beat_in_bar, is_downbeat)`.
+    fn update_bar_tracker(&mut self, cfg: BarTrackerConfig, rms_value: f32, block_secs: f32) -> (f32, u32, bool) {
+        // `time_signature` is a bare public field with no validated setter,
+        // so guard the divisor here rather than let a caller-supplied `0`
+        // panic on the very next beat.
+        let time_signature = cfg.time_signature.max(1);
+        let previous = self.bar_tracker_state.previous_rms;
+        self.bar_tracker_state.time_since_last_beat += block_secs;
+        let is_onset = rms_value > previous * cfg.onset_ratio
+            && rms_value > 1e-4
+            && self.bar_tracker_state.time_since_last_beat >= cfg.min_beat_interval;
+        self.bar_tracker_state.previous_rms = rms_value;
+
+        if !is_onset {
+            let beat_in_bar = if self.bar_tracker_state.beat_count == 0 {
+                0
+            } else {
+                ((self.bar_tracker_state.beat_count - 1) % time_signature) + 1
+            };
+            return (0.0, beat_in_bar, false);
+        }
+
+        if self.bar_tracker_state.beat_count > 0 {
+            self.bar_tracker_state.last_beat_interval = Some(self.bar_tracker_state.time_since_last_beat);
+        }
+        self.bar_tracker_state.time_since_last_beat = 0.0;
+        self.bar_tracker_state.beat_count += 1;
+        let beat_in_bar = ((self.bar_tracker_state.beat_count - 1) % time_signature) + 1;
+        (1.0, beat_in_bar, beat_in_bar == 1)
+    }
+
+    /// Continuous position within the current beat, in `0..1`, estimated
+    /// from the time since the last detected beat and the interval between
+    /// the two most recent beats. `0.0` until a tempo can be estimated (or
+    /// on the frame a beat itself lands).
+    fn beat_phase(&self) -> f32 {
+        match self.bar_tracker_state.last_beat_interval {
+            Some(interval) if interval > 0.0 => {
+                (self.bar_tracker_state.time_since_last_beat / interval).clamp(0.0, 1.0 - f32::EPSILON)
+            }
+            _ => 0.0,
+        }
+    }
+
+    /// Apply the single-pole pre-emphasis filter, carrying the last sample
+    /// of the previous block forward so the filter doesn't click at block
+    /// boundaries.
+    fn apply_pre_emphasis(&mut self, cfg: PreEmphasisConfig, samples: &[f32]) -> Vec<f32> {
+        let mut previous = self.pre_emphasis_state.previous_sample;
+        let mut output = Vec::with_capacity(samples.len());
+        for &sample in samples {
+            output.push(sample - cfg.coefficient * previous);
+            previous = sample;
+        }
+        self.pre_emphasis_state.previous_sample = previous;
+        output
+    }
+
+    /// Divide each bin by its running max (taken before this block updates
+    /// it), so a bin reads close to `1.0` when it's at or near its recent
+    /// peak and small otherwise, regardless of the spectrum's overall scale.
+    fn whiten_spectrum(&mut self, cfg: SpectralWhiteningConfig, spectrum: &[f32]) -> Vec<f32> {
+        let running_max = self
+            .whitening_running_max
+            .get_or_insert_with(|| vec![1e-6; spectrum.len()]);
+        if running_max.len() != spectrum.len() {
+            *running_max = vec![1e-6; spectrum.len()];
+        }
+
+        let mut whitened = Vec::with_capacity(spectrum.len());
+        for (bin, &magnitude) in spectrum.iter().enumerate() {
+            whitened.push(magnitude / running_max[bin].max(1e-6));
+            running_max[bin] = (running_max[bin] * cfg.decay).max(magnitude);
+        }
+        whitened
+    }
+
+    /// Bin-by-bin change in `spectrum` since the previous block, accumulated
+    /// per [`Self::flux_mode`] (positive-rectified by default) and normalised
+    /// by bin count. When
+    /// [`Self::spectral_whitening`] is set, both this block's and the
+    /// previous block's spectra are whitened first so the result reflects
+    /// relative rather than absolute change.
+    fn compute_spectral_flux(&mut self, spectrum: Vec<f32>) -> f32 {
+        let working = match self.spectral_whitening {
+            Some(cfg) => self.whiten_spectrum(cfg, &spectrum),
+            None => spectrum,
+        };
+
+        let flux = match &self.previous_flux_spectrum {
+            Some(previous) => {
+                let diffs = working.iter().zip(previous.iter()).map(|(current, previous)| current - previous);
+                match self.flux_mode {
+                    FluxMode::PositiveL1 => diffs.map(|diff| diff.max(0.0)).sum::<f32>() / working.len() as f32,
+                    FluxMode::L1 => diffs.map(f32::abs).sum::<f32>() / working.len() as f32,
+                    FluxMode::L2 => (diffs.map(|diff| diff * diff).sum::<f32>() / working.len() as f32).sqrt(),
+                }
+            }
+            None => 0.0,
+        };
+        self.previous_flux_spectrum = Some(working);
+
+        match self.spectral_flux_baseline {
+            Some(cfg) => self.apply_flux_baseline(cfg, flux),
+            None => flux,
+        }
+    }
+
+    /// Subtract a moving average of recent scalar flux values from `flux`
+    /// before flooring at zero, so a sustained low flux settles near zero
+    /// and only above-baseline change registers.
+    fn apply_flux_baseline(&mut self, cfg: SpectralFluxBaselineConfig, flux: f32) -> f32 {
+        let baseline = if self.flux_baseline_history.is_empty() {
+            0.0
+        } else {
+            self.flux_baseline_history.iter().sum::<f32>() / self.flux_baseline_history.len() as f32
+        };
+
+        self.flux_baseline_history.push_back(flux);
+        while self.flux_baseline_history.len() > cfg.window.max(1) {
+            self.flux_baseline_history.pop_front();
+        }
+
+        (flux - baseline).max(0.0)
+    }
+
+    fn apply_agc(&mut self, cfg: AgcConfig, samples: &[f32]) -> (f32, Vec<f32>) {
+        let block_rms = rms(samples);
+        let coeff = if block_rms > self.agc_state.envelope {
+            cfg.attack
+        } else {
+            cfg.release
+        };
+        self.agc_state.envelope += (block_rms - self.agc_state.envelope) * coeff;
+
+        let envelope = self.agc_state.envelope.max(1e-6);
+        let gain = (cfg.target_rms / envelope).clamp(0.0, cfg.max_gain);
+        let adjusted: Vec<f32> = samples.iter().map(|s| s * gain).collect();
+        let normalised_rms = rms(&adjusted);
+        (normalised_rms, adjusted)
+    }
+
+    /// Process one block of mono samples, producing the next analysis frame.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "process_block", skip(self, samples), fields(block_index = self.block_index, samples_len = samples.len()))
+    )]
+    pub fn process_block(&mut self, samples: &[f32]) -> AnalysisFrame {
+        #[cfg(feature = "tracing")]
+        {
+            self.block_index += 1;
+        }
+        let samples = self.sanitize_samples(samples);
+        let samples = samples.as_slice();
+        let input_rms = rms(samples);
+        let (rms_value, analysed): (f32, Vec<f32>) = match self.agc {
+            Some(cfg) => self.apply_agc(cfg, samples),
+            None => (input_rms, samples.to_vec()),
+        };
+
+        let spectral_samples = match self.pre_emphasis {
+            Some(cfg) => self.apply_pre_emphasis(cfg, &analysed),
+            None => analysed.clone(),
+        };
+
+        let (harmonic_energy, percussive_energy) = self.spectral.hpss(&spectral_samples);
+        let frequency_features =
+            self.spectral
+                .frequency_features_tuned(&spectral_samples, self.sample_rate, self.tuning_reference_hz);
+        let (low_band_energy, mid_band_energy, high_band_energy) =
+            self.band_energies(&spectral_samples);
+        let (low_band_energy_smoothed, mid_band_energy_smoothed, high_band_energy_smoothed) =
+            match self.band_smoothing {
+                Some(cfg) => {
+                    let (prev_low, prev_mid, prev_high) = self.smoothed_bands;
+                    let smoothed = (
+                        prev_low * cfg.coefficient + low_band_energy * (1.0 - cfg.coefficient),
+                        prev_mid * cfg.coefficient + mid_band_energy * (1.0 - cfg.coefficient),
+                        prev_high * cfg.coefficient + high_band_energy * (1.0 - cfg.coefficient),
+                    );
+                    self.smoothed_bands = (
+                        recover_ema(smoothed.0, low_band_energy, "low_band_energy_smoothed"),
+                        recover_ema(smoothed.1, mid_band_energy, "mid_band_energy_smoothed"),
+                        recover_ema(smoothed.2, high_band_energy, "high_band_energy_smoothed"),
+                    );
+                    self.smoothed_bands
+                }
+                None => (low_band_energy, mid_band_energy, high_band_energy),
+            };
+        let spectral_flux = self.compute_spectral_flux(self.spectral.magnitude_spectrum(&spectral_samples));
+        let block_secs = samples.len() as f32 / self.sample_rate as f32;
+        let band_ballistics = self
+            .band_ballistics
+            .map(|cfg| self.update_band_ballistics(cfg, [low_band_energy, mid_band_energy, high_band_energy], block_secs));
+
+        if let Some(cfg) = self.spectrogram {
+            self.spectrogram_history
+                .push_back(self.spectral.magnitude_spectrum(&spectral_samples));
+            while self.spectrogram_history.len() > cfg.depth.max(1) {
+                self.spectrogram_history.pop_front();
+            }
+        }
+
+        let log_spectrum = self.log_spectrum.map(|cfg| {
+            spectral::log_frequency_spectrum(
+                &self.spectral.magnitude_spectrum(&spectral_samples),
+                self.sample_rate,
+                self.spectral.fft_size(),
+                cfg.bins_per_octave,
+                cfg.min_hz,
+            )
+        });
+
+        let warming_up = match self.flux_priming {
+            Some(cfg) if self.flux_priming_blocks_seen < cfg.warmup_blocks => {
+                self.flux_priming_blocks_seen += 1;
+                true
+            }
+            _ => false,
+        };
+        let spectral_flux = if warming_up { 0.0 } else { spectral_flux };
+
+        let mut frame = AnalysisFrame::silent(self.timestamp);
+        frame.rms = rms_value;
+        frame.rms_envelope = match self.rms_envelope {
+            Some(cfg) => {
+                let coeff = if rms_value > self.rms_envelope_state.envelope { cfg.attack } else { cfg.release };
+                self.rms_envelope_state.envelope += (rms_value - self.rms_envelope_state.envelope) * coeff;
+                self.rms_envelope_state.envelope
+            }
+            None => rms_value,
+        };
+        frame.input_rms = input_rms;
+        frame.harmonic_energy = harmonic_energy;
+        frame.percussive_energy = percussive_energy;
+        frame.chroma = frequency_features.chroma;
+        frame.spectral_crest = frequency_features.spectral_crest;
+        frame.spectral_spread = frequency_features.spectral_spread;
+        frame.spectral_flux = spectral_flux;
+        frame.warming_up = warming_up;
+        frame.low_band_energy = low_band_energy;
+        frame.mid_band_energy = mid_band_energy;
+        frame.high_band_energy = high_band_energy;
+        frame.low_band_energy_smoothed = low_band_energy_smoothed;
+        frame.mid_band_energy_smoothed = mid_band_energy_smoothed;
+        frame.high_band_energy_smoothed = high_band_energy_smoothed;
+        if let Some((levels, peaks)) = band_ballistics {
+            frame.low_band_energy_ballistic = levels[0];
+            frame.mid_band_energy_ballistic = levels[1];
+            frame.high_band_energy_ballistic = levels[2];
+            frame.low_band_energy_peak = peaks[0];
+            frame.mid_band_energy_peak = peaks[1];
+            frame.high_band_energy_peak = peaks[2];
+        } else {
+            frame.low_band_energy_ballistic = low_band_energy;
+            frame.mid_band_energy_ballistic = mid_band_energy;
+            frame.high_band_energy_ballistic = high_band_energy;
+            frame.low_band_energy_peak = low_band_energy;
+            frame.mid_band_energy_peak = mid_band_energy;
+            frame.high_band_energy_peak = high_band_energy;
+        }
+        if let Some(log_spectrum) = log_spectrum {
+            frame.log_spectrum = log_spectrum;
+        }
+        frame.activity = match self.silence_gate {
+            Some(cfg) => self.update_activity(cfg, input_rms, block_secs),
+            None => true,
+        };
+        if let Some(cfg) = self.bar_tracker {
+            let (beat_confidence, beat_in_bar, is_downbeat) =
+                self.update_bar_tracker(cfg, rms_value, block_secs);
+            frame.beat_confidence = beat_confidence;
+            frame.beat_in_bar = beat_in_bar;
+            frame.is_downbeat = is_downbeat;
+            frame.beat_phase = self.beat_phase();
+        }
+        if !frame.activity {
+            frame.beat_confidence = 0.0;
+        }
+        self.apply_calibration(&mut frame, block_secs);
+
+        frame.rms = finite_or_warn(frame.rms, "rms");
+        frame.rms_envelope = finite_or_warn(frame.rms_envelope, "rms_envelope");
+        frame.rms_db = finite_or_warn(rms_to_db(frame.rms, self.rms_db_floor), "rms_db");
+        frame.input_rms = finite_or_warn(frame.input_rms, "input_rms");
+        frame.beat_confidence = finite_or_warn(frame.beat_confidence, "beat_confidence");
+        frame.low_band_energy = finite_or_warn(frame.low_band_energy, "low_band_energy");
+        frame.mid_band_energy = finite_or_warn(frame.mid_band_energy, "mid_band_energy");
+        frame.high_band_energy = finite_or_warn(frame.high_band_energy, "high_band_energy");
+        frame.low_band_energy_smoothed =
+            finite_or_warn(frame.low_band_energy_smoothed, "low_band_energy_smoothed");
+        frame.mid_band_energy_smoothed =
+            finite_or_warn(frame.mid_band_energy_smoothed, "mid_band_energy_smoothed");
+        frame.high_band_energy_smoothed =
+            finite_or_warn(frame.high_band_energy_smoothed, "high_band_energy_smoothed");
+        frame.low_band_energy_ballistic = finite_or_warn(frame.low_band_energy_ballistic, "low_band_energy_ballistic");
+        frame.mid_band_energy_ballistic = finite_or_warn(frame.mid_band_energy_ballistic, "mid_band_energy_ballistic");
+        frame.high_band_energy_ballistic =
+            finite_or_warn(frame.high_band_energy_ballistic, "high_band_energy_ballistic");
+        frame.low_band_energy_peak = finite_or_warn(frame.low_band_energy_peak, "low_band_energy_peak");
+        frame.mid_band_energy_peak = finite_or_warn(frame.mid_band_energy_peak, "mid_band_energy_peak");
+        frame.high_band_energy_peak = finite_or_warn(frame.high_band_energy_peak, "high_band_energy_peak");
+        frame.harmonic_energy = finite_or_warn(frame.harmonic_energy, "harmonic_energy");
+        frame.percussive_energy = finite_or_warn(frame.percussive_energy, "percussive_energy");
+        for value in &mut frame.chroma {
+            *value = finite_or_warn(*value, "chroma");
+        }
+        frame.spectral_crest = finite_or_warn(frame.spectral_crest, "spectral_crest");
+        frame.spectral_spread = finite_or_warn(frame.spectral_spread, "spectral_spread");
+        frame.spectral_flux = finite_or_warn(frame.spectral_flux, "spectral_flux");
+
+        if !self.extractors.is_empty() {
+            let spectrum = self.spectral.magnitude_spectrum(&spectral_samples);
+            for extractor in &mut self.extractors {
+                for (name, value) in extractor.extract(&spectrum, samples) {
+                    frame.extra.insert(name, finite_or_warn(value, "extra"));
+                }
+            }
+        }
+
+        if let Some(hook) = &mut self.frame_hook {
+            hook(&frame);
+        }
+
+        self.timestamp += samples.len() as f64 / self.sample_rate as f64;
+        frame
+    }
+
+    /// Like [`Self::process_block`], but for a stereo pair instead of a
+    /// pre-downmixed mono block: analyses the channels averaged together
+    /// (same downmix [`crate::wav::decode_wav`] applies) exactly as
+    /// [`Self::process_block`] would, then additionally populates
+    /// [`AnalysisFrame::balance`] from the two channels' relative energy.
+    /// `left` and `right` must be the same length.
+    pub fn process_stereo_block(&mut self, left: &[f32], right: &[f32]) -> AnalysisFrame {
+        let mono: Vec<f32> = left.iter().zip(right.iter()).map(|(l, r)| (l + r) / 2.0).collect();
+        let mut frame = self.process_block(&mono);
+        frame.balance = finite_or_warn(stereo::balance(left, right), "balance");
+        frame
+    }
+
+    /// Rolling window of magnitude spectra, oldest first, retained when
+    /// [`Self::spectrogram`] is configured. Empty otherwise.
+    pub fn spectrogram(&self) -> &std::collections::VecDeque<Vec<f32>> {
+        &self.spectrogram_history
+    }
+
+    /// Analyse a magnitude spectrum the caller already computed (e.g. on a
+    /// GPU) instead of raw samples, skipping this engine's own FFT.
+    /// `bin_hz` is the spacing between successive bins, i.e.
+    /// `sample_rate / fft_size` of whatever transform produced `magnitudes`.
+    ///
+    /// Only frequency-domain features are populated: band energies, chroma,
+    /// spectral crest/spread, spectral flux (against the same running state
+    /// [`Self::process_block`] maintains), and a `spectral_centroid_hz`
+    /// entry in [`AnalysisFrame::extra`] (the core frame has no field for
+    /// centroid frequency itself). Time-domain-only features (`rms`,
+    /// `input_rms`, beat tracking) are left at their silent defaults, and
+    /// the engine's own timestamp doesn't advance since no sample count is
+    /// known here.
+    pub fn process_spectrum(&mut self, magnitudes: &[f32], bin_hz: f32) -> AnalysisFrame {
+        let fft_size = (2 * magnitudes.len().saturating_sub(1)).max(2);
+        let sample_rate = (bin_hz * fft_size as f32).round().max(1.0) as u32;
+        let (low_hz, high_hz) = self.band_crossovers;
+        let nyquist = sample_rate as f32 / 2.0;
+
+        let low_band_energy = spectral::band_energy(magnitudes, sample_rate, fft_size, 0.0, low_hz);
+        let mid_band_energy = spectral::band_energy(magnitudes, sample_rate, fft_size, low_hz, high_hz);
+        let high_band_energy = spectral::band_energy(magnitudes, sample_rate, fft_size, high_hz, nyquist);
+        let frequency_features =
+            spectral::compute_frequency_features(magnitudes, sample_rate, fft_size, self.tuning_reference_hz);
+        let centroid_hz = spectral::spectral_centroid(magnitudes, sample_rate, fft_size);
+        let spectral_flux = self.compute_spectral_flux(magnitudes.to_vec());
+
+        let mut frame = AnalysisFrame::silent(self.timestamp);
+        frame.low_band_energy = finite_or_warn(low_band_energy, "low_band_energy");
+        frame.mid_band_energy = finite_or_warn(mid_band_energy, "mid_band_energy");
+        frame.high_band_energy = finite_or_warn(high_band_energy, "high_band_energy");
+        frame.chroma = frequency_features.chroma;
+        frame.spectral_crest = finite_or_warn(frequency_features.spectral_crest, "spectral_crest");
+        frame.spectral_spread = finite_or_warn(frequency_features.spectral_spread, "spectral_spread");
+        frame.spectral_flux = finite_or_warn(spectral_flux, "spectral_flux");
+        frame.extra.insert("spectral_centroid_hz".to_string(), finite_or_warn(centroid_hz, "spectral_centroid_hz"));
+        frame
+    }
+
+    /// Like [`Self::process_block`], but tolerates a driver handing over
+    /// blocks smaller than [`MIN_BUFFERED_BLOCK_SAMPLES`] (e.g. a short tail
+    /// buffer at stream end) by accumulating them internally instead of
+    /// analysing a near-empty block. Returns `None` while still buffering.
+    pub fn process_block_buffered(&mut self, samples: &[f32]) -> Option<AnalysisFrame> {
+        if self.pending_samples.is_empty() && samples.len() >= MIN_BUFFERED_BLOCK_SAMPLES {
+            return Some(self.process_block(samples));
+        }
+
+        self.pending_samples.extend_from_slice(samples);
+        if self.pending_samples.len() < MIN_BUFFERED_BLOCK_SAMPLES {
+            return None;
+        }
+
+        let block = std::mem::take(&mut self.pending_samples);
+        Some(self.process_block(&block))
+    }
+
+    /// Like [`Self::process_block`], but when [`Self::hop_interval`] is
+    /// configured, accumulates incoming samples across calls and only
+    /// analyses (and emits a frame) once enough have arrived to cover that
+    /// interval, so the analysis rate is decoupled from the audio driver's
+    /// callback size. Returns `None` while still accumulating. Behaves
+    /// exactly like [`Self::process_block`] when `hop_interval` is unset.
+    pub fn process_block_hopped(&mut self, samples: &[f32]) -> Option<AnalysisFrame> {
+        let Some(hop_interval) = self.hop_interval else {
+            return Some(self.process_block(samples));
+        };
+
+        self.hop_pending_samples.extend_from_slice(samples);
+        let hop_samples = ((hop_interval * self.sample_rate as f32).round() as usize).max(1);
+        if self.hop_pending_samples.len() < hop_samples {
+            return None;
+        }
+
+        let block = std::mem::take(&mut self.hop_pending_samples);
+        Some(self.process_block(&block))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Other tests in this file call `process_block` concurrently on other
+    // threads, hitting the very same `#[instrument]` callsite. tracing's
+    // per-callsite interest cache is process-global rather than
+    // per-dispatcher, so a thread-scoped `with_default` subscriber races
+    // with every concurrent caller over whether that cache says the
+    // callsite is "always"/"never"/"sometimes" of interest — flaky by
+    // design. Installing one global subscriber for the whole test binary
+    // sidesteps the race entirely; it tags spans by a `samples_len` marker
+    // no other test uses, so it only counts spans this test produced.
+    #[cfg(feature = "tracing")]
+    struct SpanCounter {
+        name: &'static str,
+        marker_samples_len: u64,
+        count: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    #[cfg(feature = "tracing")]
+    struct SamplesLenVisitor(Option<u64>);
+
+    #[cfg(feature = "tracing")]
+    impl tracing::field::Visit for SamplesLenVisitor {
+        fn record_u64(&mut self, field: &tracing::field::Field, value: u64) {
+            if field.name() == "samples_len" {
+                self.0 = Some(value);
+            }
+        }
+
+        fn record_debug(&mut self, _field: &tracing::field::Field, _value: &dyn std::fmt::Debug) {}
+    }
+
+    #[cfg(feature = "tracing")]
+    impl tracing::Subscriber for SpanCounter {
+        fn enabled(&self, _metadata: &tracing::Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+            if span.metadata().name() == self.name {
+                let mut visitor = SamplesLenVisitor(None);
+                span.record(&mut visitor);
+                if visitor.0 == Some(self.marker_samples_len) {
+                    self.count.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                }
+            }
+            tracing::span::Id::from_u64(1)
+        }
+
+        fn record(&self, _span: &tracing::span::Id, _values: &tracing::span::Record<'_>) {}
+        fn record_follows_from(&self, _span: &tracing::span::Id, _follows: &tracing::span::Id) {}
+        fn event(&self, _event: &tracing::Event<'_>) {}
+        fn enter(&self, _span: &tracing::span::Id) {}
+        fn exit(&self, _span: &tracing::span::Id) {}
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn process_block_span_fires_exactly_once_per_processed_block() {
+        // A block length distinctive enough that no other test in this
+        // module happens to process a same-sized block concurrently.
+        const MARKER_LEN: usize = 54_321;
+
+        let count = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let subscriber = SpanCounter {
+            name: "process_block",
+            marker_samples_len: MARKER_LEN as u64,
+            count: count.clone(),
+        };
+        // `set_global_default` only succeeds once per process; ignore
+        // failure so this test tolerates re-running under a harness that
+        // reuses the process, rather than requiring it run first.
+        let _ = tracing::subscriber::set_global_default(subscriber);
+
+        let mut engine = AnalysisEngine::new(48_000);
+        engine.process_block(&[0.0; MARKER_LEN]);
+        engine.process_block(&[0.0; MARKER_LEN]);
+        engine.process_block(&[0.0; MARKER_LEN]);
+
+        assert_eq!(count.load(std::sync::atomic::Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn pre_emphasis_raises_spectral_centroid_of_mixed_signal() {
+        let sample_rate = 48_000u32;
+        let block: Vec<f32> = (0..1024)
+            .map(|i| {
+                let t = i as f32 / sample_rate as f32;
+                (2.0 * std::f32::consts::PI * 80.0 * t).sin() * 0.8
+                    + (2.0 * std::f32::consts::PI * 6_000.0 * t).sin() * 0.2
+            })
+            .collect();
+
+        let mut engine = AnalysisEngine::new(sample_rate);
+        let emphasised = engine.apply_pre_emphasis(PreEmphasisConfig::default(), &block);
+
+        let analyzer = SpectralAnalyzer::new(1024);
+        let plain_centroid =
+            spectral::spectral_centroid(&analyzer.magnitude_spectrum(&block), sample_rate, 1024);
+        let emphasised_centroid =
+            spectral::spectral_centroid(&analyzer.magnitude_spectrum(&emphasised), sample_rate, 1024);
+
+        assert!(
+            emphasised_centroid > plain_centroid,
+            "emphasised centroid {emphasised_centroid} should exceed plain centroid {plain_centroid}"
+        );
+    }
+
+    #[test]
+    fn agc_converges_normalised_rms_toward_target() {
+        let mut engine = AnalysisEngine::new(48_000);
+        engine.agc = Some(AgcConfig {
+            target_rms: 0.3,
+            attack: 0.8,
+            release: 0.8,
+            max_gain: 40.0,
+        });
+
+        let quiet = vec![0.01_f32; 480];
+        let loud = vec![0.9_f32; 480];
+
+        let mut last_rms = 0.0;
+        for _ in 0..40 {
+            last_rms = engine.process_block(&quiet).rms;
+        }
+        assert!((last_rms - 0.3).abs() < 0.05, "got {last_rms}");
+
+        for _ in 0..40 {
+            last_rms = engine.process_block(&loud).rms;
+        }
+        assert!((last_rms - 0.3).abs() < 0.05, "got {last_rms}");
+    }
+
+    #[test]
+    fn rms_envelope_snaps_up_on_a_spike_and_decays_gradually_after() {
+        let mut engine = AnalysisEngine::new(48_000);
+        engine.rms_envelope = Some(RmsEnvelopeConfig { attack: 0.9, release: 0.05 });
+
+        let silence = vec![0.0_f32; 480];
+        let spike = vec![1.0_f32; 480];
+
+        for _ in 0..5 {
+            engine.process_block(&silence);
+        }
+
+        let spike_frame = engine.process_block(&spike);
+        assert!(
+            spike_frame.rms_envelope > 0.8,
+            "a fast attack should snap the envelope up almost immediately, got {}",
+            spike_frame.rms_envelope
+        );
+
+        let mut previous = spike_frame.rms_envelope;
+        let mut still_elevated_after_a_few_blocks = false;
+        for i in 0..100 {
+            let frame = engine.process_block(&silence);
+            assert!(
+                frame.rms_envelope <= previous + 1e-6,
+                "envelope should never rise during silence, got {} after {previous}",
+                frame.rms_envelope
+            );
+            if i == 4 && frame.rms_envelope > 0.3 {
+                still_elevated_after_a_few_blocks = true;
+            }
+            previous = frame.rms_envelope;
+        }
+        assert!(
+            still_elevated_after_a_few_blocks,
+            "a slow release should keep the envelope elevated a few blocks after the spike"
+        );
+        assert!(previous < 0.1, "envelope should have mostly decayed by now, got {previous}");
+    }
+
+    #[test]
+    fn band_ballistics_falls_gradually_after_a_transient_while_the_peak_lingers_longer() {
+        let sample_rate = 48_000;
+        let mut engine = AnalysisEngine::new(sample_rate);
+        engine.band_ballistics = Some(BandBallisticsConfig { fall_rate: 2.0, peak_hold_secs: 0.05 });
+
+        let block_size = 1024;
+        let transient = sine(100.0, sample_rate as f32, block_size);
+        let silence = vec![0.0_f32; block_size];
+
+        let transient_frame = engine.process_block(&transient);
+        let initial_level = transient_frame.low_band_energy_ballistic;
+        let initial_peak = transient_frame.low_band_energy_peak;
+        assert!(initial_level > 0.0, "got {initial_level}");
+        assert!(
+            (initial_peak - initial_level).abs() < 1e-6,
+            "the peak marker should start out equal to the level, got {initial_peak} vs {initial_level}"
+        );
+
+        let after_one_silent_block = engine.process_block(&silence);
+        assert!(
+            after_one_silent_block.low_band_energy_ballistic < initial_level
+                && after_one_silent_block.low_band_energy_ballistic > 0.0,
+            "the ballistic level should fall gradually, not instantly, got {}",
+            after_one_silent_block.low_band_energy_ballistic
+        );
+        assert!(
+            (after_one_silent_block.low_band_energy_peak - initial_peak).abs() < 1e-6,
+            "the peak-hold marker should still be lingering at its high-water mark"
+        );
+
+        let mut previous = after_one_silent_block.low_band_energy_ballistic;
+        for _ in 0..100 {
+            let frame = engine.process_block(&silence);
+            assert!(
+                frame.low_band_energy_ballistic <= previous + 1e-6,
+                "ballistic level should never rise during silence"
+            );
+            previous = frame.low_band_energy_ballistic;
+        }
+        assert!(previous < initial_level * 0.01, "ballistic level should have decayed close to zero by now, got {previous}");
+
+        let final_peak = engine.process_block(&silence).low_band_energy_peak;
+        assert!(final_peak < initial_peak, "the peak-hold marker should eventually fall too, got {final_peak}");
+    }
+
+    #[test]
+    fn activity_drops_after_silence_hold_period() {
+        let sample_rate = 48_000;
+        let mut engine = AnalysisEngine::new(sample_rate);
+        engine.silence_gate = Some(SilenceGateConfig {
+            threshold: 0.05,
+            hold_secs: 0.5,
+        });
+
+        let block_len = 4_800; // 0.1s blocks
+        let loud = vec![0.9_f32; block_len];
+        let quiet = vec![0.0_f32; block_len];
+
+        let loud_frame = engine.process_block(&loud);
+        assert!(loud_frame.activity);
+
+        let mut went_inactive = false;
+        for _ in 0..10 {
+            let frame = engine.process_block(&quiet);
+            if !frame.activity {
+                went_inactive = true;
+                assert_eq!(frame.beat_confidence, 0.0);
+                break;
+            }
+        }
+        assert!(went_inactive, "activity should drop after the hold period");
+    }
+
+    #[test]
+    fn every_fourth_beat_is_flagged_as_downbeat() {
+        let sample_rate = 48_000;
+        let mut engine = AnalysisEngine::new(sample_rate);
+        engine.bar_tracker = Some(BarTrackerConfig::default());
+
+        let block_len = 480;
+        let click = vec![0.9_f32; block_len];
+        let quiet = vec![0.0_f32; block_len];
+
+        let mut beats_seen = 0;
+        for _ in 0..16 {
+            let click_frame = engine.process_block(&click);
+            if click_frame.beat_confidence > 0.0 {
+                beats_seen += 1;
+                let expected_downbeat = beats_seen % 4 == 1;
+                assert_eq!(click_frame.is_downbeat, expected_downbeat);
+            }
+            engine.process_block(&quiet);
+        }
+        assert!(beats_seen >= 8, "expected several beats, saw {beats_seen}");
+    }
+
+    #[test]
+    fn beat_phase_ramps_between_beats_and_wraps_to_zero_on_each_new_beat() {
+        let sample_rate = 48_000;
+        let mut engine = AnalysisEngine::new(sample_rate);
+        engine.bar_tracker = Some(BarTrackerConfig::default());
+
+        let block_len = 480;
+        let click = vec![0.9_f32; block_len];
+        let quiet = vec![0.0_f32; block_len];
+
+        // The first beat has no prior interval to estimate a tempo from yet.
+        let first_beat = engine.process_block(&click);
+        assert!(first_beat.beat_confidence > 0.0);
+        assert_eq!(first_beat.beat_phase, 0.0);
+
+        let after_first = engine.process_block(&quiet);
+        assert_eq!(after_first.beat_phase, 0.0, "no interval known before a second beat lands");
+
+        // The second beat establishes a 20ms interval (click + quiet block).
+        let second_beat = engine.process_block(&click);
+        assert!(second_beat.beat_confidence > 0.0);
+        assert_eq!(second_beat.beat_phase, 0.0, "phase wraps to 0 on the beat itself");
+
+        let mid = engine.process_block(&quiet);
+        assert!(
+            (mid.beat_phase - 0.5).abs() < 1e-4,
+            "10ms into a 20ms interval should read phase ~0.5, got {}",
+            mid.beat_phase
+        );
+
+        let third_beat = engine.process_block(&click);
+        assert!(third_beat.beat_confidence > 0.0);
+        assert_eq!(third_beat.beat_phase, 0.0);
+    }
+
+    #[test]
+    fn min_beat_interval_caps_beats_detected_on_a_fast_click_train() {
+        let sample_rate = 48_000;
+        let block_len = 480;
+        let click = vec![0.9_f32; block_len];
+        let quiet = vec![0.0_f32; block_len];
+
+        let count_beats = |min_beat_interval: f32| {
+            let mut engine = AnalysisEngine::new(sample_rate);
+            engine.bar_tracker = Some(BarTrackerConfig {
+                min_beat_interval,
+                ..BarTrackerConfig::default()
+            });
+            let mut beats = 0;
+            for _ in 0..16 {
+                if engine.process_block(&click).beat_confidence > 0.0 {
+                    beats += 1;
+                }
+                engine.process_block(&quiet);
+            }
+            beats
+        };
+
+        let default_beats = count_beats(0.0);
+        // Each click/quiet cycle is 2 * 480 / 48_000 = 20ms, so a 100ms
+        // minimum interval should let through far fewer than the default.
+        let capped_beats = count_beats(0.1);
+
+        assert!(
+            capped_beats < default_beats,
+            "expected the 100ms cap to detect fewer beats than uncapped, capped={capped_beats} default={default_beats}"
+        );
+    }
+
+    #[test]
+    fn a_zero_time_signature_does_not_panic_and_counts_every_beat_as_a_downbeat() {
+        let sample_rate = 48_000;
+        let mut engine = AnalysisEngine::new(sample_rate);
+        engine.bar_tracker = Some(BarTrackerConfig {
+            time_signature: 0,
+            ..BarTrackerConfig::default()
+        });
+
+        let block_len = 480;
+        let click = vec![0.9_f32; block_len];
+        let quiet = vec![0.0_f32; block_len];
+
+        let mut beats_seen = 0;
+        for _ in 0..16 {
+            let click_frame = engine.process_block(&click);
+            if click_frame.beat_confidence > 0.0 {
+                beats_seen += 1;
+                assert!(click_frame.is_downbeat, "every beat is a downbeat once the time signature is clamped to 1");
+            }
+            engine.process_block(&quiet);
+        }
+        assert!(beats_seen >= 8, "expected several beats, saw {beats_seen}");
+    }
+
+    #[test]
+    fn flux_baseline_subtraction_suppresses_steady_sustain_but_not_onset() {
+        let sample_rate = 48_000;
+        let block_len = 1024;
+        let mut engine = AnalysisEngine::new(sample_rate);
+        engine.spectral_flux_baseline = Some(SpectralFluxBaselineConfig::default());
+
+        // A slowly, steadily swelling tone: each block differs from the last
+        // by roughly the same small amount, like a sustained chord drifting
+        // rather than a sharp onset.
+        let mut sustain_flux = 0.0;
+        for i in 0..40 {
+            let amp = 0.2 + 0.002 * i as f32;
+            let block: Vec<f32> = sine(440.0, sample_rate as f32, block_len)
+                .iter()
+                .map(|s| s * amp)
+                .collect();
+            sustain_flux = engine.process_block(&block).spectral_flux;
+        }
+
+        let onset: Vec<f32> = sine(440.0, sample_rate as f32, block_len)
+            .iter()
+            .map(|s| s * 0.95)
+            .collect();
+        let onset_flux = engine.process_block(&onset).spectral_flux;
+
+        assert!(
+            sustain_flux < 0.02,
+            "baseline should have absorbed the steady sustain, got {sustain_flux}"
+        );
+        assert!(
+            onset_flux > sustain_flux * 5.0,
+            "a real onset should spike well above the settled sustain flux, sustain={sustain_flux} onset={onset_flux}"
+        );
+    }
+
+    #[test]
+    fn flux_priming_suppresses_the_cold_start_spike_a_bare_engine_reports() {
+        let sample_rate = 48_000;
+        let block_len = 1024;
+
+        // A startup transient (loud click) followed by a steady tone: a bare
+        // engine has no prior spectrum for the click, so it reports flux = 0
+        // for it, then spikes hugely comparing the click against the tone.
+        let click: Vec<f32> = (0..block_len)
+            .map(|i| if i % 2 == 0 { 1.0 } else { -1.0 })
+            .collect();
+        let tone = sine(440.0, sample_rate as f32, block_len);
+
+        let mut bare = AnalysisEngine::new(sample_rate);
+        bare.process_block(&click);
+        let bare_second_flux = bare.process_block(&tone).spectral_flux;
+
+        let mut primed = AnalysisEngine::new(sample_rate);
+        primed.flux_priming = Some(FluxPrimingConfig { warmup_blocks: 2 });
+        let primed_first = primed.process_block(&click);
+        let primed_second = primed.process_block(&tone);
+
+        assert!(primed_first.warming_up);
+        assert!(primed_second.warming_up);
+        assert_eq!(primed_second.spectral_flux, 0.0);
+        assert!(
+            primed_second.spectral_flux < bare_second_flux,
+            "priming should suppress the cold-start spike, bare={bare_second_flux} primed={}",
+            primed_second.spectral_flux
+        );
+
+        let settled = primed.process_block(&tone);
+        assert!(!settled.warming_up);
+    }
+
+    #[test]
+    fn spectrogram_retains_only_the_configured_depth_with_the_newest_column_last() {
+        let sample_rate = 48_000;
+        let block_len = 1024;
+        let mut engine = AnalysisEngine::new(sample_rate);
+        engine.spectrogram = Some(SpectrogramConfig { depth: 4 });
+
+        for i in 0..10 {
+            let block = sine(220.0 + i as f32 * 10.0, sample_rate as f32, block_len);
+            engine.process_block(&block);
+        }
+
+        assert_eq!(engine.spectrogram().len(), 4);
+        let newest = engine.spectrogram().back().expect("non-empty");
+        let last_block = sine(220.0 + 9.0 * 10.0, sample_rate as f32, block_len);
+        assert_eq!(*newest, engine.spectral.magnitude_spectrum(&last_block));
+    }
+
+    #[test]
+    fn buffered_processing_accumulates_single_sample_blocks_until_enough_to_emit_a_frame() {
+        let mut engine = AnalysisEngine::new(48_000);
+
+        assert!(engine.process_block_buffered(&[0.1]).is_none());
+        let frame = engine.process_block_buffered(&[0.2]);
+
+        assert!(frame.is_some(), "two accumulated samples should be enough to emit a frame");
+    }
+
+    #[test]
+    fn hopped_processing_emits_frames_spaced_by_the_configured_interval_regardless_of_callback_size() {
+        let sample_rate = 48_000;
+        let mut engine = AnalysisEngine::new(sample_rate);
+        engine.hop_interval = Some(0.010); // 10ms
+
+        // Feed much smaller callback blocks than the hop interval, as a
+        // low-latency audio driver would.
+        let callback_len = 64;
+        let mut timestamps = Vec::new();
+        for _ in 0..200 {
+            let block = vec![0.0f32; callback_len];
+            if let Some(frame) = engine.process_block_hopped(&block) {
+                timestamps.push(frame.timestamp);
+            }
+        }
+
+        assert!(timestamps.len() >= 2, "expected multiple hop-spaced frames, got {}", timestamps.len());
+        for pair in timestamps.windows(2) {
+            let gap = pair[1] - pair[0];
+            assert!((gap - 0.010).abs() < 0.001, "got gap {gap}");
+        }
+    }
+
+    fn sine(freq: f32, sample_rate: f32, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| (2.0 * std::f32::consts::PI * freq * i as f32 / sample_rate).sin())
+            .collect()
+    }
+
+    #[test]
+    fn rms_db_of_a_full_scale_sine_is_near_its_true_crest_factor_and_halving_amplitude_drops_it_six_db() {
+        let sample_rate = 48_000;
+        let full_scale = sine(440.0, sample_rate as f32, 4096);
+        let half_amplitude: Vec<f32> = full_scale.iter().map(|s| s * 0.5).collect();
+
+        let full_scale_db = AnalysisEngine::new(sample_rate).process_block(&full_scale).rms_db;
+        let half_amplitude_db = AnalysisEngine::new(sample_rate).process_block(&half_amplitude).rms_db;
+
+        // A full-scale sine's RMS is 1/sqrt(2) of its peak, i.e. ~-3 dBFS, not 0.
+        assert!((full_scale_db - (-3.01)).abs() < 0.5, "got {full_scale_db}");
+        assert!((half_amplitude_db - (full_scale_db - 6.0)).abs() < 0.5, "got {half_amplitude_db}");
+    }
+
+    #[test]
+    fn rms_db_floors_at_the_configured_threshold_instead_of_reporting_negative_infinity() {
+        let sample_rate = 48_000;
+        let silence = vec![0.0; 4096];
+
+        let mut engine = AnalysisEngine::builder(sample_rate).rms_db_floor(-60.0).build().unwrap();
+        let frame = engine.process_block(&silence);
+
+        assert_eq!(frame.rms_db, -60.0);
+    }
+
+    #[test]
+    fn dual_resolution_low_band_energy_matches_single_fft_path() {
+        let sample_rate = 48_000;
+        let bass = sine(80.0, sample_rate as f32, 4096);
+
+        let mut single = AnalysisEngine::new(sample_rate);
+        let mut dual = AnalysisEngine::new(sample_rate);
+        dual.set_dual_resolution(Some(DualResolutionConfig {
+            long_fft_size: 4096,
+            short_fft_size: 512,
+            crossover_hz: 300.0,
+        }))
+        .expect("valid power-of-two sizes");
+
+        let single_low = single.process_block(&bass).low_band_energy;
+        let dual_low = dual.process_block(&bass).low_band_energy;
+
+        assert!(single_low > 0.0 && dual_low > 0.0, "{single_low} {dual_low}");
+        let ratio = dual_low / single_low;
+        assert!((0.5..2.0).contains(&ratio), "single={single_low} dual={dual_low}");
+    }
+
+    #[test]
+    fn set_dual_resolution_rejects_an_invalid_fft_size_instead_of_panicking() {
+        let mut engine = AnalysisEngine::new(48_000);
+
+        let err = engine
+            .set_dual_resolution(Some(DualResolutionConfig {
+                long_fft_size: 0,
+                short_fft_size: 512,
+                crossover_hz: 300.0,
+            }))
+            .expect_err("fft_size 0 can't be planned");
+
+        assert_eq!(err, EngineError::InvalidFftSize(0));
+        assert_eq!(engine.dual_resolution(), None, "a rejected config must not be applied");
+    }
+
+    #[test]
+    fn band_smoothing_reduces_variance_of_a_flickering_band_energy() {
+        let sample_rate = 48_000;
+        let loud = sine(5_000.0, sample_rate as f32, 1024);
+        let quiet = vec![0.0f32; 1024];
+
+        let mut raw_engine = AnalysisEngine::new(sample_rate);
+        let mut smoothed_engine = AnalysisEngine::new(sample_rate);
+        smoothed_engine.band_smoothing = Some(BandSmoothingConfig { coefficient: 0.8 });
+
+        let mut raw_values = Vec::new();
+        let mut smoothed_values = Vec::new();
+        for i in 0..20 {
+            let block: &[f32] = if i % 2 == 0 { &loud } else { &quiet };
+            raw_values.push(raw_engine.process_block(block).high_band_energy);
+            smoothed_values.push(smoothed_engine.process_block(block).high_band_energy_smoothed);
+        }
+
+        fn variance(values: &[f32]) -> f32 {
+            let mean = values.iter().sum::<f32>() / values.len() as f32;
+            values.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / values.len() as f32
+        }
+
+        assert!(
+            variance(&smoothed_values) < variance(&raw_values),
+            "smoothed={} raw={}",
+            variance(&smoothed_values),
+            variance(&raw_values)
+        );
+    }
+
+    #[test]
+    fn a_poisoned_smoothed_band_recovers_on_the_next_block_instead_of_staying_non_finite() {
+        let sample_rate = 48_000;
+        let block = sine(5_000.0, sample_rate as f32, 1024);
+
+        let mut engine = AnalysisEngine::new(sample_rate);
+        engine.band_smoothing = Some(BandSmoothingConfig::default());
+        // Simulate a prior block that drove the EMA fixed point non-finite
+        // (e.g. an intermediate overflow `sanitize_samples` can't catch).
+        engine.smoothed_bands = (f32::NAN, f32::INFINITY, f32::NAN);
+
+        let frame = engine.process_block(&block);
+        assert!(frame.low_band_energy_smoothed.is_finite());
+        assert!(frame.mid_band_energy_smoothed.is_finite());
+        assert!(frame.high_band_energy_smoothed.is_finite());
+
+        // And it must not still be poisoned on the following block either.
+        let next_frame = engine.process_block(&block);
+        assert!(next_frame.low_band_energy_smoothed.is_finite());
+        assert!(next_frame.mid_band_energy_smoothed.is_finite());
+        assert!(next_frame.high_band_energy_smoothed.is_finite());
+    }
+
+    #[test]
+    fn calibration_rescales_observed_max_to_one() {
+        let sample_rate = 48_000;
+        let mut engine = AnalysisEngine::new(sample_rate);
+        let block_len = 4_800; // 0.1s blocks
+        engine.begin_calibration(0.5);
+
+        for amp in [0.1_f32, 0.2, 0.3, 0.4, 0.5] {
+            engine.process_block(&vec![amp; block_len]);
+        }
+
+        let bounds = engine
+            .calibration_bounds()
+            .expect("calibration should be active")
+            .clone();
+        let (min, max) = bounds["rms"];
+        assert!((min - 0.1).abs() < 1e-3, "min = {min}");
+        assert!((max - 0.5).abs() < 1e-3, "max = {max}");
+
+        let frame = engine.process_block(&vec![0.5_f32; block_len]);
+        assert!((frame.rms - 1.0).abs() < 0.05, "got {}", frame.rms);
+    }
+
+    #[test]
+    fn builder_settings_stick_on_the_built_engine() {
+        let engine = AnalysisEngine::builder(48_000)
+            .fft_size(2048)
+            .window(WindowFunction::Hann)
+            .bands(300.0, 3_000.0)
+            .tempo_estimator(TempoEstimator::OnsetRatio)
+            .enable_agc(true)
+            .build()
+            .expect("valid configuration");
+
+        assert_eq!(engine.sample_rate(), 48_000);
+        assert_eq!(engine.fft_size(), 2048);
+        assert_eq!(engine.window(), WindowFunction::Hann);
+        assert_eq!(engine.band_crossovers(), (300.0, 3_000.0));
+        assert_eq!(engine.tempo_estimator(), TempoEstimator::OnsetRatio);
+        assert!(engine.agc.is_some());
+    }
+
+    #[test]
+    fn set_band_crossovers_retunes_the_split_and_rejects_an_invalid_range() {
+        let mut engine = AnalysisEngine::new(48_000);
+
+        engine.set_band_crossovers(150.0, 4_000.0).expect("valid range");
+        assert_eq!(engine.band_crossovers(), (150.0, 4_000.0));
+
+        assert_eq!(
+            engine.set_band_crossovers(4_000.0, 150.0),
+            Err(EngineError::InvalidBandCrossovers { low_hz: 4_000.0, high_hz: 150.0 })
+        );
+        // A rejected update leaves the previous, still-valid crossovers in place.
+        assert_eq!(engine.band_crossovers(), (150.0, 4_000.0));
+    }
+
+    #[test]
+    fn spectral_whitening_reveals_quiet_onset_after_loud_section() {
+        let sample_rate = 48_000;
+        let block_len = 1024;
+        let loud = sine(440.0, sample_rate as f32, block_len)
+            .iter()
+            .map(|s| s * 0.9)
+            .collect::<Vec<f32>>();
+        let silence = vec![0.0_f32; block_len];
+        let quiet_onset = sine(440.0, sample_rate as f32, block_len)
+            .iter()
+            .map(|s| s * 0.02)
+            .collect::<Vec<f32>>();
+
+        let mut plain = AnalysisEngine::new(sample_rate);
+        for _ in 0..10 {
+            plain.process_block(&loud);
+        }
+        for _ in 0..20 {
+            plain.process_block(&silence);
+        }
+        let plain_flux = plain.process_block(&quiet_onset).spectral_flux;
+
+        let mut whitened = AnalysisEngine::new(sample_rate);
+        whitened.spectral_whitening = Some(SpectralWhiteningConfig::default());
+        for _ in 0..10 {
+            whitened.process_block(&loud);
+        }
+        for _ in 0..20 {
+            whitened.process_block(&silence);
+        }
+        let whitened_flux = whitened.process_block(&quiet_onset).spectral_flux;
+
+        // An onset detector keyed on a fixed flux threshold would miss the
+        // quiet onset against the raw spectrum but catch it once whitened.
+        let onset_threshold = 0.1;
+        assert!(
+            plain_flux < onset_threshold,
+            "raw flux should miss the quiet onset, got {plain_flux}"
+        );
+        assert!(
+            whitened_flux > onset_threshold,
+            "whitened flux should detect the quiet onset, got {whitened_flux}"
+        );
+    }
+
+    #[test]
+    fn non_finite_samples_are_sanitized_and_counted_and_frame_stays_finite() {
+        let sample_rate = 48_000;
+        let mut engine = AnalysisEngine::new(sample_rate);
+
+        let mut block = vec![0.1_f32; 1024];
+        block[10] = f32::NAN;
+        block[500] = f32::INFINITY;
+
+        let frame = engine.process_block(&block);
+
+        assert!(frame.rms.is_finite());
+        assert!(frame.input_rms.is_finite());
+        assert!(frame.spectral_flux.is_finite());
+        assert!(frame.chroma.iter().all(|v| v.is_finite()));
+        assert_eq!(engine.non_finite_input_samples(), 2);
+    }
+
+    #[test]
+    fn builder_rejects_non_power_of_two_fft_size() {
+        match AnalysisEngine::builder(48_000).fft_size(1000).build() {
+            Err(error) => assert_eq!(error, EngineError::InvalidFftSize(1000)),
+            Ok(_) => panic!("expected a non-power-of-two fft_size to be rejected"),
+        }
+    }
+
+    #[test]
+    fn pad_to_efficient_fft_size_accepts_and_analyzes_a_padded_block() {
+        let sample_rate = 48_000;
+        let mut engine = AnalysisEngine::builder(sample_rate)
+            .fft_size(1000)
+            .pad_to_efficient_fft_size(true)
+            .build()
+            .expect("padding should let a non-power-of-two fft_size build");
+
+        assert_eq!(engine.fft_size(), 1024, "1000 should pad up to the next power of two");
+
+        let block: Vec<f32> = (0..1000)
+            .map(|i| (2.0 * std::f32::consts::PI * 440.0 * i as f32 / sample_rate as f32).sin())
+            .collect();
+        let frame = engine.process_block(&block);
+        assert!(frame.chroma.iter().all(|v| v.is_finite()));
+
+        // Confirm the padded fft_size, not the requested one, drives the
+        // frequency math: a centroid computed against the wrong bin_hz for
+        // the true (padded) spectrum length would land far from 440 Hz.
+        let padded = SpectralAnalyzer::new(engine.fft_size());
+        let centroid = spectral::spectral_centroid(&padded.magnitude_spectrum(&block), sample_rate, engine.fft_size());
+        assert!(
+            (centroid - 440.0).abs() < 100.0,
+            "expected a centroid near 440 Hz, got {centroid}"
+        );
+    }
+
+    #[test]
+    fn tuning_reference_hz_sharpens_chroma_for_a_432hz_tuned_track() {
+        let sample_rate = 48_000;
+        let block = sine(432.0, sample_rate as f32, 2048);
+        let a_bin = 9; // MIDI pitch class 69 % 12 == A
+
+        let mut default_tuned = AnalysisEngine::new(sample_rate);
+        let default_chroma = default_tuned.process_block(&block).chroma;
+
+        let mut engine = AnalysisEngineBuilder::new(sample_rate)
+            .tuning_reference_hz(432.0)
+            .build()
+            .expect("valid config");
+        assert_eq!(engine.tuning_reference_hz(), 432.0);
+        let chroma = engine.process_block(&block).chroma;
+
+        assert!(
+            chroma[a_bin] > default_chroma[a_bin],
+            "binning against the track's actual 432 Hz reference should concentrate more energy \
+             in the A bin than the default A440 reference, got {chroma:?} vs {default_chroma:?}"
+        );
+    }
+
+    #[test]
+    fn process_stereo_block_reports_balance_from_relative_channel_energy() {
+        let sample_rate = 48_000;
+        let block = sine(440.0, sample_rate as f32, 1024);
+        let silence = vec![0.0_f32; 1024];
+
+        let mut left_only_engine = AnalysisEngine::new(sample_rate);
+        let left_only = left_only_engine.process_stereo_block(&block, &silence);
+        assert!((left_only.balance - (-1.0)).abs() < 1e-6, "got {}", left_only.balance);
+
+        let mut centered_engine = AnalysisEngine::new(sample_rate);
+        let centered = centered_engine.process_stereo_block(&block, &block);
+        assert!(centered.balance.abs() < 1e-6, "got {}", centered.balance);
+    }
+
+    #[test]
+    fn exported_state_reproduces_the_continuous_flux_across_a_reset() {
+        let sample_rate = 48_000;
+        let block_len = 1024;
+        let warmup = sine(220.0, sample_rate as f32, block_len);
+        let next_block = sine(440.0, sample_rate as f32, block_len);
+
+        let mut continuous = AnalysisEngine::new(sample_rate);
+        continuous.spectral_whitening = Some(SpectralWhiteningConfig::default());
+        continuous.bar_tracker = Some(BarTrackerConfig::default());
+        for _ in 0..5 {
+            continuous.process_block(&warmup);
+        }
+        let continuous_flux = continuous.process_block(&next_block).spectral_flux;
+
+        let mut looping = AnalysisEngine::new(sample_rate);
+        looping.spectral_whitening = Some(SpectralWhiteningConfig::default());
+        looping.bar_tracker = Some(BarTrackerConfig::default());
+        for _ in 0..5 {
+            looping.process_block(&warmup);
+        }
+        let state = looping.export_state();
+
+        // Reset to a fresh engine (as a loop restart would), then restore.
+        looping = AnalysisEngine::new(sample_rate);
+        looping.spectral_whitening = Some(SpectralWhiteningConfig::default());
+        looping.bar_tracker = Some(BarTrackerConfig::default());
+        looping.import_state(state);
+        let looped_flux = looping.process_block(&next_block).spectral_flux;
+
+        assert!(
+            (continuous_flux - looped_flux).abs() < 1e-6,
+            "continuous={continuous_flux} looped={looped_flux}"
+        );
+    }
+
+    #[test]
+    fn exported_state_reproduces_the_continuous_band_smoothing_across_a_reset() {
+        let sample_rate = 48_000;
+        let block_len = 1024;
+        let warmup = sine(220.0, sample_rate as f32, block_len);
+        let next_block = sine(440.0, sample_rate as f32, block_len);
+
+        let mut continuous = AnalysisEngine::new(sample_rate);
+        continuous.band_smoothing = Some(BandSmoothingConfig::default());
+        for _ in 0..5 {
+            continuous.process_block(&warmup);
+        }
+        let continuous_smoothed = continuous.process_block(&next_block).low_band_energy_smoothed;
+
+        let mut looping = AnalysisEngine::new(sample_rate);
+        looping.band_smoothing = Some(BandSmoothingConfig::default());
+        for _ in 0..5 {
+            looping.process_block(&warmup);
+        }
+        let state = looping.export_state();
+
+        looping = AnalysisEngine::new(sample_rate);
+        looping.band_smoothing = Some(BandSmoothingConfig::default());
+        looping.import_state(state);
+        let looped_smoothed = looping.process_block(&next_block).low_band_energy_smoothed;
+
+        assert!(
+            (continuous_smoothed - looped_smoothed).abs() < 1e-6,
+            "continuous={continuous_smoothed} looped={looped_smoothed}"
+        );
+    }
+
+    #[test]
+    fn exported_state_reproduces_the_continuous_rms_envelope_across_a_reset() {
+        let sample_rate = 48_000;
+        let block_len = 1024;
+        let warmup = sine(220.0, sample_rate as f32, block_len);
+        let next_block = sine(440.0, sample_rate as f32, block_len);
+
+        let mut continuous = AnalysisEngine::new(sample_rate);
+        continuous.rms_envelope = Some(RmsEnvelopeConfig::default());
+        for _ in 0..5 {
+            continuous.process_block(&warmup);
+        }
+        let continuous_envelope = continuous.process_block(&next_block).rms_envelope;
+
+        let mut looping = AnalysisEngine::new(sample_rate);
+        looping.rms_envelope = Some(RmsEnvelopeConfig::default());
+        for _ in 0..5 {
+            looping.process_block(&warmup);
+        }
+        let state = looping.export_state();
+
+        looping = AnalysisEngine::new(sample_rate);
+        looping.rms_envelope = Some(RmsEnvelopeConfig::default());
+        looping.import_state(state);
+        let looped_envelope = looping.process_block(&next_block).rms_envelope;
+
+        assert!(
+            (continuous_envelope - looped_envelope).abs() < 1e-6,
+            "continuous={continuous_envelope} looped={looped_envelope}"
+        );
+    }
+
+    #[test]
+    fn exported_state_reproduces_the_continuous_band_ballistics_across_a_reset() {
+        let sample_rate = 48_000;
+        let block_len = 1024;
+        let warmup = sine(220.0, sample_rate as f32, block_len);
+        let next_block = vec![0.0_f32; block_len];
+
+        let mut continuous = AnalysisEngine::new(sample_rate);
+        continuous.band_ballistics = Some(BandBallisticsConfig::default());
+        for _ in 0..5 {
+            continuous.process_block(&warmup);
+        }
+        let continuous_peak = continuous.process_block(&next_block).low_band_energy_peak;
+
+        let mut looping = AnalysisEngine::new(sample_rate);
+        looping.band_ballistics = Some(BandBallisticsConfig::default());
+        for _ in 0..5 {
+            looping.process_block(&warmup);
+        }
+        let state = looping.export_state();
+
+        looping = AnalysisEngine::new(sample_rate);
+        looping.band_ballistics = Some(BandBallisticsConfig::default());
+        looping.import_state(state);
+        let looped_peak = looping.process_block(&next_block).low_band_energy_peak;
+
+        assert!(
+            (continuous_peak - looped_peak).abs() < 1e-6,
+            "continuous={continuous_peak} looped={looped_peak}"
+        );
+    }
+
+    #[test]
+    fn reset_adaptive_state_forgets_running_state_but_keeps_configuration() {
+        let sample_rate = 48_000;
+        let block = sine(440.0, sample_rate as f32, 1024);
+
+        let mut engine = AnalysisEngine::new(sample_rate);
+        engine.spectral_whitening = Some(SpectralWhiteningConfig::default());
+        engine.band_smoothing = Some(BandSmoothingConfig::default());
+        engine.rms_envelope = Some(RmsEnvelopeConfig::default());
+        engine.band_ballistics = Some(BandBallisticsConfig::default());
+        for _ in 0..5 {
+            engine.process_block(&block);
+        }
+        assert!(engine.export_state().whitening_running_max.is_some());
+        assert_ne!(engine.export_state().smoothed_bands, (0.0, 0.0, 0.0));
+        assert_ne!(engine.export_state().rms_envelope_state.envelope, 0.0);
+
+        engine.reset_adaptive_state();
+
+        assert!(engine.spectral_whitening.is_some(), "configuration should survive a reset");
+        assert!(engine.export_state().whitening_running_max.is_none());
+        assert_eq!(engine.export_state().smoothed_bands, (0.0, 0.0, 0.0));
+        assert_eq!(engine.export_state().rms_envelope_state.envelope, 0.0);
+        assert_eq!(engine.export_state().band_ballistics_state.peaks, [0.0; 3]);
+        // The very next frame should look like a fresh engine's first frame.
+        assert_eq!(engine.process_block(&block).spectral_flux, 0.0);
+    }
+
+    struct ConstantExtractor {
+        name: &'static str,
+        value: f32,
+    }
+
+    impl FeatureExtractor for ConstantExtractor {
+        fn extract(&mut self, _spectrum: &[f32], _samples: &[f32]) -> HashMap<String, f32> {
+            HashMap::from([(self.name.to_string(), self.value)])
+        }
+    }
+
+    #[test]
+    fn registered_extractor_output_appears_in_the_frame_and_is_mappable() {
+        let mut engine = AnalysisEngine::new(48_000);
+        engine.register_extractor(ConstantExtractor { name: "proprietary_score", value: 0.75 });
+
+        let frame = engine.process_block(&sine(440.0, 48_000.0, 1024));
+
+        assert_eq!(frame.extra.get("proprietary_score"), Some(&0.75));
+        assert_eq!(frame.feature("proprietary_score"), Some(0.75));
+    }
+
+    #[test]
+    fn frame_hook_sees_every_processed_block() {
+        let mut engine = AnalysisEngine::new(48_000);
+        let seen_rms = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen_rms_clone = seen_rms.clone();
+        engine.set_frame_hook(move |frame| seen_rms_clone.lock().unwrap().push(frame.rms));
+
+        let quiet = vec![0.0f32; 1024];
+        let loud = sine(440.0, 48_000.0, 1024);
+        engine.process_block(&quiet);
+        engine.process_block(&loud);
+
+        let seen = seen_rms.lock().unwrap();
+        assert_eq!(seen.len(), 2);
+        assert!(seen[1] > seen[0], "expected the loud block's rms to be seen too");
+    }
+
+    #[test]
+    fn l2_flux_differs_from_positive_l1_on_a_transition_but_both_are_zero_for_repeats() {
+        let sample_rate = 48_000.0;
+        let quiet = sine(220.0, sample_rate, 1024);
+        let loud: Vec<f32> = sine(220.0, sample_rate, 1024).iter().map(|s| s * 4.0).collect();
+
+        let mut positive_l1 = AnalysisEngine::builder(48_000).build().unwrap();
+        positive_l1.process_block(&quiet);
+        assert_eq!(positive_l1.process_block(&quiet).spectral_flux, 0.0);
+        let positive_l1_flux = positive_l1.process_block(&loud).spectral_flux;
+
+        let mut l2 = AnalysisEngine::builder(48_000).flux_mode(FluxMode::L2).build().unwrap();
+        l2.process_block(&quiet);
+        assert_eq!(l2.process_block(&quiet).spectral_flux, 0.0);
+        let l2_flux = l2.process_block(&loud).spectral_flux;
+
+        assert!(positive_l1_flux > 0.0, "got {positive_l1_flux}");
+        assert!(l2_flux > 0.0, "got {l2_flux}");
+        assert_ne!(positive_l1_flux, l2_flux);
+    }
+
+    #[test]
+    fn noise_gate_learned_from_a_hum_tone_substantially_reduces_its_own_low_band_energy() {
+        let sample_rate = 48_000;
+        let hum = sine(80.0, sample_rate as f32, 1024);
+
+        let mut engine = AnalysisEngine::new(sample_rate);
+        engine.learn_noise_profile(0.1);
+        let mut ungated_low = 0.0;
+        for _ in 0..10 {
+            ungated_low = engine.process_block(&hum).low_band_energy;
+        }
+        assert!(engine.noise_profile().is_some(), "profile should have finished learning");
+
+        engine.noise_gate_enabled = true;
+        let gated_low = engine.process_block(&hum).low_band_energy;
+
+        assert!(
+            gated_low < ungated_low * 0.1,
+            "expected the gate to mostly remove the hum's own low-band energy, ungated={ungated_low} gated={gated_low}"
+        );
+    }
+
+    #[test]
+    fn process_spectrum_centroid_lands_on_the_single_populated_bin() {
+        let bin_hz = 43.066_406; // 44_100 Hz / 1024-point FFT
+        let mut magnitudes = vec![0.0f32; 513];
+        let peak_bin = 20;
+        magnitudes[peak_bin] = 1.0;
+
+        let mut engine = AnalysisEngine::new(44_100);
+        let frame = engine.process_spectrum(&magnitudes, bin_hz);
+
+        let centroid_hz = frame.extra.get("spectral_centroid_hz").copied().expect("centroid should be reported");
+        let expected_hz = peak_bin as f32 * bin_hz;
+        assert!((centroid_hz - expected_hz).abs() < 1.0, "expected ~{expected_hz}, got {centroid_hz}");
+    }
+}
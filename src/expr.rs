@@ -0,0 +1,266 @@
+//! A tiny arithmetic grammar for combining multiple analysis features into
+//! one mapping source, e.g. `rms * beat_confidence`.
+
+use std::fmt;
+
+use crate::analysis::AnalysisFrame;
+
+const KNOWN_FEATURES: &[&str] = &[
+    "rms",
+    "beat_confidence",
+    "low_band_energy",
+    "mid_band_energy",
+    "high_band_energy",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number(f32),
+    Feature(String),
+    Add(Box<Expr>, Box<Expr>),
+    Sub(Box<Expr>, Box<Expr>),
+    Mul(Box<Expr>, Box<Expr>),
+    Div(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    pub fn eval(&self, frame: &AnalysisFrame) -> f32 {
+        match self {
+            Expr::Number(n) => *n,
+            Expr::Feature(name) => frame.feature(name).unwrap_or(0.0),
+            Expr::Add(a, b) => a.eval(frame) + b.eval(frame),
+            Expr::Sub(a, b) => a.eval(frame) - b.eval(frame),
+            Expr::Mul(a, b) => a.eval(frame) * b.eval(frame),
+            Expr::Div(a, b) => {
+                let divisor = b.eval(frame);
+                if divisor == 0.0 {
+                    0.0
+                } else {
+                    a.eval(frame) / divisor
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprError {
+    UnexpectedEnd,
+    UnexpectedToken(String),
+    UnknownFeature(String),
+    DivisionByZero,
+}
+
+impl fmt::Display for ExprError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExprError::UnexpectedEnd => write!(f, "unexpected end of expression"),
+            ExprError::UnexpectedToken(t) => write!(f, "unexpected token `{t}`"),
+            ExprError::UnknownFeature(name) => write!(f, "unknown feature `{name}`"),
+            ExprError::DivisionByZero => write!(f, "division by a literal zero"),
+        }
+    }
+}
+
+impl std::error::Error for ExprError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f32),
+    Ident(String),
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    LParen,
+    RParen,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, ExprError> {
+    let mut tokens = Vec::new();
+    let chars: Vec<char> = input.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            ' ' | '\t' | '\n' => i += 1,
+            '+' => {
+                tokens.push(Token::Plus);
+                i += 1;
+            }
+            '-' => {
+                tokens.push(Token::Minus);
+                i += 1;
+            }
+            '*' => {
+                tokens.push(Token::Star);
+                i += 1;
+            }
+            '/' => {
+                tokens.push(Token::Slash);
+                i += 1;
+            }
+            '(' => {
+                tokens.push(Token::LParen);
+                i += 1;
+            }
+            ')' => {
+                tokens.push(Token::RParen);
+                i += 1;
+            }
+            c if c.is_ascii_digit() || c == '.' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                let text: String = chars[start..i].iter().collect();
+                let value = text
+                    .parse::<f32>()
+                    .map_err(|_| ExprError::UnexpectedToken(text.clone()))?;
+                tokens.push(Token::Number(value));
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                let text: String = chars[start..i].iter().collect();
+                tokens.push(Token::Ident(text));
+            }
+            other => return Err(ExprError::UnexpectedToken(other.to_string())),
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn advance(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    // expr := term (('+' | '-') term)*
+    fn parse_expr(&mut self) -> Result<Expr, ExprError> {
+        let mut node = self.parse_term()?;
+        loop {
+            match self.peek() {
+                Some(Token::Plus) => {
+                    self.advance();
+                    let rhs = self.parse_term()?;
+                    node = Expr::Add(Box::new(node), Box::new(rhs));
+                }
+                Some(Token::Minus) => {
+                    self.advance();
+                    let rhs = self.parse_term()?;
+                    node = Expr::Sub(Box::new(node), Box::new(rhs));
+                }
+                _ => break,
+            }
+        }
+        Ok(node)
+    }
+
+    // term := factor (('*' | '/') factor)*
+    fn parse_term(&mut self) -> Result<Expr, ExprError> {
+        let mut node = self.parse_factor()?;
+        loop {
+            match self.peek() {
+                Some(Token::Star) => {
+                    self.advance();
+                    let rhs = self.parse_factor()?;
+                    node = Expr::Mul(Box::new(node), Box::new(rhs));
+                }
+                Some(Token::Slash) => {
+                    self.advance();
+                    let rhs = self.parse_factor()?;
+                    if matches!(rhs, Expr::Number(n) if n == 0.0) {
+                        return Err(ExprError::DivisionByZero);
+                    }
+                    node = Expr::Div(Box::new(node), Box::new(rhs));
+                }
+                _ => break,
+            }
+        }
+        Ok(node)
+    }
+
+    // factor := number | ident | '(' expr ')' | '-' factor
+    fn parse_factor(&mut self) -> Result<Expr, ExprError> {
+        match self.advance().ok_or(ExprError::UnexpectedEnd)? {
+            Token::Number(n) => Ok(Expr::Number(n)),
+            Token::Ident(name) => {
+                if KNOWN_FEATURES.contains(&name.as_str()) {
+                    Ok(Expr::Feature(name))
+                } else {
+                    Err(ExprError::UnknownFeature(name))
+                }
+            }
+            Token::Minus => {
+                let inner = self.parse_factor()?;
+                Ok(Expr::Sub(Box::new(Expr::Number(0.0)), Box::new(inner)))
+            }
+            Token::LParen => {
+                let inner = self.parse_expr()?;
+                match self.advance() {
+                    Some(Token::RParen) => Ok(inner),
+                    Some(other) => Err(ExprError::UnexpectedToken(format!("{other:?}"))),
+                    None => Err(ExprError::UnexpectedEnd),
+                }
+            }
+            other => Err(ExprError::UnexpectedToken(format!("{other:?}"))),
+        }
+    }
+}
+
+/// Parse and validate an expression, rejecting unknown feature identifiers
+/// and literal division by zero up front.
+pub fn parse(input: &str) -> Result<Expr, ExprError> {
+    let tokens = tokenize(input)?;
+    let mut parser = Parser { tokens, pos: 0 };
+    let expr = parser.parse_expr()?;
+    if parser.pos != parser.tokens.len() {
+        return Err(ExprError::UnexpectedToken(format!(
+            "{:?}",
+            parser.tokens[parser.pos]
+        )));
+    }
+    Ok(expr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rms_times_two_doubles_rms() {
+        let expr = parse("rms * 2.0").unwrap();
+        let frame = AnalysisFrame {
+            rms: 0.4,
+            ..AnalysisFrame::silent(0.0)
+        };
+        assert!((expr.eval(&frame) - 0.8).abs() < 1e-6);
+    }
+
+    #[test]
+    fn unknown_identifier_rejected_at_parse_time() {
+        assert_eq!(
+            parse("bogus_feature + 1.0"),
+            Err(ExprError::UnknownFeature("bogus_feature".into()))
+        );
+    }
+
+    #[test]
+    fn literal_division_by_zero_rejected_at_parse_time() {
+        assert_eq!(parse("rms / 0"), Err(ExprError::DivisionByZero));
+    }
+}
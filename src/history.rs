@@ -0,0 +1,212 @@
+//! Buffers recent analysis frames for timeline queries at arbitrary times,
+//! so renderers running at a different rate than analysis don't have to
+//! track frame boundaries themselves.
+
+use std::collections::VecDeque;
+
+use crate::analysis::AnalysisFrame;
+
+/// Rolling buffer of analysis frames, queryable by timestamp.
+#[derive(Debug, Clone, Default)]
+pub struct AnalysisHandle {
+    frames: VecDeque<AnalysisFrame>,
+    /// Retain at most this many of the most recent frames, evicting the
+    /// oldest on push once exceeded. `None` (the default) retains every
+    /// frame forever, which is fine for a bounded test or a short session
+    /// but will exhaust memory over a multi-day installation. See
+    /// [`Self::with_capacity`].
+    max_frames: Option<usize>,
+    first_timestamp: Option<f64>,
+    last_timestamp: f64,
+}
+
+impl AnalysisHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Retain only the most recent `max_frames` frames, bounding memory use
+    /// for a long-running session. [`Self::duration_seconds`] still tracks
+    /// the whole session's elapsed time accurately, but [`Self::sample_at`]
+    /// and [`Self::sample_interpolated`] can only see the retained window
+    /// once older frames have been evicted.
+    pub fn with_capacity(max_frames: usize) -> Self {
+        Self {
+            max_frames: Some(max_frames),
+            ..Self::default()
+        }
+    }
+
+    pub fn push(&mut self, frame: AnalysisFrame) {
+        self.first_timestamp.get_or_insert(frame.timestamp);
+        self.last_timestamp = frame.timestamp;
+        self.frames.push_back(frame);
+        if let Some(max_frames) = self.max_frames {
+            while self.frames.len() > max_frames {
+                self.frames.pop_front();
+            }
+        }
+    }
+
+    /// Number of frames currently retained (bounded by capacity if one was
+    /// configured).
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Elapsed time between the first frame ever pushed and the most recent
+    /// one, accurate for the whole session even after a configured capacity
+    /// has evicted the earliest frames.
+    pub fn duration_seconds(&self) -> f64 {
+        match self.first_timestamp {
+            Some(first) => self.last_timestamp - first,
+            None => 0.0,
+        }
+    }
+
+    /// Nearest frame at or before `time`, falling back to the first frame if
+    /// `time` precedes all of them. `None` if the handle is empty.
+    pub fn sample_at(&self, time: f64) -> Option<&AnalysisFrame> {
+        if self.frames.is_empty() {
+            return None;
+        }
+        match self.frames.iter().rposition(|frame| frame.timestamp <= time) {
+            Some(idx) => self.frames.get(idx),
+            None => self.frames.front(),
+        }
+    }
+
+    /// Linearly interpolate each numeric field between the frames bracketing
+    /// `time`, falling back to the nearest endpoint frame when `time` is out
+    /// of range. `None` if the handle is empty.
+    pub fn sample_interpolated(&self, time: f64) -> Option<AnalysisFrame> {
+        let first = self.frames.front()?;
+        let last = self.frames.back()?;
+
+        if time <= first.timestamp {
+            return Some(first.clone());
+        }
+        if time >= last.timestamp {
+            return Some(last.clone());
+        }
+
+        let after_idx = self.frames.iter().position(|frame| frame.timestamp > time)?;
+        let before = &self.frames[after_idx - 1];
+        let after = &self.frames[after_idx];
+        let span = after.timestamp - before.timestamp;
+        let t = if span > 0.0 {
+            ((time - before.timestamp) / span) as f32
+        } else {
+            0.0
+        };
+        Some(lerp_frame(before, after, t))
+    }
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+fn lerp_frame(a: &AnalysisFrame, b: &AnalysisFrame, t: f32) -> AnalysisFrame {
+    AnalysisFrame {
+        timestamp: a.timestamp + (b.timestamp - a.timestamp) * t as f64,
+        rms: lerp(a.rms, b.rms, t),
+        rms_db: lerp(a.rms_db, b.rms_db, t),
+        rms_envelope: lerp(a.rms_envelope, b.rms_envelope, t),
+        balance: lerp(a.balance, b.balance, t),
+        input_rms: lerp(a.input_rms, b.input_rms, t),
+        beat_confidence: lerp(a.beat_confidence, b.beat_confidence, t),
+        low_band_energy: lerp(a.low_band_energy, b.low_band_energy, t),
+        mid_band_energy: lerp(a.mid_band_energy, b.mid_band_energy, t),
+        high_band_energy: lerp(a.high_band_energy, b.high_band_energy, t),
+        low_band_energy_smoothed: lerp(a.low_band_energy_smoothed, b.low_band_energy_smoothed, t),
+        mid_band_energy_smoothed: lerp(a.mid_band_energy_smoothed, b.mid_band_energy_smoothed, t),
+        high_band_energy_smoothed: lerp(a.high_band_energy_smoothed, b.high_band_energy_smoothed, t),
+        low_band_energy_ballistic: lerp(a.low_band_energy_ballistic, b.low_band_energy_ballistic, t),
+        mid_band_energy_ballistic: lerp(a.mid_band_energy_ballistic, b.mid_band_energy_ballistic, t),
+        high_band_energy_ballistic: lerp(a.high_band_energy_ballistic, b.high_band_energy_ballistic, t),
+        low_band_energy_peak: lerp(a.low_band_energy_peak, b.low_band_energy_peak, t),
+        mid_band_energy_peak: lerp(a.mid_band_energy_peak, b.mid_band_energy_peak, t),
+        high_band_energy_peak: lerp(a.high_band_energy_peak, b.high_band_energy_peak, t),
+        harmonic_energy: lerp(a.harmonic_energy, b.harmonic_energy, t),
+        percussive_energy: lerp(a.percussive_energy, b.percussive_energy, t),
+        chroma: std::array::from_fn(|i| lerp(a.chroma[i], b.chroma[i], t)),
+        spectral_crest: lerp(a.spectral_crest, b.spectral_crest, t),
+        spectral_spread: lerp(a.spectral_spread, b.spectral_spread, t),
+        spectral_flux: lerp(a.spectral_flux, b.spectral_flux, t),
+        activity: if t < 0.5 { a.activity } else { b.activity },
+        beat_in_bar: if t < 0.5 { a.beat_in_bar } else { b.beat_in_bar },
+        // An interpolated instant never represents a real onset.
+        is_downbeat: false,
+        // Vector-valued and not meaningfully lerp-able bin-by-bin; take the
+        // nearer frame's, same as the other discrete fields above.
+        log_spectrum: if t < 0.5 { a.log_spectrum.clone() } else { b.log_spectrum.clone() },
+        // An interpolated instant never represents a genuine cold-start block.
+        warming_up: false,
+        beat_phase: lerp(a.beat_phase, b.beat_phase, t),
+        // Extractor-contributed features aren't necessarily meaningful to
+        // blend (a classifier's output isn't linear in general), so take the
+        // nearer frame's, same as the other discrete fields above.
+        extra: if t < 0.5 { a.extra.clone() } else { b.extra.clone() },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame_with_rms(timestamp: f64, rms: f32) -> AnalysisFrame {
+        AnalysisFrame {
+            rms,
+            ..AnalysisFrame::silent(timestamp)
+        }
+    }
+
+    #[test]
+    fn interpolates_rms_between_bracketing_frames() {
+        let mut handle = AnalysisHandle::new();
+        handle.push(frame_with_rms(0.0, 0.0));
+        handle.push(frame_with_rms(1.0, 1.0));
+
+        let sampled = handle.sample_interpolated(0.5).expect("frames present");
+        assert!((sampled.rms - 0.5).abs() < 1e-6, "got {}", sampled.rms);
+    }
+
+    #[test]
+    fn out_of_range_queries_clamp_to_endpoints() {
+        let mut handle = AnalysisHandle::new();
+        handle.push(frame_with_rms(0.0, 0.0));
+        handle.push(frame_with_rms(1.0, 1.0));
+
+        assert_eq!(handle.sample_interpolated(-1.0).unwrap().rms, 0.0);
+        assert_eq!(handle.sample_interpolated(2.0).unwrap().rms, 1.0);
+    }
+
+    #[test]
+    fn sample_at_takes_the_nearest_prior_frame_with_no_blending() {
+        let mut handle = AnalysisHandle::new();
+        handle.push(frame_with_rms(0.0, 0.0));
+        handle.push(frame_with_rms(1.0, 1.0));
+
+        assert_eq!(handle.sample_at(0.9).unwrap().rms, 0.0);
+        assert_eq!(handle.sample_at(1.0).unwrap().rms, 1.0);
+    }
+
+    #[test]
+    fn capacity_bounds_retained_frames_while_duration_keeps_growing() {
+        let mut handle = AnalysisHandle::with_capacity(10);
+        for i in 0..1_000 {
+            handle.push(frame_with_rms(i as f64 * 0.1, 0.0));
+        }
+
+        assert_eq!(handle.len(), 10);
+        assert!((handle.duration_seconds() - 99.9).abs() < 1e-6, "got {}", handle.duration_seconds());
+        // The retained window no longer reaches back to time 0: only the
+        // most recent frames survive eviction.
+        assert!((handle.sample_at(0.0).unwrap().timestamp - 99.0).abs() < 1e-6);
+    }
+}
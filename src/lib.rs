@@ -0,0 +1,33 @@
+//! Core engine for the music visualiser: turns analysed audio features into
+//! scene parameters.
+
+pub mod analysis;
+pub mod assets;
+pub mod audio;
+pub mod capture;
+pub mod config;
+pub mod engine;
+pub mod expr;
+pub mod history;
+pub mod mapping;
+pub mod metrics;
+pub mod pipeline;
+pub mod playback;
+pub mod recording;
+pub mod render_loop;
+pub mod scene;
+pub mod scheduler;
+pub mod schema;
+pub mod signals;
+pub mod sonify;
+pub mod spectral;
+pub mod stereo;
+pub mod summary;
+pub mod timeline;
+pub mod wav;
+
+pub use analysis::AnalysisFrame;
+pub use assets::AssetHandle;
+pub use engine::AnalysisEngine;
+pub use mapping::MappingDescriptor;
+pub use scene::{SceneDescriptor, SceneInstance, SceneKind};
@@ -0,0 +1,787 @@
+//! Maps analysed audio features onto scene parameters.
+
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::AnalysisFrame;
+use crate::expr::{self, Expr, ExprError};
+
+/// Shaping curve applied to a normalised `0..1` feature reading before it is
+/// spread across a mapping's output range.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Curve {
+    Linear,
+    Exponential,
+    Logarithmic,
+}
+
+/// Which smoothing strategy a mapping applies to its mapped value before
+/// slew limiting. See [`MappingDescriptor::smoothing`] and
+/// [`MappingDescriptor::one_euro`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Default)]
+pub enum SmoothingMode {
+    /// Exponential moving average at a fixed retention factor.
+    #[default]
+    Ema,
+    /// [One-euro filter](https://cristal.univ-lille.fr/~casiez/1euro/): a
+    /// low-pass filter whose cutoff frequency widens with the signal's
+    /// speed, cutting lag on fast changes while still smoothing slow,
+    /// noisy drift.
+    OneEuro,
+}
+
+/// Tuning for [`SmoothingMode::OneEuro`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct OneEuroConfig {
+    /// Cutoff frequency (Hz) used when the signal is still; lower values
+    /// smooth more at rest.
+    pub min_cutoff: f32,
+    /// How much the cutoff widens per unit of signal speed; higher values
+    /// cut more lag from fast changes at the cost of more noise passing
+    /// through during them.
+    pub beta: f32,
+    /// Cutoff frequency (Hz) used to smooth the speed estimate itself.
+    pub d_cutoff: f32,
+}
+
+impl Default for OneEuroConfig {
+    fn default() -> Self {
+        Self {
+            min_cutoff: 1.0,
+            beta: 0.0,
+            d_cutoff: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct OneEuroState {
+    initialized: bool,
+    value: f32,
+    speed: f32,
+}
+
+fn one_euro_alpha(cutoff: f32, dt: f32) -> f32 {
+    let tau = 1.0 / (2.0 * std::f32::consts::PI * cutoff);
+    1.0 / (1.0 + tau / dt)
+}
+
+fn one_euro_low_pass(previous: f32, value: f32, alpha: f32) -> f32 {
+    alpha * value + (1.0 - alpha) * previous
+}
+
+/// A color in linear `0..1` RGB, as sampled from a [`Gradient`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RgbColor {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+impl RgbColor {
+    fn lerp(self, other: RgbColor, t: f32) -> RgbColor {
+        RgbColor {
+            r: lerp(self.r, other.r, t),
+            g: lerp(self.g, other.g, t),
+            b: lerp(self.b, other.b, t),
+        }
+    }
+}
+
+/// One color anchor in a [`Gradient`], at a `0..1` position along it.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GradientStop {
+    pub position: f32,
+    pub color: RgbColor,
+}
+
+/// An ordered list of color stops sampled by [`Self::sample`] to turn a
+/// normalised `0..1` feature reading into an RGB color, for mappings that
+/// drive a scene's tint rather than a single scalar. Stops must be given in
+/// ascending `position` order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Gradient {
+    pub stops: Vec<GradientStop>,
+}
+
+impl Gradient {
+    pub fn new(stops: Vec<GradientStop>) -> Self {
+        Self { stops }
+    }
+
+    /// Sample the gradient at `t` (clamped to `0..1`), linearly interpolating
+    /// between the two stops bracketing it. Returns black if no stops are
+    /// configured, and the nearest stop's color if `t` falls outside every
+    /// bracket.
+    pub fn sample(&self, t: f32) -> RgbColor {
+        let t = t.clamp(0.0, 1.0);
+        let Some(first) = self.stops.first() else {
+            return RgbColor { r: 0.0, g: 0.0, b: 0.0 };
+        };
+        if t <= first.position {
+            return first.color;
+        }
+        for window in self.stops.windows(2) {
+            let [a, b] = window else { unreachable!() };
+            if t <= b.position {
+                let span = (b.position - a.position).max(1e-6);
+                return a.color.lerp(b.color, (t - a.position) / span);
+            }
+        }
+        self.stops.last().expect("checked non-empty above").color
+    }
+}
+
+/// Describes how a single analysis feature drives a single scene parameter.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MappingDescriptor {
+    pub source: String,
+    pub target: String,
+    /// When set, overrides `source`: the raw value is computed by evaluating
+    /// this arithmetic expression against the analysis frame instead of
+    /// reading a single named feature.
+    pub expression: Option<String>,
+    #[serde(skip)]
+    compiled_expression: Option<Expr>,
+    /// When set (and no `expression` is set), overrides `source`: the raw
+    /// value is the sum of each named feature times its weight, clamped to
+    /// `0..1`. Weights needn't sum to 1. Simpler than [`Self::with_expression`]
+    /// for the common case of blending a handful of features linearly.
+    #[serde(default)]
+    pub sources: Option<Vec<(String, f32)>>,
+    pub min: f32,
+    pub max: f32,
+    pub curve: Curve,
+    /// Which smoothing strategy [`Self::evaluate`] applies to the mapped
+    /// value.
+    pub smoothing_mode: SmoothingMode,
+    /// Exponential smoothing factor in `0..1`; `0.0` disables smoothing.
+    /// Only used when `smoothing_mode` is [`SmoothingMode::Ema`].
+    pub smoothing: f32,
+    /// Only used when `smoothing_mode` is [`SmoothingMode::OneEuro`].
+    pub one_euro: OneEuroConfig,
+    #[serde(skip)]
+    one_euro_state: OneEuroState,
+    /// Below this raw feature value the mapping is considered inactive.
+    pub gate: Option<f32>,
+    /// When gated, hold the last output instead of snapping to `min`.
+    pub hold_below_gate: bool,
+    /// Maximum change in output per second, in output units. `None` disables
+    /// slew limiting.
+    pub max_slew: Option<f32>,
+    /// When true, `smoothing` is a retention factor per *beat* rather than
+    /// per evaluation: the same value feels the same regardless of tempo.
+    /// Falls back to treating `smoothing` as a raw per-frame factor when no
+    /// tempo is known yet.
+    pub smoothing_in_beats: bool,
+    /// When set, [`Self::evaluate_color`] samples this gradient at the raw,
+    /// curve-shaped feature reading instead of producing a scalar.
+    pub gradient: Option<Gradient>,
+    /// When set, [`MappingMatrix::evaluate_all`] suppresses this mapping's
+    /// [`ParameterUpdate`] whenever its output has moved less than this much
+    /// since the last one it emitted, so a downstream MIDI/OSC sink isn't
+    /// flooded with imperceptible jitter. `None` (the default) emits every
+    /// evaluation.
+    #[serde(default)]
+    pub min_delta: Option<f32>,
+    /// When `false`, [`MappingMatrix::evaluate_all`] skips this mapping
+    /// entirely rather than calling [`Self::evaluate`]: no
+    /// [`ParameterUpdate`] is produced, and `last_value`/smoothing state is
+    /// left untouched so re-enabling resumes right where it left off instead
+    /// of snapping. Toggle with [`MappingMatrix::set_enabled`].
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(skip)]
+    last_value: f32,
+    #[serde(skip)]
+    last_emitted: Option<f32>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl MappingDescriptor {
+    pub fn new(source: impl Into<String>, target: impl Into<String>) -> Self {
+        Self {
+            source: source.into(),
+            target: target.into(),
+            expression: None,
+            compiled_expression: None,
+            sources: None,
+            min: 0.0,
+            max: 1.0,
+            curve: Curve::Linear,
+            smoothing_mode: SmoothingMode::default(),
+            smoothing: 0.0,
+            one_euro: OneEuroConfig::default(),
+            one_euro_state: OneEuroState::default(),
+            gate: None,
+            hold_below_gate: false,
+            max_slew: None,
+            smoothing_in_beats: false,
+            gradient: None,
+            min_delta: None,
+            enabled: true,
+            last_value: 0.0,
+            last_emitted: None,
+        }
+    }
+
+    /// Build a mapping whose raw value comes from evaluating an arithmetic
+    /// expression over known feature names instead of a single `source`.
+    /// The expression is parsed and validated immediately, so unknown
+    /// features or a literal division by zero are reported here rather than
+    /// at evaluation time.
+    pub fn with_expression(
+        target: impl Into<String>,
+        expression: impl Into<String>,
+    ) -> Result<Self, ExprError> {
+        let expression = expression.into();
+        let compiled = expr::parse(&expression)?;
+        let mut mapping = Self::new(String::new(), target);
+        mapping.expression = Some(expression);
+        mapping.compiled_expression = Some(compiled);
+        Ok(mapping)
+    }
+
+    fn raw_value(&self, frame: &AnalysisFrame) -> f32 {
+        match &self.compiled_expression {
+            Some(expr) => expr.eval(frame),
+            None => match &self.sources {
+                Some(sources) => sources
+                    .iter()
+                    .map(|(name, weight)| frame.feature(name).unwrap_or(0.0) * weight)
+                    .sum::<f32>()
+                    .clamp(0.0, 1.0),
+                None => frame.feature(&self.source).unwrap_or(0.0),
+            },
+        }
+    }
+
+    fn apply_curve(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self.curve {
+            Curve::Linear => t,
+            Curve::Exponential => t * t,
+            Curve::Logarithmic => t.sqrt(),
+        }
+    }
+
+    /// Map a raw feature reading to the descriptor's output range via its
+    /// curve. Does not apply the gate or smoothing.
+    pub fn map_value(&self, raw: f32) -> f32 {
+        let shaped = self.apply_curve(raw);
+        self.min + shaped * (self.max - self.min)
+    }
+
+    fn effective_smoothing(&self, dt: f32, tempo_bpm: Option<f32>) -> f32 {
+        if !self.smoothing_in_beats {
+            return self.smoothing;
+        }
+        match tempo_bpm {
+            Some(bpm) if bpm > 0.0 => {
+                let beat_duration = 60.0 / bpm;
+                self.smoothing.powf(dt / beat_duration)
+            }
+            // No tempo yet: treat the configured value as a raw factor.
+            _ => self.smoothing,
+        }
+    }
+
+    /// One-euro filter: low-pass the value with a cutoff that widens with
+    /// the estimated speed (itself low-passed at `d_cutoff`), so a fast
+    /// change is tracked with little lag while a still, noisy signal is
+    /// smoothed as if at `min_cutoff`.
+    fn apply_one_euro(&mut self, value: f32, dt: f32) -> f32 {
+        let dt = dt.max(1e-6);
+        if !self.one_euro_state.initialized {
+            self.one_euro_state = OneEuroState {
+                initialized: true,
+                value,
+                speed: 0.0,
+            };
+            return value;
+        }
+
+        let speed = (value - self.one_euro_state.value) / dt;
+        let speed_alpha = one_euro_alpha(self.one_euro.d_cutoff, dt);
+        let speed = one_euro_low_pass(self.one_euro_state.speed, speed, speed_alpha);
+
+        let cutoff = self.one_euro.min_cutoff + self.one_euro.beta * speed.abs();
+        let value_alpha = one_euro_alpha(cutoff, dt);
+        let filtered = one_euro_low_pass(self.one_euro_state.value, value, value_alpha);
+
+        self.one_euro_state.value = filtered;
+        self.one_euro_state.speed = speed;
+        filtered
+    }
+
+    /// Resolve this mapping's output for a given analysis frame and time
+    /// step, updating its internal smoothing and slew state. `tempo_bpm` is
+    /// only consulted when `smoothing_in_beats` is set.
+    pub fn evaluate(&mut self, frame: &AnalysisFrame, dt: f32, tempo_bpm: Option<f32>) -> f32 {
+        let raw = self.raw_value(frame);
+
+        if let Some(gate) = self.gate {
+            if raw < gate {
+                let value = if self.hold_below_gate {
+                    self.last_value
+                } else {
+                    self.min
+                };
+                self.last_value = value;
+                return value;
+            }
+        }
+
+        let mapped = self.map_value(raw);
+        let smoothed = match self.smoothing_mode {
+            SmoothingMode::Ema => {
+                let smoothing = self.effective_smoothing(dt, tempo_bpm);
+                if smoothing > 0.0 {
+                    self.last_value + (mapped - self.last_value) * (1.0 - smoothing)
+                } else {
+                    mapped
+                }
+            }
+            SmoothingMode::OneEuro => self.apply_one_euro(mapped, dt),
+        };
+
+        let value = if let Some(max_slew) = self.max_slew {
+            let max_step = max_slew * dt.max(0.0);
+            let delta = (smoothed - self.last_value).clamp(-max_step, max_step);
+            self.last_value + delta
+        } else {
+            smoothed
+        };
+
+        // Smoothing and slew can momentarily overshoot the configured range
+        // (e.g. easing into a step input), so floor/ceil to it here
+        // regardless of what produced `value`.
+        let (low, high) = if self.min <= self.max {
+            (self.min, self.max)
+        } else {
+            (self.max, self.min)
+        };
+        let value = value.clamp(low, high);
+
+        self.last_value = value;
+        value
+    }
+
+    /// Resolve this mapping's [`Gradient`] output for a given analysis
+    /// frame, if one is configured. Samples the gradient at the raw feature
+    /// reading after curve shaping, independent of `min`/`max`/smoothing,
+    /// which only apply to the scalar output of [`Self::evaluate`].
+    pub fn evaluate_color(&self, frame: &AnalysisFrame) -> Option<ColorUpdate> {
+        let gradient = self.gradient.as_ref()?;
+        let t = self.apply_curve(self.raw_value(frame));
+        Some(ColorUpdate {
+            target: self.target.clone(),
+            color: gradient.sample(t),
+        })
+    }
+}
+
+/// One mapping's resolved output for a block: the scene parameter it
+/// targets and the value it should be set to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParameterUpdate {
+    pub target: String,
+    pub value: f32,
+}
+
+/// One mapping's resolved [`Gradient`] output for a block: the scene tint
+/// parameter it targets and the color it should be set to. Produced
+/// alongside [`ParameterUpdate`]s by mappings with a configured
+/// [`MappingDescriptor::gradient`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorUpdate {
+    pub target: String,
+    pub color: RgbColor,
+}
+
+/// A collection of mappings evaluated together against one analysis frame
+/// per block, producing the [`ParameterUpdate`]s a
+/// [`crate::pipeline::Pipeline`] applies to scenes.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MappingMatrix {
+    pub mappings: Vec<MappingDescriptor>,
+}
+
+impl MappingMatrix {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, mapping: MappingDescriptor) {
+        self.mappings.push(mapping);
+    }
+
+    /// Enable or disable the mapping at `index` without removing it: a
+    /// disabled mapping produces no [`ParameterUpdate`] but keeps its
+    /// smoothing state, so re-enabling it resumes from its prior value
+    /// instead of jumping. Does nothing if `index` is out of range.
+    pub fn set_enabled(&mut self, index: usize, enabled: bool) {
+        if let Some(mapping) = self.mappings.get_mut(index) {
+            mapping.enabled = enabled;
+        }
+    }
+
+    /// Evaluate every mapping against `frame`, advancing each one's own
+    /// smoothing/slew state. A mapping with [`MappingDescriptor::min_delta`]
+    /// set is still evaluated (its smoothing state keeps advancing) but
+    /// produces no [`ParameterUpdate`] when its output hasn't moved far
+    /// enough from the last one emitted.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "evaluate_all", skip(self, frame, tempo_bpm), fields(mapping_count = self.mappings.len()))
+    )]
+    pub fn evaluate_all(&mut self, frame: &AnalysisFrame, dt: f32, tempo_bpm: Option<f32>) -> Vec<ParameterUpdate> {
+        self.mappings
+            .iter_mut()
+            .filter_map(|mapping| {
+                if !mapping.enabled {
+                    return None;
+                }
+                let value = mapping.evaluate(frame, dt, tempo_bpm);
+                if let Some(min_delta) = mapping.min_delta {
+                    if let Some(last_emitted) = mapping.last_emitted {
+                        if (value - last_emitted).abs() < min_delta {
+                            return None;
+                        }
+                    }
+                }
+                mapping.last_emitted = Some(value);
+                Some(ParameterUpdate {
+                    target: mapping.target.clone(),
+                    value,
+                })
+            })
+            .collect()
+    }
+
+    /// Evaluate every mapping across a whole slice of frames at once, for
+    /// offline rendering of a recorded session: `dt` between frames is
+    /// derived from consecutive `timestamp`s, and each mapping's smoothing
+    /// and slew state carries over from one frame to the next exactly as it
+    /// would in [`Self::evaluate_all`] called frame-by-frame.
+    pub fn evaluate_timeline(&mut self, frames: &[AnalysisFrame]) -> Vec<Vec<ParameterUpdate>> {
+        let mut previous_timestamp: Option<f64> = None;
+        frames
+            .iter()
+            .map(|frame| {
+                let dt = previous_timestamp
+                    .map(|previous| (frame.timestamp - previous).max(0.0) as f32)
+                    .unwrap_or(0.0);
+                previous_timestamp = Some(frame.timestamp);
+                self.evaluate_all(frame, dt, None)
+            })
+            .collect()
+    }
+
+    /// Resolve every mapping's [`Gradient`] output for `frame`, alongside
+    /// [`Self::evaluate_all`]'s scalar updates. Mappings with no configured
+    /// gradient produce nothing here.
+    pub fn evaluate_colors(&self, frame: &AnalysisFrame) -> Vec<ColorUpdate> {
+        self.mappings.iter().filter_map(|mapping| mapping.evaluate_color(frame)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame_with_rms(rms: f32) -> AnalysisFrame {
+        AnalysisFrame {
+            rms,
+            ..AnalysisFrame::silent(0.0)
+        }
+    }
+
+    #[test]
+    fn min_delta_suppresses_a_near_identical_update_but_emits_a_real_change() {
+        let mut mapping = MappingDescriptor::new("rms", "strobe.intensity");
+        mapping.min_delta = Some(0.05);
+        let mut matrix = MappingMatrix::new();
+        matrix.push(mapping);
+
+        let first = matrix.evaluate_all(&frame_with_rms(0.5), 1.0, None);
+        assert_eq!(first.len(), 1, "first evaluation always emits");
+
+        let suppressed = matrix.evaluate_all(&frame_with_rms(0.51), 1.0, None);
+        assert!(suppressed.is_empty(), "a near-identical frame should suppress the update");
+
+        let emitted = matrix.evaluate_all(&frame_with_rms(0.9), 1.0, None);
+        assert_eq!(emitted.len(), 1, "a real change should still emit");
+    }
+
+    #[test]
+    fn disabling_a_mapping_suppresses_updates_and_resumes_from_its_smoothed_value() {
+        let mut mapping = MappingDescriptor::new("rms", "strobe.intensity");
+        mapping.smoothing = 0.9;
+        let mut matrix = MappingMatrix::new();
+        matrix.push(mapping);
+
+        // Warm up the smoothing state with a few evaluations.
+        for _ in 0..5 {
+            matrix.evaluate_all(&frame_with_rms(0.8), 1.0 / 30.0, None);
+        }
+        let smoothed_before_disable = matrix.mappings[0].last_value;
+
+        matrix.set_enabled(0, false);
+        let updates = matrix.evaluate_all(&frame_with_rms(0.1), 1.0 / 30.0, None);
+        assert!(updates.is_empty(), "a disabled mapping should produce no update");
+        assert_eq!(
+            matrix.mappings[0].last_value, smoothed_before_disable,
+            "disabled mapping should retain its last smoothed value untouched"
+        );
+
+        matrix.set_enabled(0, true);
+        let resumed = matrix.evaluate_all(&frame_with_rms(0.8), 1.0 / 30.0, None);
+        assert_eq!(resumed.len(), 1);
+        // Resuming from the same smoothed value with the same input should
+        // continue easing toward it rather than jumping, i.e. it should
+        // still be close to where it left off.
+        assert!((resumed[0].value - smoothed_before_disable).abs() < 0.1, "got {}", resumed[0].value);
+    }
+
+    #[test]
+    fn gate_suppresses_low_readings() {
+        let mut mapping = MappingDescriptor::new("rms", "strobe.intensity");
+        mapping.gate = Some(0.3);
+
+        assert_eq!(mapping.evaluate(&frame_with_rms(0.1), 1.0, None), mapping.min);
+        assert_eq!(mapping.evaluate(&frame_with_rms(0.2), 1.0, None), mapping.min);
+
+        let engaged = mapping.evaluate(&frame_with_rms(0.5), 1.0, None);
+        assert_eq!(engaged, mapping.map_value(0.5));
+    }
+
+    #[test]
+    fn max_slew_limits_step_size() {
+        let mut mapping = MappingDescriptor::new("rms", "strobe.intensity");
+        mapping.max_slew = Some(0.5); // units per second
+
+        let dt = 1.0 / 60.0;
+        let mut reached_full = false;
+        for _ in 0..180 {
+            let value = mapping.evaluate(&frame_with_rms(1.0), dt, None);
+            assert!(value <= 1.0 + f32::EPSILON);
+            if value >= 1.0 {
+                reached_full = true;
+            } else {
+                assert!(value < 1.0);
+            }
+        }
+        assert!(reached_full, "should eventually reach the target after enough steps");
+
+        // A single step at this slew rate and dt cannot jump straight to 1.0.
+        let mut fresh = MappingDescriptor::new("rms", "strobe.intensity");
+        fresh.max_slew = Some(0.5);
+        let first_step = fresh.evaluate(&frame_with_rms(1.0), dt, None);
+        assert!(first_step < 1.0);
+    }
+
+    #[test]
+    fn evaluate_clamps_output_to_configured_range_even_with_aggressive_smoothing() {
+        let mut mapping = MappingDescriptor::new("rms", "strobe.intensity");
+        // A misconfigured smoothing factor above 1.0 makes the EMA
+        // extrapolate rather than interpolate, which would otherwise send
+        // the output well outside [min, max] on a step input.
+        mapping.smoothing = 5.0;
+        let dt = 1.0 / 60.0;
+
+        for _ in 0..10 {
+            let value = mapping.evaluate(&frame_with_rms(1.0), dt, None);
+            assert!(
+                (mapping.min..=mapping.max).contains(&value),
+                "value {value} escaped [{}, {}]",
+                mapping.min,
+                mapping.max
+            );
+        }
+    }
+
+    #[test]
+    fn expression_source_combines_features() {
+        let mut mapping = MappingDescriptor::with_expression("led.brightness", "rms * 2.0")
+            .expect("valid expression");
+        let value = mapping.evaluate(&frame_with_rms(0.3), 1.0, None);
+        assert!((value - mapping.map_value(0.6)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn weighted_sources_combine_into_the_mapping_input() {
+        let mut mapping = MappingDescriptor::new("", "led.brightness");
+        mapping.sources = Some(vec![("low_band_energy".to_string(), 0.7), ("rms".to_string(), 0.3)]);
+
+        let frame = AnalysisFrame {
+            low_band_energy: 0.5,
+            rms: 0.4,
+            ..AnalysisFrame::silent(0.0)
+        };
+
+        let value = mapping.evaluate(&frame, 1.0, None);
+        let expected_raw = 0.7 * 0.5 + 0.3 * 0.4;
+        assert!((value - mapping.map_value(expected_raw)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn expression_rejects_unknown_feature_at_add_time() {
+        let result = MappingDescriptor::with_expression("led.brightness", "nonsense * 2.0");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn beat_relative_smoothing_adapts_to_tempo() {
+        let dt = 1.0 / 30.0;
+
+        let mut slow = MappingDescriptor::new("rms", "strobe.intensity");
+        slow.smoothing = 0.9;
+        slow.smoothing_in_beats = true;
+        let slow_value = slow.evaluate(&frame_with_rms(1.0), dt, Some(60.0));
+
+        let mut fast = MappingDescriptor::new("rms", "strobe.intensity");
+        fast.smoothing = 0.9;
+        fast.smoothing_in_beats = true;
+        let fast_value = fast.evaluate(&frame_with_rms(1.0), dt, Some(120.0));
+
+        // At double the tempo, the same beat has half the wall-clock time, so
+        // the per-frame retention is lower and the output moves further.
+        assert!(fast_value > slow_value);
+
+        // With no tempo known yet, the configured value is used verbatim
+        // rather than being converted against a beat duration.
+        let mut no_tempo = MappingDescriptor::new("rms", "strobe.intensity");
+        no_tempo.smoothing = 0.9;
+        no_tempo.smoothing_in_beats = true;
+        let raw_value = no_tempo.evaluate(&frame_with_rms(1.0), dt, None);
+        assert!((raw_value - mapping_value_with_factor(0.9, dt)).abs() < 1e-6);
+    }
+
+    fn mapping_value_with_factor(smoothing: f32, dt: f32) -> f32 {
+        let mut mapping = MappingDescriptor::new("rms", "strobe.intensity");
+        mapping.smoothing = smoothing;
+        mapping.evaluate(&frame_with_rms(1.0), dt, None)
+    }
+
+    #[test]
+    fn one_euro_tracks_fast_step_quicker_than_equivalent_ema_but_still_smooths_noise() {
+        let dt = 1.0 / 60.0;
+        let min_cutoff = 1.0;
+
+        let mut one_euro = MappingDescriptor::new("rms", "target");
+        one_euro.smoothing_mode = SmoothingMode::OneEuro;
+        one_euro.one_euro = OneEuroConfig {
+            min_cutoff,
+            beta: 0.7,
+            d_cutoff: 1.0,
+        };
+
+        // An EMA tuned to the same cutoff at rest, so the only difference is
+        // one-euro's adaptive widening once the signal starts moving fast.
+        let mut ema = MappingDescriptor::new("rms", "target");
+        ema.smoothing_mode = SmoothingMode::Ema;
+        ema.smoothing = 1.0 - one_euro_alpha(min_cutoff, dt);
+
+        let noisy_plateau = [0.48, 0.52, 0.49, 0.51, 0.50, 0.47, 0.53];
+        let mut one_euro_plateau = Vec::new();
+        let mut ema_plateau = Vec::new();
+        for &v in noisy_plateau.iter().cycle().take(60) {
+            one_euro_plateau.push(one_euro.evaluate(&frame_with_rms(v), dt, None));
+            ema_plateau.push(ema.evaluate(&frame_with_rms(v), dt, None));
+        }
+        let variance = |values: &[f32]| {
+            let mean = values.iter().sum::<f32>() / values.len() as f32;
+            values.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / values.len() as f32
+        };
+        // Skip the settling transient: both filters start from a cold
+        // state, so only the tail reflects steady-state noise rejection.
+        fn settled(values: &[f32]) -> &[f32] {
+            &values[values.len() - 20..]
+        }
+        assert!(
+            variance(settled(&one_euro_plateau)) < 0.001,
+            "one-euro should smooth the noisy plateau"
+        );
+        assert!(
+            variance(settled(&ema_plateau)) < 0.001,
+            "ema should smooth the noisy plateau too"
+        );
+
+        // A fast step to 1.0: one-euro's cutoff widens with the derivative
+        // and should converge faster than the statically-equivalent EMA.
+        let mut one_euro_value = 0.0;
+        let mut ema_value = 0.0;
+        for _ in 0..3 {
+            one_euro_value = one_euro.evaluate(&frame_with_rms(1.0), dt, None);
+            ema_value = ema.evaluate(&frame_with_rms(1.0), dt, None);
+        }
+        assert!(
+            one_euro_value > ema_value,
+            "one-euro {one_euro_value} should track the step faster than ema {ema_value}"
+        );
+    }
+
+    #[test]
+    fn evaluate_timeline_returns_per_frame_updates_with_smoothing_carried_across_frames() {
+        let mut matrix = MappingMatrix::new();
+        let mut rms_mapping = MappingDescriptor::new("rms", "strobe.intensity");
+        rms_mapping.smoothing = 0.9;
+        let high_mapping = MappingDescriptor::new("high_band_energy", "particles.tone");
+        matrix.push(rms_mapping);
+        matrix.push(high_mapping);
+
+        let frames: Vec<AnalysisFrame> = (0..3)
+            .map(|i| AnalysisFrame {
+                timestamp: i as f64 / 60.0,
+                ..frame_with_rms(1.0)
+            })
+            .collect();
+
+        let timeline = matrix.evaluate_timeline(&frames);
+
+        assert_eq!(timeline.len(), 3);
+        for updates in &timeline {
+            assert_eq!(updates.len(), 2);
+        }
+
+        let strobe_values: Vec<f32> = timeline
+            .iter()
+            .map(|updates| updates.iter().find(|u| u.target == "strobe.intensity").unwrap().value)
+            .collect();
+        assert!(
+            strobe_values[0] < strobe_values[1] && strobe_values[1] < strobe_values[2],
+            "smoothing should accumulate progressively closer to the target across frames: {strobe_values:?}"
+        );
+    }
+
+    #[test]
+    fn midpoint_of_a_black_to_white_gradient_is_mid_gray() {
+        let mut mapping = MappingDescriptor::new("rms", "particles.tint");
+        mapping.gradient = Some(Gradient::new(vec![
+            GradientStop {
+                position: 0.0,
+                color: RgbColor { r: 0.0, g: 0.0, b: 0.0 },
+            },
+            GradientStop {
+                position: 1.0,
+                color: RgbColor { r: 1.0, g: 1.0, b: 1.0 },
+            },
+        ]));
+
+        let update = mapping
+            .evaluate_color(&frame_with_rms(0.5))
+            .expect("gradient is configured");
+
+        assert_eq!(update.target, "particles.tint");
+        assert!((update.color.r - 0.5).abs() < 1e-6);
+        assert!((update.color.g - 0.5).abs() < 1e-6);
+        assert!((update.color.b - 0.5).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,89 @@
+//! Optional per-stage timing for [`crate::pipeline::Pipeline::process_block`].
+//! Disabled by default (`Pipeline::metrics` is `None`), so a caller that
+//! doesn't ask for timing pays no `Instant::now()` calls at all.
+
+use std::time::{Duration, Instant};
+
+/// Rolling timing stats for one pipeline stage: how many samples were
+/// recorded, their average duration, and the worst single duration seen.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StageStats {
+    samples: u64,
+    total: Duration,
+    worst: Duration,
+}
+
+impl StageStats {
+    fn record(&mut self, elapsed: Duration) {
+        self.samples += 1;
+        self.total += elapsed;
+        self.worst = self.worst.max(elapsed);
+    }
+
+    pub fn samples(&self) -> u64 {
+        self.samples
+    }
+
+    pub fn worst(&self) -> Duration {
+        self.worst
+    }
+
+    /// `Duration::ZERO` if no samples have been recorded yet.
+    pub fn average(&self) -> Duration {
+        if self.samples == 0 {
+            Duration::ZERO
+        } else {
+            self.total / self.samples as u32
+        }
+    }
+}
+
+/// Per-stage timing for one [`crate::pipeline::Pipeline`], accumulated
+/// across every `process_block` call while metrics are enabled.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PipelineMetrics {
+    pub analysis: StageStats,
+    pub mapping: StageStats,
+    pub render: StageStats,
+}
+
+impl PipelineMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Time `f`, record its duration against `stage`, and return `f`'s
+    /// result.
+    pub(crate) fn time<T>(stage: &mut StageStats, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = f();
+        stage.record(start.elapsed());
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread::sleep;
+
+    #[test]
+    fn stage_stats_track_samples_average_and_worst() {
+        let mut stats = StageStats::default();
+        stats.record(Duration::from_millis(10));
+        stats.record(Duration::from_millis(30));
+
+        assert_eq!(stats.samples(), 2);
+        assert_eq!(stats.worst(), Duration::from_millis(30));
+        assert_eq!(stats.average(), Duration::from_millis(20));
+    }
+
+    #[test]
+    fn time_records_a_nonzero_duration_for_slow_work() {
+        let mut stage = StageStats::default();
+        PipelineMetrics::time(&mut stage, || sleep(Duration::from_millis(1)));
+
+        assert_eq!(stage.samples(), 1);
+        assert!(stage.worst() > Duration::ZERO);
+    }
+}
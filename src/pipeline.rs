@@ -0,0 +1,252 @@
+//! Ties audio capture, analysis, mapping, and scene state into one
+//! frame-by-frame driver, so an app and a headless test push the exact same
+//! blocks through the exact same code path instead of each wiring the
+//! stages together separately.
+
+use std::path::Path;
+
+use crate::analysis::AnalysisFrame;
+use crate::audio::{AudioConfig, AudioEngine};
+use crate::engine::AnalysisEngine;
+use crate::mapping::{MappingMatrix, ParameterUpdate};
+use crate::metrics::PipelineMetrics;
+use crate::recording::{Recorder, RecordingExport};
+use crate::scene::RenderGraph;
+use crate::scheduler::{CueSheetError, Scheduler};
+
+/// The canonical audio -> analysis -> mapping -> scene driver. Construct
+/// once per show (or per test) and call [`Self::process_block`] with each
+/// block of captured or decoded samples.
+pub struct Pipeline {
+    pub audio: AudioEngine,
+    pub mappings: MappingMatrix,
+    pub render: RenderGraph,
+    pub scheduler: Scheduler,
+    /// Per-stage timing, collected only while `Some`. See
+    /// [`Self::enable_metrics`].
+    pub metrics: Option<PipelineMetrics>,
+    block_dt: f32,
+}
+
+impl Pipeline {
+    pub fn new(sample_rate: u32, block_size: usize) -> Self {
+        Self {
+            audio: AudioEngine::new(AnalysisEngine::new(sample_rate), AudioConfig::default(), block_size),
+            mappings: MappingMatrix::new(),
+            render: RenderGraph::new(),
+            scheduler: Scheduler::new(),
+            metrics: None,
+            block_dt: block_size as f32 / sample_rate as f32,
+        }
+    }
+
+    /// Load this pipeline's cue sheet, replacing any previously loaded cues.
+    pub fn load_cues(&mut self, path: &Path) -> Result<(), CueSheetError> {
+        self.scheduler = Scheduler::load_cues(path)?;
+        Ok(())
+    }
+
+    /// Start collecting per-stage timing on every subsequent
+    /// `process_block` call. Metrics collection is near-zero cost when not
+    /// enabled: no timer is read unless [`Self::metrics`] is `Some`.
+    pub fn enable_metrics(&mut self) {
+        self.metrics = Some(PipelineMetrics::new());
+    }
+
+    /// Per-stage timing accumulated over every `process_block` call, if [`Self::enable_metrics`]
+    /// has been called.
+    pub fn metrics(&self) -> Option<&PipelineMetrics> {
+        self.metrics.as_ref()
+    }
+
+    /// Push one block of mono samples through the whole pipeline: analyse it
+    /// (once enough samples have accumulated into a full block), evaluate
+    /// every mapping against the resulting frame, apply the updates to every
+    /// scene, and advance each scene's own state. Returns the
+    /// [`ParameterUpdate`]s produced; a partially-filled block produces
+    /// none yet.
+    pub fn process_block(&mut self, samples: &[f32]) -> Vec<ParameterUpdate> {
+        self.process_block_with_frames(samples).1
+    }
+
+    /// Same as [`Self::process_block`], but also returns the
+    /// [`AnalysisFrame`]s produced, for callers (such as [`LiveSession`])
+    /// that need them beyond the parameter updates.
+    pub fn process_block_with_frames(&mut self, samples: &[f32]) -> (Vec<AnalysisFrame>, Vec<ParameterUpdate>) {
+        let frames = match self.metrics.as_mut() {
+            Some(metrics) => PipelineMetrics::time(&mut metrics.analysis, || self.audio.push_samples(samples)),
+            None => self.audio.push_samples(samples),
+        };
+
+        let mut updates = Vec::new();
+        for frame in &frames {
+            let frame_updates = match self.metrics.as_mut() {
+                Some(metrics) => {
+                    PipelineMetrics::time(&mut metrics.mapping, || self.mappings.evaluate_all(frame, self.block_dt, None))
+                }
+                None => self.mappings.evaluate_all(frame, self.block_dt, None),
+            };
+
+            match self.metrics.as_mut() {
+                Some(metrics) => PipelineMetrics::time(&mut metrics.render, || {
+                    self.render.apply_updates(&frame_updates, frame.timestamp);
+                    self.render.update(frame, self.block_dt);
+                }),
+                None => {
+                    self.render.apply_updates(&frame_updates, frame.timestamp);
+                    self.render.update(frame, self.block_dt);
+                }
+            }
+            updates.extend(frame_updates);
+        }
+        (frames, updates)
+    }
+}
+
+/// Whether a [`LiveSession`] is still driving blocks or has wrapped up.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SessionStatus {
+    /// Still running; carries this block's parameter updates.
+    Running(Vec<ParameterUpdate>),
+    /// [`LiveSession::max_duration`] was reached and the recorder has been
+    /// finalized.
+    Finished(RecordingExport),
+}
+
+/// Wraps a [`Pipeline`] with a [`Recorder`] and an optional wall-clock or
+/// logical duration cap, so a timed demo can drive blocks without the
+/// caller having to check elapsed time itself. Once
+/// [`Self::max_duration`] is reached (per [`crate::audio::AudioEngine`]'s
+/// clock), the session stops accepting blocks and finalizes the recorder.
+pub struct LiveSession {
+    pub pipeline: Pipeline,
+    pub recorder: Recorder,
+    max_duration: Option<f64>,
+    finished: bool,
+}
+
+impl LiveSession {
+    pub fn new(pipeline: Pipeline, recorder: Recorder, max_duration: Option<f64>) -> Self {
+        Self {
+            pipeline,
+            recorder,
+            max_duration,
+            finished: false,
+        }
+    }
+
+    pub fn max_duration(&self) -> Option<f64> {
+        self.max_duration
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Push one block through the pipeline, recording every frame produced.
+    /// Once already finished, or once the clock's elapsed time now reaches
+    /// [`Self::max_duration`], returns [`SessionStatus::Finished`] with the
+    /// recorder's finalized export instead of processing further blocks.
+    pub fn process_block(&mut self, samples: &[f32]) -> SessionStatus {
+        if self.finished {
+            return SessionStatus::Finished(self.recorder.export());
+        }
+
+        let (frames, updates) = self.pipeline.process_block_with_frames(samples);
+        for frame in frames {
+            self.recorder.record_frame(frame);
+        }
+
+        let elapsed = self.pipeline.audio.lock_clock().time();
+        if self.max_duration.is_some_and(|max| elapsed >= max) {
+            self.finished = true;
+            return SessionStatus::Finished(self.recorder.export());
+        }
+
+        SessionStatus::Running(updates)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::BarTrackerConfig;
+    use crate::mapping::MappingDescriptor;
+    use crate::recording::RecordingSettings;
+
+    #[test]
+    fn beat_mapped_parameter_rises_on_a_beat_heavy_block() {
+        let sample_rate = 8_000u32;
+        let block_size = 256usize;
+        let mut pipeline = Pipeline::new(sample_rate, block_size);
+        pipeline.audio.lock_analysis().bar_tracker = Some(BarTrackerConfig::default());
+        pipeline.mappings.push(MappingDescriptor::new("beat_confidence", "strobe.intensity"));
+
+        let quiet = vec![0.0f32; block_size];
+        let loud = vec![0.9f32; block_size];
+
+        let mut quiet_value = 0.0;
+        for _ in 0..6 {
+            let updates = pipeline.process_block(&quiet);
+            if let Some(update) = updates.iter().find(|u| u.target == "strobe.intensity") {
+                quiet_value = update.value;
+            }
+        }
+
+        let beat_updates = pipeline.process_block(&loud);
+        let beat_value = beat_updates
+            .iter()
+            .find(|u| u.target == "strobe.intensity")
+            .expect("one full block in, one frame and one mapping update out")
+            .value;
+
+        assert!(
+            beat_value > quiet_value,
+            "beat-mapped parameter should rise on the beat-heavy block, quiet={quiet_value} beat={beat_value}"
+        );
+    }
+
+    #[test]
+    fn enabled_metrics_track_nonzero_analysis_time_and_matching_sample_count() {
+        let sample_rate = 8_000u32;
+        let block_size = 256usize;
+        let mut pipeline = Pipeline::new(sample_rate, block_size);
+        pipeline.enable_metrics();
+
+        let block = vec![0.1f32; block_size];
+        for _ in 0..5 {
+            pipeline.process_block(&block);
+        }
+
+        let metrics = pipeline.metrics().expect("metrics were enabled");
+        assert_eq!(metrics.analysis.samples(), 5);
+        assert!(metrics.analysis.average() > std::time::Duration::ZERO);
+    }
+
+    #[test]
+    fn live_session_finishes_and_finalizes_the_recorder_once_max_duration_elapses() {
+        let sample_rate = 8_000u32;
+        let block_size = 256usize;
+        let pipeline = Pipeline::new(sample_rate, block_size);
+        let mut session = LiveSession::new(pipeline, Recorder::new(RecordingSettings::default()), Some(1.0));
+
+        let block = vec![0.1f32; block_size];
+        let status = session.process_block(&block);
+        assert!(matches!(status, SessionStatus::Running(_)));
+        assert!(!session.is_finished());
+
+        // Advance the clock past max_duration, as an external render loop would.
+        session.pipeline.audio.lock_clock().advance(2.0);
+
+        match session.process_block(&block) {
+            SessionStatus::Finished(export) => {
+                assert!(!export.frames.is_empty());
+            }
+            SessionStatus::Running(_) => panic!("session should have finished after max_duration elapsed"),
+        }
+        assert!(session.is_finished());
+
+        // Further blocks report finished without reprocessing.
+        assert!(matches!(session.process_block(&block), SessionStatus::Finished(_)));
+    }
+}
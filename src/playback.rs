@@ -0,0 +1,423 @@
+//! Drives an [`AnalysisEngine`] from a decoded file at wall-clock rate,
+//! through the exact same `process_block` path live capture uses, so a
+//! performer can rehearse against a known track.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::analysis::AnalysisFrame;
+use crate::engine::AnalysisEngine;
+use crate::timeline::PlaybackClock;
+use crate::wav::{self, WavError};
+
+/// How an [`AnalysisEngine`] is being fed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AudioMode {
+    /// Fed from a live capture callback via [`crate::audio::AudioEngine`].
+    Live,
+    /// Fed from a decoded file via [`FilePlaybackDriver`].
+    FilePlayback { path: PathBuf },
+}
+
+#[derive(Debug)]
+pub enum PlaybackError {
+    Io(std::io::Error),
+    Wav(WavError),
+}
+
+impl fmt::Display for PlaybackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlaybackError::Io(e) => write!(f, "failed to read audio file: {e}"),
+            PlaybackError::Wav(e) => write!(f, "failed to decode audio file: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for PlaybackError {}
+
+impl From<std::io::Error> for PlaybackError {
+    fn from(e: std::io::Error) -> Self {
+        PlaybackError::Io(e)
+    }
+}
+
+impl From<WavError> for PlaybackError {
+    fn from(e: WavError) -> Self {
+        PlaybackError::Wav(e)
+    }
+}
+
+const TIME_STRETCH_WINDOW: usize = 1024;
+const TIME_STRETCH_HOP: usize = TIME_STRETCH_WINDOW / 4;
+
+/// Time-domain windowed overlap-add stretch: rescales `samples`'s duration
+/// by `factor` (`factor > 1.0` for a longer, slower result) while leaving
+/// its frequency content intact, unlike naive resampling. A simplification
+/// of a true phase vocoder — windows aren't phase-aligned across the seam,
+/// so complex material can pick up audible artifacts — but a window still
+/// contains an unmodified copy of the source signal, so a pure tone's pitch
+/// survives untouched.
+fn time_stretch(samples: &[f32], factor: f32) -> Vec<f32> {
+    if samples.is_empty() || factor <= 0.0 {
+        return samples.to_vec();
+    }
+
+    let hop_synthesis = ((TIME_STRETCH_HOP as f32 * factor).round() as usize).max(1);
+    let output_len = (samples.len() as f32 * factor).round() as usize;
+    let mut output = vec![0.0f32; output_len + TIME_STRETCH_WINDOW];
+    let mut gain = vec![0.0f32; output_len + TIME_STRETCH_WINDOW];
+
+    let window: Vec<f32> = (0..TIME_STRETCH_WINDOW)
+        .map(|i| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / (TIME_STRETCH_WINDOW - 1) as f32).cos())
+        .collect();
+
+    let mut read = 0;
+    let mut write = 0;
+    while read < samples.len() {
+        let end = (read + TIME_STRETCH_WINDOW).min(samples.len());
+        for (i, &sample) in samples[read..end].iter().enumerate() {
+            output[write + i] += sample * window[i];
+            gain[write + i] += window[i];
+        }
+        read += TIME_STRETCH_HOP;
+        write += hop_synthesis;
+    }
+
+    for (sample, gain) in output.iter_mut().zip(gain.iter()) {
+        if *gain > 1e-6 {
+            *sample /= gain;
+        }
+    }
+    output.truncate(output_len);
+    output
+}
+
+/// A pull-based source of raw mono sample blocks, abstracting over where
+/// audio actually comes from (a decoded file, a generated test signal) so a
+/// driver can be built against the trait instead of a concrete source.
+///
+/// Synchronous rather than `async fn` in trait: nothing else in this crate
+/// touches an async runtime, and pulling one in (tokio, async-std) just to
+/// support this one trait would be a heavier dependency than the sources it
+/// abstracts over actually need. [`crate::capture::CaptureDevice`] follows
+/// the same synchronous, blocking-call convention for the same reason.
+pub trait AudioSource: Send {
+    /// Return the next block of up to `block_size` mono samples, or `None`
+    /// once the source is exhausted (a finished file) or has failed
+    /// unrecoverably.
+    fn next_block(&mut self, block_size: usize) -> Option<Vec<f32>>;
+}
+
+/// Endlessly generates a sine wave, useful for exercising a pipeline
+/// without a real device or file.
+pub struct SyntheticSource {
+    freq_hz: f32,
+    sample_rate: u32,
+    phase: f32,
+}
+
+impl SyntheticSource {
+    pub fn sine(freq_hz: f32, sample_rate: u32) -> Self {
+        Self { freq_hz, sample_rate, phase: 0.0 }
+    }
+}
+
+impl AudioSource for SyntheticSource {
+    fn next_block(&mut self, block_size: usize) -> Option<Vec<f32>> {
+        let step = 2.0 * std::f32::consts::PI * self.freq_hz / self.sample_rate as f32;
+        let block: Vec<f32> = (0..block_size).map(|i| (self.phase + step * i as f32).sin()).collect();
+        self.phase = (self.phase + step * block_size as f32) % (2.0 * std::f32::consts::PI);
+        Some(block)
+    }
+}
+
+/// Reads sequential blocks from a decoded file already held in memory,
+/// returning `None` once every sample has been consumed.
+pub struct FileSource {
+    samples: Vec<f32>,
+    cursor: usize,
+}
+
+impl FileSource {
+    /// Decode `path` as PCM16 WAVE. Resampling is not performed: the file's
+    /// sample rate is the caller's responsibility to match against whatever
+    /// consumes the blocks.
+    pub fn open(path: &Path) -> Result<Self, PlaybackError> {
+        let bytes = std::fs::read(path)?;
+        let decoded = wav::decode_wav(&bytes)?;
+        Ok(Self { samples: decoded.samples, cursor: 0 })
+    }
+}
+
+impl AudioSource for FileSource {
+    fn next_block(&mut self, block_size: usize) -> Option<Vec<f32>> {
+        if self.cursor >= self.samples.len() {
+            return None;
+        }
+        let end = (self.cursor + block_size).min(self.samples.len());
+        let block = self.samples[self.cursor..end].to_vec();
+        self.cursor = end;
+        Some(block)
+    }
+}
+
+/// Reads a decoded file's samples at wall-clock rate, synced to a
+/// [`PlaybackClock`]: each [`Self::pump`] call banks the elapsed time and
+/// emits one [`AnalysisFrame`] per block duration's worth of banked time.
+/// Pausing the clock pauses consumption — banked time is simply not spent.
+pub struct FilePlaybackDriver {
+    samples: Vec<f32>,
+    cursor: usize,
+    block_size: usize,
+    block_duration: f64,
+    banked_secs: f64,
+    clock: PlaybackClock,
+    /// Playback speed multiplier: `1.0` is normal, `0.5` half speed. Applied
+    /// as naive resampling (banking wall-clock time faster or slower)
+    /// unless [`Self::set_time_stretch`] is enabled.
+    rate: f64,
+    time_stretch: bool,
+}
+
+impl FilePlaybackDriver {
+    /// Decode `path` and build a driver that feeds `engine` in blocks of
+    /// `block_size` samples, resampling is not performed: the file's sample
+    /// rate must match `engine.sample_rate()`.
+    pub fn open(path: &Path, engine: &AnalysisEngine, block_size: usize) -> Result<Self, PlaybackError> {
+        let bytes = std::fs::read(path)?;
+        let decoded = wav::decode_wav(&bytes)?;
+        Ok(Self::from_samples(decoded.samples, engine.sample_rate(), block_size))
+    }
+
+    fn from_samples(samples: Vec<f32>, sample_rate: u32, block_size: usize) -> Self {
+        Self {
+            samples,
+            cursor: 0,
+            block_size,
+            block_duration: block_size as f64 / sample_rate as f64,
+            banked_secs: 0.0,
+            clock: PlaybackClock::new(),
+            rate: 1.0,
+            time_stretch: false,
+        }
+    }
+
+    pub fn clock(&self) -> &PlaybackClock {
+        &self.clock
+    }
+
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    /// Whether [`Self::set_rate`] pitch-preserves via [`time_stretch`]
+    /// instead of naively resampling. Off by default: time-stretching is a
+    /// heavier DSP path and only worth its cost when pitch preservation
+    /// actually matters.
+    pub fn set_time_stretch(&mut self, enabled: bool) {
+        self.time_stretch = enabled;
+    }
+
+    /// Change the playback speed multiplier (`1.0` normal, `0.5` half
+    /// speed). With [`Self::set_time_stretch`] off, this simply banks
+    /// wall-clock time faster or slower, which shifts pitch along with
+    /// speed — the same effect as playing a record at the wrong RPM. With it
+    /// on, the unplayed remainder of the file is stretched in place (see
+    /// [`time_stretch`]) so pitch stays put while duration changes instead.
+    pub fn set_rate(&mut self, rate: f64) {
+        let rate = rate.max(0.01);
+        if self.time_stretch && (rate - self.rate).abs() > f64::EPSILON {
+            // The unplayed remainder is already stretched by `1.0 / self.rate`
+            // from a previous call, so the factor applied here must be
+            // relative to that, not to the absolute target rate — otherwise
+            // repeated calls compound incorrectly instead of composing.
+            let factor = (self.rate / rate) as f32;
+            let remainder = time_stretch(&self.samples[self.cursor..], factor);
+            self.samples.truncate(self.cursor);
+            self.samples.extend(remainder);
+        }
+        self.rate = rate;
+    }
+
+    pub fn pause(&mut self) {
+        self.clock.pause();
+    }
+
+    pub fn resume(&mut self) {
+        self.clock.resume();
+    }
+
+    /// Whether every sample in the file has been consumed.
+    pub fn is_finished(&self) -> bool {
+        self.cursor >= self.samples.len()
+    }
+
+    /// Bank `elapsed_secs` of wall-clock time, then emit one frame per block
+    /// duration's worth of banked time that the (unpaused) clock can afford,
+    /// stopping early once the file runs out. The final partial block is
+    /// zero-padded rather than dropped.
+    pub fn pump(&mut self, elapsed_secs: f64, engine: &mut AnalysisEngine) -> Vec<AnalysisFrame> {
+        if self.clock.is_paused() {
+            return Vec::new();
+        }
+
+        // Time-stretching already reshaped the sample buffer to the new
+        // duration, so from here on it's consumed at the normal pace;
+        // without it, speed changes are simulated by banking time faster or
+        // slower against the file's original samples.
+        self.banked_secs += if self.time_stretch { elapsed_secs } else { elapsed_secs * self.rate };
+        let mut frames = Vec::new();
+
+        while self.banked_secs >= self.block_duration && !self.is_finished() {
+            let end = (self.cursor + self.block_size).min(self.samples.len());
+            let mut block = vec![0.0f32; self.block_size];
+            block[..end - self.cursor].copy_from_slice(&self.samples[self.cursor..end]);
+            self.cursor = end;
+
+            frames.push(engine.process_block(&block));
+            self.clock.advance(self.block_duration);
+            self.banked_secs -= self.block_duration;
+        }
+
+        frames
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spectral::{spectral_centroid, SpectralAnalyzer};
+    use crate::wav::encode_wav_pcm16;
+
+    fn sine(freq: f32, sample_rate: f32, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| (2.0 * std::f32::consts::PI * freq * i as f32 / sample_rate).sin())
+            .collect()
+    }
+
+    #[test]
+    fn synthetic_source_never_exhausts_and_produces_the_requested_block_size() {
+        let mut source = SyntheticSource::sine(440.0, 8_000);
+
+        let block = source.next_block(256).expect("synthetic source never runs out");
+        assert_eq!(block.len(), 256);
+        let rms = (block.iter().map(|s| s * s).sum::<f32>() / block.len() as f32).sqrt();
+        assert!((rms - std::f32::consts::FRAC_1_SQRT_2).abs() < 0.05, "got {rms}");
+    }
+
+    #[test]
+    fn file_source_exhausts_once_every_sample_is_consumed() {
+        let samples = sine(440.0, 8_000.0, 300);
+        let path = std::env::temp_dir().join("music_visualiser_file_source_test.wav");
+        std::fs::write(&path, encode_wav_pcm16(8_000, &samples)).unwrap();
+
+        let mut source = FileSource::open(&path).unwrap();
+        assert_eq!(source.next_block(200).unwrap().len(), 200);
+        assert_eq!(source.next_block(200).unwrap().len(), 100);
+        assert!(source.next_block(200).is_none());
+
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn frames_arrive_with_timestamps_tracking_the_clock() {
+        let sample_rate = 8_000u32;
+        let samples = sine(440.0, sample_rate as f32, sample_rate as usize * 2);
+        let path = std::env::temp_dir().join("music_visualiser_playback_test.wav");
+        std::fs::write(&path, encode_wav_pcm16(sample_rate, &samples)).unwrap();
+
+        let mut engine = AnalysisEngine::new(sample_rate);
+        let mut driver = FilePlaybackDriver::open(&path, &engine, 1024).unwrap();
+
+        let frames = driver.pump(1.0, &mut engine);
+        assert!(!frames.is_empty());
+        let block_duration = 1024.0 / sample_rate as f64;
+        for (i, frame) in frames.iter().enumerate() {
+            let expected = i as f64 * block_duration;
+            assert!((frame.timestamp - expected).abs() < 1e-9, "{frame:?}");
+        }
+        assert_eq!(driver.clock().time(), frames.len() as f64 * block_duration);
+
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn pausing_the_clock_stops_consumption() {
+        let sample_rate = 8_000u32;
+        let samples = sine(440.0, sample_rate as f32, sample_rate as usize * 2);
+        let path = std::env::temp_dir().join("music_visualiser_playback_pause_test.wav");
+        std::fs::write(&path, encode_wav_pcm16(sample_rate, &samples)).unwrap();
+
+        let mut engine = AnalysisEngine::new(sample_rate);
+        let mut driver = FilePlaybackDriver::open(&path, &engine, 1024).unwrap();
+
+        driver.pause();
+        let frames = driver.pump(1.0, &mut engine);
+        assert!(frames.is_empty());
+        assert_eq!(driver.clock().time(), 0.0);
+
+        driver.resume();
+        let frames = driver.pump(1.0, &mut engine);
+        assert!(!frames.is_empty());
+
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn time_stretch_by_2x_doubles_duration_while_preserving_dominant_frequency() {
+        let sample_rate = 8_000u32;
+        let samples = sine(440.0, sample_rate as f32, sample_rate as usize * 2);
+
+        let stretched = time_stretch(&samples, 2.0);
+
+        assert!(
+            (stretched.len() as f32 - samples.len() as f32 * 2.0).abs() < TIME_STRETCH_WINDOW as f32,
+            "expected roughly double the length, got {} from {}",
+            stretched.len(),
+            samples.len()
+        );
+
+        let analyzer = SpectralAnalyzer::new(4096);
+        let original_centroid =
+            spectral_centroid(&analyzer.magnitude_spectrum(&samples[..4096]), sample_rate, 4096);
+        let stretched_centroid =
+            spectral_centroid(&analyzer.magnitude_spectrum(&stretched[..4096]), sample_rate, 4096);
+
+        assert!(
+            (original_centroid - stretched_centroid).abs() < 5.0,
+            "original={original_centroid} stretched={stretched_centroid}"
+        );
+    }
+
+    #[test]
+    fn set_rate_composes_correctly_across_repeated_calls() {
+        let sample_rate = 8_000u32;
+        let samples = sine(440.0, sample_rate as f32, sample_rate as usize * 2);
+        let path = std::env::temp_dir().join("music_visualiser_playback_set_rate_test.wav");
+        std::fs::write(&path, encode_wav_pcm16(sample_rate, &samples)).unwrap();
+
+        let engine = AnalysisEngine::new(sample_rate);
+        let mut driver = FilePlaybackDriver::open(&path, &engine, 1024).unwrap();
+        driver.set_time_stretch(true);
+        let original_len = driver.samples.len();
+
+        driver.set_rate(0.5);
+        let doubled_len = driver.samples.len();
+        assert!(
+            (doubled_len as f32 - original_len as f32 * 2.0).abs() < TIME_STRETCH_WINDOW as f32,
+            "expected roughly double the length after halving rate, got {doubled_len} from {original_len}"
+        );
+
+        // Returning to 1.0 should shrink the already-doubled remainder back
+        // toward its original length, not leave it doubled or double it again.
+        driver.set_rate(1.0);
+        let restored_len = driver.samples.len();
+        assert!(
+            (restored_len as f32 - original_len as f32).abs() < TIME_STRETCH_WINDOW as f32 * 2.0,
+            "expected roughly the original length after returning to 1.0x, got {restored_len} from {original_len}"
+        );
+
+        std::fs::remove_file(&path).ok();
+    }
+}
@@ -0,0 +1,511 @@
+//! Captures a sequence of analysis frames for later playback or archival.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::AnalysisFrame;
+
+/// 4-byte magic identifying a compressed recording, followed by a 2-byte
+/// little-endian format version.
+const MAGIC: &[u8; 4] = b"MVRC";
+const FORMAT_VERSION: u16 = 1;
+
+/// Current [`RecordingExport::schema_version`]: bumped whenever
+/// [`AnalysisFrame`]'s field set changes.
+const CURRENT_SCHEMA_VERSION: u32 = 1;
+
+fn current_schema_version() -> u32 {
+    CURRENT_SCHEMA_VERSION
+}
+
+/// Error loading or flushing a compressed recording.
+#[derive(Debug)]
+pub enum RecordingIoError {
+    Io(io::Error),
+    Encode(bincode::Error),
+    BadMagic,
+    UnsupportedVersion(u16),
+    /// [`Recorder::finish_to_file`] was called on a [`Recorder`] whose
+    /// [`RecordingSettings::output_template`] is unset.
+    NoOutputTemplate,
+    /// [`Recorder::resume`] found an existing recording whose
+    /// `schema_version` doesn't match [`CURRENT_SCHEMA_VERSION`]: appending
+    /// onto it would mix frames shaped by two different schemas in one
+    /// export.
+    SchemaMismatch { expected: u32, found: u32 },
+}
+
+impl fmt::Display for RecordingIoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordingIoError::Io(e) => write!(f, "i/o error: {e}"),
+            RecordingIoError::Encode(e) => write!(f, "bincode error: {e}"),
+            RecordingIoError::BadMagic => write!(f, "not a music-visualiser recording file"),
+            RecordingIoError::UnsupportedVersion(v) => {
+                write!(f, "recording format version {v} is not supported")
+            }
+            RecordingIoError::NoOutputTemplate => {
+                write!(f, "RecordingSettings::output_template is unset")
+            }
+            RecordingIoError::SchemaMismatch { expected, found } => write!(
+                f,
+                "cannot resume recording: schema_version {found} does not match the current schema_version {expected}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RecordingIoError {}
+
+impl From<io::Error> for RecordingIoError {
+    fn from(e: io::Error) -> Self {
+        RecordingIoError::Io(e)
+    }
+}
+
+impl From<bincode::Error> for RecordingIoError {
+    fn from(e: bincode::Error) -> Self {
+        RecordingIoError::Encode(e)
+    }
+}
+
+/// Controls how a [`Recorder`] keeps frames.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordingSettings {
+    /// Keep only every Nth frame offered to [`Recorder::record_frame`]. `1`
+    /// keeps every frame.
+    pub decimation: u32,
+    /// Output path used by [`Recorder::finish_to_file`], expanded at that
+    /// call. Supports `{date}` (`YYYY-MM-DD`), `{time}` (`HH-MM-SS`), and
+    /// `{n}` (an auto-incrementing counter, bumped past any file that
+    /// already exists at the resulting path) placeholders. A template with
+    /// no placeholders is used as a fixed path. `None` (the default) means
+    /// [`Recorder::finish_to_file`] isn't usable; use [`Recorder::finish`]
+    /// or [`Recorder::flush_compressed`] instead.
+    pub output_template: Option<String>,
+}
+
+impl Default for RecordingSettings {
+    fn default() -> Self {
+        Self { decimation: 1, output_template: None }
+    }
+}
+
+impl RecordingSettings {
+    /// Set a fixed output path (no placeholder expansion) for
+    /// [`Recorder::finish_to_file`].
+    pub fn with_output_path(mut self, path: impl Into<String>) -> Self {
+        self.output_template = Some(path.into());
+        self
+    }
+
+    /// Set a `{date}`/`{time}`/`{n}` output path template for
+    /// [`Recorder::finish_to_file`]. See [`Self::output_template`].
+    pub fn with_output_template(mut self, template: impl Into<String>) -> Self {
+        self.output_template = Some(template.into());
+        self
+    }
+}
+
+/// A recorded sequence of frames, ready to serialise.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordingExport {
+    /// The [`AnalysisFrame`] field set this export was written against.
+    /// [`load_compressed`] warns rather than fails when this is newer than
+    /// [`CURRENT_SCHEMA_VERSION`], but only once the bincode payload has
+    /// decoded: bincode carries no field names, so a file whose frames have
+    /// a different field set than this build's fails to decode or decodes
+    /// to wrong values, and `#[serde(default)]` on the frame fields does not
+    /// help there.
+    #[serde(default = "current_schema_version")]
+    pub schema_version: u32,
+    pub frames: Vec<AnalysisFrame>,
+}
+
+impl Default for RecordingExport {
+    fn default() -> Self {
+        Self {
+            schema_version: CURRENT_SCHEMA_VERSION,
+            frames: Vec::new(),
+        }
+    }
+}
+
+/// Accumulates analysis frames into a [`RecordingExport`], decimating
+/// according to its [`RecordingSettings`] to bound archival file size.
+#[derive(Debug, Clone)]
+pub struct Recorder {
+    settings: RecordingSettings,
+    frames: Vec<AnalysisFrame>,
+    seen: u32,
+}
+
+impl Recorder {
+    pub fn new(settings: RecordingSettings) -> Self {
+        Self {
+            settings,
+            frames: Vec::new(),
+            seen: 0,
+        }
+    }
+
+    /// Build a recorder that resumes appending to the recording already at
+    /// `path`, instead of starting over, so an interrupted long recording
+    /// doesn't lose what was already captured. Falls back to
+    /// [`Self::new`] if `path` doesn't exist yet. Errors with
+    /// [`RecordingIoError::SchemaMismatch`] if the existing recording's
+    /// `schema_version` doesn't match [`CURRENT_SCHEMA_VERSION`] rather than
+    /// silently mixing frame shapes in one export.
+    pub fn resume(settings: RecordingSettings, path: impl AsRef<Path>) -> Result<Self, RecordingIoError> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::new(settings));
+        }
+
+        let existing = load_compressed(path)?;
+        if existing.schema_version != CURRENT_SCHEMA_VERSION {
+            return Err(RecordingIoError::SchemaMismatch {
+                expected: CURRENT_SCHEMA_VERSION,
+                found: existing.schema_version,
+            });
+        }
+
+        // `record_frame` keeps a frame when `seen % decimation == 0` and
+        // increments `seen` on every offer. Offers dropped after the last kept
+        // frame aren't stored, so this takes the counter to be one past the
+        // last kept boundary, as if the previous recording stopped right after
+        // it, not N full decimation periods later.
+        let decimation = settings.decimation.max(1);
+        let kept = existing.frames.len() as u32;
+        let seen = if kept == 0 { 0 } else { (kept - 1) * decimation + 1 };
+        Ok(Self {
+            settings,
+            frames: existing.frames,
+            seen,
+        })
+    }
+
+    /// Offer a frame to the recorder; it is kept only if it falls on a
+    /// decimation boundary. Timestamps of kept frames are unaffected.
+    pub fn record_frame(&mut self, frame: AnalysisFrame) {
+        let decimation = self.settings.decimation.max(1);
+        if self.seen.is_multiple_of(decimation) {
+            self.frames.push(frame);
+        }
+        self.seen += 1;
+    }
+
+    /// Number of frames kept so far (after decimation).
+    pub fn recorded_frames(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn export(&self) -> RecordingExport {
+        RecordingExport {
+            schema_version: CURRENT_SCHEMA_VERSION,
+            frames: self.frames.clone(),
+        }
+    }
+
+    /// Finalize the recording, consuming the recorder and returning its
+    /// [`RecordingExport`]. Use this once a session is done; use
+    /// [`Self::export`] for a snapshot mid-recording.
+    pub fn finish(self) -> RecordingExport {
+        RecordingExport {
+            schema_version: CURRENT_SCHEMA_VERSION,
+            frames: self.frames,
+        }
+    }
+
+    /// Bincode-encode and gzip the recorded frames to `path`, behind a small
+    /// magic header + version so [`load_compressed`] can reject mismatched
+    /// files with a clear error instead of a decode panic.
+    pub fn flush_compressed(&self, path: impl AsRef<Path>) -> Result<(), RecordingIoError> {
+        let encoded = bincode::serialize(&self.export())?;
+        let file = File::create(path)?;
+        let mut writer = GzEncoder::new(file, Compression::default());
+        writer.write_all(MAGIC)?;
+        writer.write_all(&FORMAT_VERSION.to_le_bytes())?;
+        writer.write_all(&encoded)?;
+        writer.finish()?;
+        Ok(())
+    }
+
+    /// Expand [`RecordingSettings::output_template`] and
+    /// [`Self::flush_compressed`] to the resulting path, bumping `{n}` past
+    /// any file that already exists there so a repeated recording never
+    /// silently overwrites the last one. Returns the path actually written.
+    pub fn finish_to_file(&self) -> Result<std::path::PathBuf, RecordingIoError> {
+        let template = self.settings.output_template.as_deref().ok_or(RecordingIoError::NoOutputTemplate)?;
+        let now_secs = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+
+        let mut n = 0u32;
+        let path = loop {
+            let candidate = std::path::PathBuf::from(expand_template(template, now_secs, n));
+            if !candidate.exists() {
+                break candidate;
+            }
+            n += 1;
+        };
+
+        self.flush_compressed(&path)?;
+        Ok(path)
+    }
+}
+
+/// Replace `{date}` (`YYYY-MM-DD`), `{time}` (`HH-MM-SS`), and `{n}`
+/// placeholders in an output path template. `now_secs` is Unix time; a
+/// template with none of these placeholders is returned unchanged.
+fn expand_template(template: &str, now_secs: i64, n: u32) -> String {
+    let (year, month, day, hour, minute, second) = civil_date_time_from_unix_secs(now_secs);
+    template
+        .replace("{date}", &format!("{year:04}-{month:02}-{day:02}"))
+        .replace("{time}", &format!("{hour:02}-{minute:02}-{second:02}"))
+        .replace("{n}", &n.to_string())
+}
+
+/// Unix seconds to `(year, month, day, hour, minute, second)`, UTC, ignoring
+/// leap seconds. Implemented by hand rather than pulling in a full calendar
+/// dependency for one templating feature; the day/month math is Howard
+/// Hinnant's well-known `civil_from_days` algorithm.
+fn civil_date_time_from_unix_secs(secs: i64) -> (i64, u32, u32, u32, u32, u32) {
+    let days = secs.div_euclid(86_400);
+    let secs_of_day = secs.rem_euclid(86_400);
+    let hour = (secs_of_day / 3_600) as u32;
+    let minute = ((secs_of_day % 3_600) / 60) as u32;
+    let second = (secs_of_day % 60) as u32;
+
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097); // [0, 146096]
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365; // [0, 399]
+    let y = yoe + era * 400;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100); // [0, 365]
+    let mp = (5 * doy + 2) / 153; // [0, 11]
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32; // [1, 31]
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32; // [1, 12]
+    let year = if month <= 2 { y + 1 } else { y };
+
+    (year, month, day, hour, minute, second)
+}
+
+/// Load a recording previously written by [`Recorder::flush_compressed`].
+pub fn load_compressed(path: impl AsRef<Path>) -> Result<RecordingExport, RecordingIoError> {
+    let file = File::open(path)?;
+    let mut reader = GzDecoder::new(file);
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf)?;
+
+    if buf.len() < 6 || &buf[0..4] != MAGIC {
+        return Err(RecordingIoError::BadMagic);
+    }
+    let version = u16::from_le_bytes([buf[4], buf[5]]);
+    if version != FORMAT_VERSION {
+        return Err(RecordingIoError::UnsupportedVersion(version));
+    }
+
+    let export: RecordingExport = bincode::deserialize(&buf[6..])?;
+    if export.schema_version > CURRENT_SCHEMA_VERSION {
+        eprintln!(
+            "warning: recording schema_version {} is newer than this build supports ({CURRENT_SCHEMA_VERSION}); loading best-effort",
+            export.schema_version
+        );
+    }
+    Ok(export)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decimation_keeps_every_nth_frame_in_order() {
+        let mut recorder = Recorder::new(RecordingSettings { decimation: 5, ..RecordingSettings::default() });
+        for i in 0..100 {
+            recorder.record_frame(AnalysisFrame::silent(i as f64));
+        }
+
+        assert_eq!(recorder.recorded_frames(), 20);
+
+        let export = recorder.export();
+        let mut last = f64::NEG_INFINITY;
+        for frame in &export.frames {
+            assert!(frame.timestamp > last);
+            last = frame.timestamp;
+        }
+    }
+
+    #[test]
+    fn decimation_of_one_keeps_every_frame() {
+        let mut recorder = Recorder::new(RecordingSettings::default());
+        for i in 0..10 {
+            recorder.record_frame(AnalysisFrame::silent(i as f64));
+        }
+        assert_eq!(recorder.recorded_frames(), 10);
+    }
+
+    #[test]
+    fn finish_to_file_auto_increments_n_to_avoid_overwriting() {
+        let dir = std::env::temp_dir().join("music_visualiser_output_template_test");
+        std::fs::create_dir_all(&dir).unwrap();
+        let template = dir.join("take-{n}.mvrec").to_string_lossy().into_owned();
+
+        let settings = RecordingSettings::default().with_output_template(template);
+        let first_path = Recorder::new(settings.clone()).finish_to_file().unwrap();
+        let second_path = Recorder::new(settings).finish_to_file().unwrap();
+
+        assert_ne!(first_path, second_path);
+        assert!(first_path.exists());
+        assert!(second_path.exists());
+
+        std::fs::remove_file(&first_path).ok();
+        std::fs::remove_file(&second_path).ok();
+        std::fs::remove_dir(&dir).ok();
+    }
+
+    #[test]
+    fn finish_to_file_without_a_template_errors() {
+        let recorder = Recorder::new(RecordingSettings::default());
+        assert!(matches!(recorder.finish_to_file(), Err(RecordingIoError::NoOutputTemplate)));
+    }
+
+    fn sample_recorder() -> Recorder {
+        let mut recorder = Recorder::new(RecordingSettings::default());
+        for i in 0..200 {
+            let mut frame = AnalysisFrame::silent(i as f64 / 30.0);
+            frame.rms = (i % 10) as f32 / 10.0;
+            recorder.record_frame(frame);
+        }
+        recorder
+    }
+
+    #[test]
+    fn export_stamps_the_current_schema_version() {
+        let recorder = sample_recorder();
+        assert_eq!(recorder.export().schema_version, CURRENT_SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn compressed_round_trip_preserves_frames() {
+        let recorder = sample_recorder();
+        let path = std::env::temp_dir().join("music_visualiser_recording_round_trip.mvrc");
+
+        recorder.flush_compressed(&path).expect("flush succeeds");
+        let loaded = load_compressed(&path).expect("load succeeds");
+
+        assert_eq!(loaded, recorder.export());
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn resume_appends_new_frames_onto_an_existing_recording() {
+        let path = std::env::temp_dir().join("music_visualiser_recording_resume_test.mvrc");
+        std::fs::remove_file(&path).ok();
+
+        let mut first = Recorder::new(RecordingSettings::default());
+        for i in 0..5 {
+            first.record_frame(AnalysisFrame::silent(i as f64));
+        }
+        first.flush_compressed(&path).expect("initial flush succeeds");
+
+        let mut resumed = Recorder::resume(RecordingSettings::default(), &path).expect("resume succeeds");
+        assert_eq!(resumed.recorded_frames(), 5);
+        for i in 5..8 {
+            resumed.record_frame(AnalysisFrame::silent(i as f64));
+        }
+        resumed.flush_compressed(&path).expect("resumed flush succeeds");
+
+        let loaded = load_compressed(&path).expect("load succeeds");
+        assert_eq!(loaded.frames.len(), 8);
+        for (i, frame) in loaded.frames.iter().enumerate() {
+            assert_eq!(frame.timestamp, i as f64);
+        }
+
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn resume_preserves_the_decimation_cadence_across_a_pause() {
+        let path = std::env::temp_dir().join("music_visualiser_recording_resume_decimation_test.mvrc");
+        std::fs::remove_file(&path).ok();
+
+        let settings = RecordingSettings { decimation: 5, ..RecordingSettings::default() };
+        let mut first = Recorder::new(settings.clone());
+        // Offers 0..16 keep exactly the frames offered at seen = 0, 5, 10, 15,
+        // leaving the true counter at 16 right after the 4th kept frame.
+        for i in 0..16 {
+            first.record_frame(AnalysisFrame::silent(i as f64));
+        }
+        assert_eq!(first.recorded_frames(), 4);
+        first.flush_compressed(&path).expect("initial flush succeeds");
+
+        let mut resumed = Recorder::resume(settings, &path).expect("resume succeeds");
+        assert_eq!(resumed.recorded_frames(), 4);
+
+        // The next 4 offers (seen = 16, 17, 18, 19) must NOT land on a
+        // decimation boundary; only the one after that (seen = 20) should.
+        for i in 16..20 {
+            resumed.record_frame(AnalysisFrame::silent(i as f64));
+        }
+        assert_eq!(
+            resumed.recorded_frames(),
+            4,
+            "resuming must not shift the decimation cadence, so these offers should still be dropped"
+        );
+        resumed.record_frame(AnalysisFrame::silent(20.0));
+        assert_eq!(resumed.recorded_frames(), 5, "the offer that lands on seen = 20 should be kept");
+
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn resume_rejects_a_recording_with_a_mismatched_schema_version() {
+        let path = std::env::temp_dir().join("music_visualiser_recording_resume_mismatch_test.mvrc");
+
+        let export = RecordingExport { schema_version: CURRENT_SCHEMA_VERSION + 1, frames: Vec::new() };
+        let encoded = bincode::serialize(&export).unwrap();
+        let file = File::create(&path).unwrap();
+        let mut writer = GzEncoder::new(file, Compression::default());
+        writer.write_all(MAGIC).unwrap();
+        writer.write_all(&FORMAT_VERSION.to_le_bytes()).unwrap();
+        writer.write_all(&encoded).unwrap();
+        writer.finish().unwrap();
+
+        let error = Recorder::resume(RecordingSettings::default(), &path).expect_err("mismatched schema should error");
+        assert!(matches!(
+            error,
+            RecordingIoError::SchemaMismatch { expected, found }
+                if expected == CURRENT_SCHEMA_VERSION && found == CURRENT_SCHEMA_VERSION + 1
+        ));
+
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn compressed_file_is_smaller_than_pretty_json() {
+        let recorder = sample_recorder();
+        let export = recorder.export();
+
+        let json = serde_json::to_string_pretty(&export).expect("json serialises");
+        let path = std::env::temp_dir().join("music_visualiser_recording_size.mvrc");
+        recorder.flush_compressed(&path).expect("flush succeeds");
+        let compressed_len = std::fs::metadata(&path).unwrap().len() as usize;
+        std::fs::remove_file(&path).ok();
+
+        assert!(
+            compressed_len < json.len(),
+            "compressed {compressed_len} bytes, json {len} bytes",
+            len = json.len()
+        );
+    }
+}
@@ -0,0 +1,113 @@
+//! Decouples render cadence from analysis cadence: analysis arrives once per
+//! audio block (~47 Hz for a 1024-sample block at 48 kHz), but a renderer
+//! typically wants a steady cadence of its own, interpolating between
+//! whatever frames have landed so far.
+
+use crate::analysis::AnalysisFrame;
+use crate::history::AnalysisHandle;
+use crate::timeline::{InstantTimeSource, TimeSource};
+
+/// Calls a render callback at a steady `target_fps`, sampling
+/// [`AnalysisHandle::sample_interpolated`] at each virtual frame's own time
+/// rather than at analysis's own rate. [`Self::tick`] can be polled as often
+/// as convenient: it catches up by rendering every frame that's come due
+/// since the last call, and renders nothing if none has.
+pub struct RenderLoop<S: TimeSource = InstantTimeSource> {
+    source: S,
+    target_fps: f32,
+    started_at: Option<f64>,
+    frames_rendered: u64,
+}
+
+impl RenderLoop<InstantTimeSource> {
+    pub fn new(target_fps: f32) -> Self {
+        Self::with_source(InstantTimeSource::new(), target_fps)
+    }
+}
+
+impl Default for RenderLoop<InstantTimeSource> {
+    fn default() -> Self {
+        Self::new(60.0)
+    }
+}
+
+impl<S: TimeSource> RenderLoop<S> {
+    pub fn with_source(source: S, target_fps: f32) -> Self {
+        Self {
+            source,
+            target_fps,
+            started_at: None,
+            frames_rendered: 0,
+        }
+    }
+
+    /// Render every virtual frame due since the loop started (or since the
+    /// last tick), each sampled at its own scheduled time. Does nothing if
+    /// called before the next frame is due.
+    pub fn tick(&mut self, handle: &AnalysisHandle, mut render: impl FnMut(&AnalysisFrame)) {
+        let now = self.source.now();
+        let started_at = *self.started_at.get_or_insert(now);
+        let elapsed = now - started_at;
+        let due = (elapsed * self.target_fps as f64) as u64;
+
+        while self.frames_rendered < due {
+            let frame_time = self.frames_rendered as f64 / self.target_fps as f64;
+            if let Some(frame) = handle.sample_interpolated(frame_time) {
+                render(&frame);
+            }
+            self.frames_rendered += 1;
+        }
+    }
+
+    /// Frames rendered per second of elapsed wall time so far. `0.0` before
+    /// the first tick or if no time has elapsed yet.
+    pub fn achieved_fps(&self) -> f32 {
+        let Some(started_at) = self.started_at else {
+            return 0.0;
+        };
+        let elapsed = self.source.now() - started_at;
+        if elapsed > 0.0 {
+            (self.frames_rendered as f64 / elapsed) as f32
+        } else {
+            0.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timeline::MockTimeSource;
+
+    fn frame_with_rms(timestamp: f64, rms: f32) -> AnalysisFrame {
+        AnalysisFrame {
+            rms,
+            ..AnalysisFrame::silent(timestamp)
+        }
+    }
+
+    #[test]
+    fn one_logical_second_at_60_fps_renders_close_to_60_frames() {
+        let mut handle = AnalysisHandle::new();
+        handle.push(frame_with_rms(0.0, 0.0));
+        handle.push(frame_with_rms(1.0, 1.0));
+
+        let source = MockTimeSource::new();
+        let mut render_loop = RenderLoop::with_source(&source, 60.0);
+
+        let mut rendered = 0u32;
+        render_loop.tick(&handle, |_frame| rendered += 1); // establishes t=0 as the loop's start
+        source.advance(1.0);
+        render_loop.tick(&handle, |_frame| rendered += 1);
+
+        assert!(
+            (55..=60).contains(&rendered),
+            "expected close to 60 renders over one logical second, got {rendered}"
+        );
+        assert!(
+            (55.0..=60.0).contains(&render_loop.achieved_fps()),
+            "got {}",
+            render_loop.achieved_fps()
+        );
+    }
+}
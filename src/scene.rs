@@ -0,0 +1,1331 @@
+//! Visual scenes driven by analysis output.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::AnalysisFrame;
+use crate::mapping::{ColorUpdate, MappingMatrix, ParameterUpdate};
+
+/// The kind of visual simulation a scene runs, along with its construction
+/// parameters.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SceneKind {
+    Kaleidoscope { order: u32 },
+    Particles {
+        emission: f32,
+        tone: f32,
+        /// Seeds the [`SceneInstance`]'s particle PRNG, so a recorded session
+        /// with the same seed and input frames replays identical particle
+        /// trajectories. `0` (the default) is as good a seed as any other.
+        #[serde(default)]
+        seed: u64,
+    },
+    Tunnel { speed: f32, segment_length: f32 },
+    Text { content: String, size: f32 },
+    /// An ambient backdrop that keeps drifting even during silence, instead
+    /// of freezing solid: hue and brightness follow a smooth pseudo-noise
+    /// field, nudged by `rms` and `high_band_energy` when there's audio to
+    /// react to.
+    Gradient {
+        /// Seeds the noise field's phase, so two scenes with the same seed
+        /// drift identically. `0` (the default) is as good a seed as any
+        /// other.
+        #[serde(default)]
+        seed: u64,
+    },
+}
+
+/// Particles simulated per `Particles` scene. Small enough that a full
+/// physics/rendering particle count isn't needed for deterministic replay to
+/// be meaningful.
+const PARTICLE_COUNT: usize = 16;
+
+/// Minimal splitmix64-based PRNG, so procedural scenes can be deterministic
+/// from a stored seed without pulling in a `rand` dependency for something
+/// this small.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+struct SeededRng(u64);
+
+impl SeededRng {
+    fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform value in `-1.0..=1.0`.
+    fn next_signed(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32 * 2.0 - 1.0
+    }
+}
+
+/// Smooth, seed-varied pseudo-noise for a `Gradient` scene's drift: a small
+/// sum of sine harmonics at fixed relative frequencies, offset by `phase` so
+/// different seeds don't drift in lockstep. Not true Perlin/simplex noise —
+/// this crate has no such dependency — but shares the property that matters
+/// here: continuous, deterministic given `phase`, and bounded to `-1.0..=1.0`.
+fn smooth_noise(phase: f32, t: f32) -> f32 {
+    let a = (t * 0.9 + phase).sin();
+    let b = (t * 0.37 + phase * 1.7).sin() * 0.5;
+    let c = (t * 1.53 + phase * 2.3).sin() * 0.25;
+    (a + b + c) / 1.75
+}
+
+/// Static description of a scene: what kind it is and what it's called in a
+/// config.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SceneDescriptor {
+    pub name: String,
+    pub kind: SceneKind,
+    /// Name of a mesh registered in the config's `AssetStore`, if this
+    /// scene renders one.
+    pub asset: Option<String>,
+}
+
+impl SceneDescriptor {
+    pub fn new(name: impl Into<String>, kind: SceneKind) -> Self {
+        Self {
+            name: name.into(),
+            kind,
+            asset: None,
+        }
+    }
+
+    pub fn with_asset(mut self, asset: impl Into<String>) -> Self {
+        self.asset = Some(asset.into());
+        self
+    }
+
+    /// Canonical parameter keys this scene publishes, so editor UIs and
+    /// config validation can offer them as mapping targets without knowing
+    /// about `SceneInstance::update`'s internals.
+    pub fn parameter_names(&self) -> Vec<&'static str> {
+        match self.kind {
+            SceneKind::Kaleidoscope { .. } => vec!["kaleidoscope.rotation", "kaleidoscope.order"],
+            SceneKind::Particles { .. } => vec!["particles.emission", "particles.tone"],
+            SceneKind::Tunnel { .. } => vec!["tunnel.energy", "tunnel.speed"],
+            SceneKind::Text { .. } => vec!["text.opacity"],
+            SceneKind::Gradient { .. } => vec!["gradient.hue", "gradient.brightness"],
+        }
+    }
+}
+
+/// A running scene: its descriptor plus the parameter values it has most
+/// recently published.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SceneInstance {
+    pub descriptor: SceneDescriptor,
+    pub parameters: HashMap<String, f32>,
+    pub last_analysis: Option<AnalysisFrame>,
+    /// Mapping-driven overrides of otherwise-constant scene parameters, such
+    /// as `tunnel.speed`.
+    overrides: HashMap<String, f32>,
+    tunnel_distance: f32,
+    /// Accumulated rotation angle of a `Kaleidoscope` scene, wrapped to
+    /// `0..TAU`.
+    kaleidoscope_angle: f32,
+    /// Runtime override of a `Text` scene's content, set via [`Self::set_text`].
+    text_override: Option<String>,
+    /// Current position of each particle in a `Particles` scene, driven by
+    /// `particle_rng`. Empty for other scene kinds.
+    particle_positions: Vec<(f32, f32)>,
+    /// Seeded from the descriptor's `seed` on construction, so cloning a
+    /// scene from a snapshot and replaying the same frames reproduces the
+    /// same particle trajectories.
+    particle_rng: SeededRng,
+    /// Elapsed time fed to a `Gradient` scene's noise field. `0.0` for other
+    /// scene kinds.
+    gradient_time: f32,
+    /// Phase offset derived from the descriptor's `seed`, so two `Gradient`
+    /// scenes with different seeds drift differently.
+    gradient_phase: f32,
+}
+
+/// A serialisable capture of a scene's runtime state, for saving and
+/// restoring a live session's exact visual state.
+pub type SceneSnapshot = SceneInstance;
+
+impl SceneInstance {
+    pub fn new(descriptor: SceneDescriptor) -> Self {
+        let (particle_positions, particle_rng) = match descriptor.kind {
+            SceneKind::Particles { seed, .. } => (vec![(0.0, 0.0); PARTICLE_COUNT], SeededRng::new(seed)),
+            _ => (Vec::new(), SeededRng::default()),
+        };
+        let gradient_phase = match descriptor.kind {
+            SceneKind::Gradient { seed } => SeededRng::new(seed).next_signed() * 1_000.0,
+            _ => 0.0,
+        };
+        Self {
+            descriptor,
+            parameters: HashMap::new(),
+            last_analysis: None,
+            overrides: HashMap::new(),
+            tunnel_distance: 0.0,
+            kaleidoscope_angle: 0.0,
+            text_override: None,
+            particle_positions,
+            particle_rng,
+            gradient_time: 0.0,
+            gradient_phase,
+        }
+    }
+
+    /// Override a mutable scene parameter by its canonical key, as written
+    /// by a mapping target.
+    pub fn set_parameter(&mut self, key: &str, value: f32) {
+        self.overrides.insert(key.to_string(), value);
+    }
+
+    /// Distance travelled along a `Tunnel` scene's path, wrapped modulo its
+    /// segment length. Always `0.0` for other scene kinds.
+    pub fn tunnel_distance(&self) -> f32 {
+        self.tunnel_distance
+    }
+
+    /// Accumulated rotation angle of a `Kaleidoscope` scene, in radians,
+    /// wrapped to `0..TAU`. Always `0.0` for other scene kinds.
+    pub fn kaleidoscope_angle(&self) -> f32 {
+        self.kaleidoscope_angle
+    }
+
+    /// Current position of each particle in a `Particles` scene. Empty for
+    /// other scene kinds.
+    pub fn particle_positions(&self) -> &[(f32, f32)] {
+        &self.particle_positions
+    }
+
+    /// Every parameter this scene currently publishes, keyed by the same
+    /// canonical names as [`SceneDescriptor::parameter_names`], for
+    /// consumers (like a shader uniform uploader) that want the whole map
+    /// rather than looking values up one key at a time.
+    pub fn parameters(&self) -> &HashMap<String, f32> {
+        &self.parameters
+    }
+
+    /// Effective segment count for a `Kaleidoscope` scene: a
+    /// `kaleidoscope.order` mapping override if one has been applied
+    /// (rounded to the nearest whole segment, minimum 1), otherwise the
+    /// descriptor's original `order`. `0` for other scene kinds.
+    pub fn kaleidoscope_segments(&self) -> u32 {
+        match self.descriptor.kind {
+            SceneKind::Kaleidoscope { order } => self
+                .overrides
+                .get("kaleidoscope.order")
+                .map(|&value| value.round().max(1.0) as u32)
+                .unwrap_or(order),
+            _ => 0,
+        }
+    }
+
+    /// Swap a `Text` scene's displayed content at runtime without rebuilding
+    /// the scene. Has no effect on other scene kinds.
+    pub fn set_text(&mut self, content: impl Into<String>) {
+        self.text_override = Some(content.into());
+    }
+
+    /// The text a `Text` scene is currently displaying: the runtime override
+    /// from [`Self::set_text`] if one was set, otherwise the descriptor's
+    /// original content. `None` for other scene kinds.
+    pub fn current_text(&self) -> Option<&str> {
+        match &self.descriptor.kind {
+            SceneKind::Text { content, .. } => Some(self.text_override.as_deref().unwrap_or(content)),
+            _ => None,
+        }
+    }
+
+    pub fn update(&mut self, frame: &AnalysisFrame, dt: f32) {
+        match self.descriptor.kind {
+            SceneKind::Kaleidoscope { .. } => {
+                // A beat lands as a momentary kick on top of the steady
+                // RMS-driven spin, so the kaleidoscope visibly "jumps" on
+                // the beat rather than just spinning faster on average.
+                let speed = frame.rms * std::f32::consts::TAU + frame.beat_confidence * std::f32::consts::TAU;
+                self.kaleidoscope_angle = (self.kaleidoscope_angle + speed * dt).rem_euclid(std::f32::consts::TAU);
+                self.parameters
+                    .insert("kaleidoscope.rotation".to_string(), self.kaleidoscope_angle);
+                self.parameters
+                    .insert("kaleidoscope.order".to_string(), self.kaleidoscope_segments() as f32);
+            }
+            SceneKind::Particles { .. } => {
+                self.parameters
+                    .insert("particles.emission".to_string(), frame.rms);
+                self.parameters
+                    .insert("particles.tone".to_string(), frame.high_band_energy);
+                let mut rng = self.particle_rng;
+                for (x, y) in &mut self.particle_positions {
+                    *x += rng.next_signed() * frame.rms * dt;
+                    *y += rng.next_signed() * frame.high_band_energy * dt;
+                }
+                self.particle_rng = rng;
+            }
+            SceneKind::Tunnel {
+                speed,
+                segment_length,
+            } => {
+                let speed = self.overrides.get("tunnel.speed").copied().unwrap_or(speed);
+                self.tunnel_distance += speed * (1.0 + frame.rms) * dt;
+                if segment_length > 0.0 {
+                    self.tunnel_distance %= segment_length;
+                }
+                self.parameters
+                    .insert("tunnel.energy".to_string(), frame.rms);
+                self.parameters
+                    .insert("tunnel.speed".to_string(), speed);
+            }
+            SceneKind::Text { .. } => {
+                self.parameters
+                    .insert("text.opacity".to_string(), frame.beat_confidence);
+            }
+            SceneKind::Gradient { .. } => {
+                self.gradient_time += dt;
+                let hue_noise = smooth_noise(self.gradient_phase, self.gradient_time * 0.2);
+                let brightness_noise = smooth_noise(self.gradient_phase + 500.0, self.gradient_time * 0.15);
+                let hue = (0.5 + 0.5 * hue_noise + frame.high_band_energy * 0.25).rem_euclid(1.0);
+                let brightness = (0.3 + 0.35 * (0.5 + 0.5 * brightness_noise) + frame.rms * 0.4).clamp(0.0, 1.0);
+                self.parameters.insert("gradient.hue".to_string(), hue);
+                self.parameters.insert("gradient.brightness".to_string(), brightness);
+            }
+        }
+        self.last_analysis = Some(frame.clone());
+    }
+
+    /// Capture the current parameter values (and last analysis frame) for
+    /// later restoration.
+    pub fn snapshot(&self) -> SceneSnapshot {
+        self.clone()
+    }
+
+    /// Rebuild a scene instance from a previously captured snapshot.
+    pub fn restore(snapshot: SceneSnapshot) -> Self {
+        snapshot
+    }
+}
+
+/// Configures [`AutoSceneSwitcher`]'s energy thresholds and anti-flicker
+/// dwell time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AutoSwitcherConfig {
+    /// Smoothed energy at or above this switches to the energetic scene.
+    pub high_threshold: f32,
+    /// Smoothed energy at or below this switches to the calm scene.
+    /// Keeping this below `high_threshold` is what gives the switch its
+    /// hysteresis band: energy sitting between the two never triggers a
+    /// switch either way.
+    pub low_threshold: f32,
+    /// Exponential smoothing factor applied to the incoming energy each
+    /// call, in `0..1`. Higher values react more slowly to transients.
+    pub smoothing: f32,
+    /// Minimum time a scene must stay active before another switch is
+    /// considered, to avoid flicker when energy hovers near a threshold.
+    pub min_dwell: f32,
+}
+
+impl Default for AutoSwitcherConfig {
+    fn default() -> Self {
+        Self {
+            high_threshold: 0.6,
+            low_threshold: 0.3,
+            smoothing: 0.9,
+            min_dwell: 2.0,
+        }
+    }
+}
+
+/// Picks between a calm and an energetic scene based on a smoothed energy
+/// metric, with hysteresis and a minimum dwell time so the active scene
+/// doesn't flicker when energy hovers near a threshold.
+#[derive(Debug, Clone)]
+pub struct AutoSceneSwitcher {
+    config: AutoSwitcherConfig,
+    calm_scene: String,
+    energetic_scene: String,
+    active_scene: String,
+    smoothed_energy: f32,
+    dwell: f32,
+}
+
+impl AutoSceneSwitcher {
+    pub fn new(
+        config: AutoSwitcherConfig,
+        calm_scene: impl Into<String>,
+        energetic_scene: impl Into<String>,
+    ) -> Self {
+        let calm_scene = calm_scene.into();
+        Self {
+            config,
+            active_scene: calm_scene.clone(),
+            calm_scene,
+            energetic_scene: energetic_scene.into(),
+            smoothed_energy: 0.0,
+            dwell: 0.0,
+        }
+    }
+
+    /// The name of the scene that should currently be shown.
+    pub fn active_scene(&self) -> &str {
+        &self.active_scene
+    }
+
+    /// Feed one block's energy metric (e.g. [`AnalysisFrame::rms`]) and
+    /// advance the dwell timer by `dt` seconds. Returns the active scene
+    /// name after this call, which only changes once the smoothed energy
+    /// has crossed a threshold and the minimum dwell time has elapsed.
+    pub fn update(&mut self, energy: f32, dt: f32) -> &str {
+        self.smoothed_energy =
+            self.smoothed_energy * self.config.smoothing + energy * (1.0 - self.config.smoothing);
+        self.dwell += dt;
+
+        if self.dwell >= self.config.min_dwell {
+            let target = if self.smoothed_energy >= self.config.high_threshold {
+                Some(self.energetic_scene.as_str())
+            } else if self.smoothed_energy <= self.config.low_threshold {
+                Some(self.calm_scene.as_str())
+            } else {
+                None
+            };
+            if let Some(target) = target {
+                if target != self.active_scene {
+                    self.active_scene = target.to_string();
+                    self.dwell = 0.0;
+                }
+            }
+        }
+
+        &self.active_scene
+    }
+}
+
+/// A [`SceneInstance`] registered in a [`RenderGraph`], along with which
+/// [`ParameterUpdate`] targets it is allowed to see.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoutedScene {
+    pub scene: SceneInstance,
+    /// Only updates whose target starts with this prefix reach the scene.
+    /// `None` (the default, via [`RenderGraph::push`]) accepts everything,
+    /// as does the literal wildcard prefix `"*"`.
+    pub target_prefix: Option<String>,
+}
+
+impl RoutedScene {
+    fn accepts(&self, target: &str) -> bool {
+        match &self.target_prefix {
+            None => true,
+            Some(prefix) => prefix == "*" || target.starts_with(prefix.as_str()),
+        }
+    }
+}
+
+/// How long [`RenderGraph`]'s master fade envelope takes to ramp up to full
+/// intensity at session start, and back down to zero once
+/// [`RenderGraph::finish`] is called. See [`RenderGraph::start_fade_in`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FadeEnvelopeConfig {
+    pub fade_in_secs: f32,
+    pub fade_out_secs: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FadePhase {
+    In,
+    Out,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct FadeState {
+    config: FadeEnvelopeConfig,
+    phase: FadePhase,
+    elapsed_secs: f32,
+}
+
+/// The set of scenes a [`crate::pipeline::Pipeline`] drives each block:
+/// applies mapping output to each scene's overrides (filtered by its
+/// [`RoutedScene::target_prefix`]), then advances each scene's own state.
+/// Scenes also ignore overrides that aren't theirs by kind, so layering a
+/// prefix filter on top just narrows an already-safe broadcast.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RenderGraph {
+    pub scenes: Vec<RoutedScene>,
+    /// Retain at most this many of the most recent [`Self::apply_updates`]
+    /// batches for [`Self::update_history`], evicting the oldest once
+    /// exceeded; `Some(0)` retains one batch, the same as `Some(1)`. `None`
+    /// (the default) retains no history: this exists for
+    /// reproducing a glitchy show after the fact, not something every
+    /// caller should pay for.
+    pub history_depth: Option<usize>,
+    #[serde(skip)]
+    update_history: VecDeque<(f64, Vec<ParameterUpdate>)>,
+    /// Master fade envelope, driving [`Self::master_multiplier`]. `None`
+    /// (the default) never fades: every scene renders at full intensity, as
+    /// before this existed.
+    #[serde(skip)]
+    fade: Option<FadeState>,
+}
+
+impl RenderGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a scene that receives every update, regardless of target.
+    pub fn push(&mut self, scene: SceneInstance) {
+        self.scenes.push(RoutedScene {
+            scene,
+            target_prefix: None,
+        });
+    }
+
+    /// Register a scene that only receives updates whose target starts with
+    /// `prefix` (or every update, if `prefix` is `"*"`).
+    pub fn push_with_prefix(&mut self, scene: SceneInstance, prefix: impl Into<String>) {
+        self.scenes.push(RoutedScene {
+            scene,
+            target_prefix: Some(prefix.into()),
+        });
+    }
+
+    /// Drop every registered scene, returning to the state of [`Self::new`].
+    /// Use after a preset reload so stale scenes and their cached parameter
+    /// values don't linger alongside the newly loaded ones.
+    pub fn clear(&mut self) {
+        self.scenes.clear();
+    }
+
+    /// Apply every update to every scene whose prefix filter accepts it. If
+    /// [`Self::history_depth`] is set, the whole batch is also retained
+    /// under `timestamp` for [`Self::update_history`].
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "apply_updates", skip(self, updates), fields(update_count = updates.len(), scene_count = self.scenes.len()))
+    )]
+    pub fn apply_updates(&mut self, updates: &[ParameterUpdate], timestamp: f64) {
+        for update in updates {
+            for routed in &mut self.scenes {
+                if routed.accepts(&update.target) {
+                    routed.scene.set_parameter(&update.target, update.value);
+                }
+            }
+        }
+
+        if let Some(depth) = self.history_depth {
+            self.update_history.push_back((timestamp, updates.to_vec()));
+            while self.update_history.len() > depth.max(1) {
+                self.update_history.pop_front();
+            }
+        }
+    }
+
+    /// Recent [`Self::apply_updates`] batches paired with the timestamp they
+    /// were applied under, oldest first, bounded by [`Self::history_depth`].
+    /// Empty unless a depth has been configured.
+    pub fn update_history(&self) -> &VecDeque<(f64, Vec<ParameterUpdate>)> {
+        &self.update_history
+    }
+
+    /// Mapping targets that no registered scene publishes, e.g.
+    /// `tunnel.energy` when only a kaleidoscope is registered: the mapping
+    /// still evaluates every block, but its updates land on nothing. A
+    /// preflight check for wiring up a config, not something
+    /// [`Self::apply_updates`] itself enforces — an update for an
+    /// unpublished target is silently a no-op there, same as always.
+    pub fn orphan_mapping_targets(&self, mappings: &MappingMatrix) -> Vec<String> {
+        let published: std::collections::HashSet<&str> = self
+            .scenes
+            .iter()
+            .flat_map(|routed| routed.scene.descriptor.parameter_names())
+            .collect();
+        mappings
+            .mappings
+            .iter()
+            .map(|mapping| mapping.target.as_str())
+            .filter(|target| !published.contains(target))
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Apply every color update to every scene whose prefix filter accepts
+    /// it, as a tint: stored as the target's `.r`/`.g`/`.b` parameters so a
+    /// scene can read them the same way it reads any other override.
+    pub fn apply_color_updates(&mut self, updates: &[ColorUpdate]) {
+        for update in updates {
+            for routed in &mut self.scenes {
+                if routed.accepts(&update.target) {
+                    routed.scene.set_parameter(&format!("{}.r", update.target), update.color.r);
+                    routed.scene.set_parameter(&format!("{}.g", update.target), update.color.g);
+                    routed.scene.set_parameter(&format!("{}.b", update.target), update.color.b);
+                }
+            }
+        }
+    }
+
+    /// Start (or restart) the master fade envelope: [`Self::master_multiplier`]
+    /// ramps from `0.0` to `1.0` over `config.fade_in_secs`, driven by the
+    /// `dt` passed to each subsequent [`Self::update`] call.
+    pub fn start_fade_in(&mut self, config: FadeEnvelopeConfig) {
+        self.fade = Some(FadeState {
+            config,
+            phase: FadePhase::In,
+            elapsed_secs: 0.0,
+        });
+    }
+
+    /// Begin the fade-out: [`Self::master_multiplier`] ramps from wherever
+    /// it currently stands down to `0.0` over the envelope's
+    /// `fade_out_secs`. No-op if [`Self::start_fade_in`] hasn't been called.
+    pub fn finish(&mut self) {
+        if let Some(fade) = &mut self.fade {
+            fade.phase = FadePhase::Out;
+            fade.elapsed_secs = 0.0;
+        }
+    }
+
+    /// The fade envelope's current multiplier, `1.0` if no fade has been
+    /// started via [`Self::start_fade_in`].
+    pub fn master_multiplier(&self) -> f32 {
+        let Some(fade) = &self.fade else {
+            return 1.0;
+        };
+        let ramp_secs = match fade.phase {
+            FadePhase::In => fade.config.fade_in_secs,
+            FadePhase::Out => fade.config.fade_out_secs,
+        };
+        if ramp_secs <= 0.0 {
+            return match fade.phase {
+                FadePhase::In => 1.0,
+                FadePhase::Out => 0.0,
+            };
+        }
+        let t = (fade.elapsed_secs / ramp_secs).clamp(0.0, 1.0);
+        match fade.phase {
+            FadePhase::In => t,
+            FadePhase::Out => 1.0 - t,
+        }
+    }
+
+    /// Whether [`Self::finish`] was called and the fade-out has reached
+    /// zero. Always `false` before a fade envelope exists or while it's
+    /// still fading in.
+    pub fn fade_out_complete(&self) -> bool {
+        matches!(&self.fade, Some(fade) if fade.phase == FadePhase::Out && fade.elapsed_secs >= fade.config.fade_out_secs)
+    }
+
+    /// Advance every scene's own state for this block, then scale every
+    /// resulting parameter by [`Self::master_multiplier`] so a fade-in or
+    /// fade-out uniformly dims (or restores) every scene's intensity.
+    pub fn update(&mut self, frame: &AnalysisFrame, dt: f32) {
+        if let Some(fade) = &mut self.fade {
+            fade.elapsed_secs += dt;
+        }
+        let multiplier = self.master_multiplier();
+
+        for routed in &mut self.scenes {
+            routed.scene.update(frame, dt);
+            if multiplier < 1.0 {
+                for value in routed.scene.parameters.values_mut() {
+                    *value *= multiplier;
+                }
+            }
+        }
+    }
+
+    /// Confirm every scene has received at least one analysis frame before
+    /// rendering. Use [`Self::draw_detailed`] instead if you need to know
+    /// which scenes are stale.
+    pub fn draw(&self) -> Result<(), DrawError> {
+        if self.scenes.iter().any(|routed| routed.scene.last_analysis.is_none()) {
+            Err(DrawError::MissingAnalysis)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Like [`Self::draw`], but on failure names every scene still missing
+    /// analysis instead of returning an opaque error.
+    pub fn draw_detailed(&self) -> Result<(), Vec<StaleScene>> {
+        let stale: Vec<StaleScene> = self
+            .scenes
+            .iter()
+            .enumerate()
+            .filter(|(_, routed)| routed.scene.last_analysis.is_none())
+            .map(|(index, routed)| StaleScene { index, name: routed.scene.descriptor.name.clone() })
+            .collect();
+
+        if stale.is_empty() {
+            Ok(())
+        } else {
+            Err(stale)
+        }
+    }
+
+    /// Like [`Self::draw`], but drives `backend` through the frame instead of
+    /// just validating readiness: [`RenderBackend::begin_frame`], one
+    /// [`RenderBackend::draw_scene`] call per registered scene in
+    /// registration order, then [`RenderBackend::end_frame`]. Pass
+    /// [`NullBackend`] to reproduce [`Self::draw`]'s behaviour exactly, or a
+    /// mock in tests to assert on what was drawn.
+    pub fn draw_with_backend(&self, backend: &mut dyn RenderBackend) -> Result<(), DrawError> {
+        self.draw()?;
+        backend.begin_frame();
+        for routed in &self.scenes {
+            backend.draw_scene(&routed.scene.descriptor);
+        }
+        backend.end_frame();
+        Ok(())
+    }
+}
+
+/// A pluggable draw target for [`RenderGraph::draw_with_backend`]. Keeps
+/// `RenderGraph` itself free of any dependency on a particular graphics API:
+/// a host application implements this against wgpu (or anything else) to
+/// actually put pixels on screen, while a test implements it against a `Vec`
+/// to record what would have been drawn.
+pub trait RenderBackend {
+    fn begin_frame(&mut self);
+    fn draw_scene(&mut self, descriptor: &SceneDescriptor);
+    fn end_frame(&mut self);
+}
+
+/// The default backend, matching [`RenderGraph::draw`]'s historic behaviour
+/// of not drawing anything at all, just validating readiness.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NullBackend;
+
+impl RenderBackend for NullBackend {
+    fn begin_frame(&mut self) {}
+    fn draw_scene(&mut self, _descriptor: &SceneDescriptor) {}
+    fn end_frame(&mut self) {}
+}
+
+/// A scene identified by [`RenderGraph::draw_detailed`] as missing analysis
+/// data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaleScene {
+    pub index: usize,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrawError {
+    MissingAnalysis,
+}
+
+impl fmt::Display for DrawError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DrawError::MissingAnalysis => write!(f, "one or more scenes have no analysis data yet"),
+        }
+    }
+}
+
+impl std::error::Error for DrawError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn particles_descriptor_reports_its_parameters() {
+        let descriptor = SceneDescriptor::new(
+            "particles",
+            SceneKind::Particles {
+                emission: 0.5,
+                tone: 0.5,
+                seed: 0,
+            },
+        );
+        let names = descriptor.parameter_names();
+        assert!(names.contains(&"particles.emission"));
+        assert!(names.contains(&"particles.tone"));
+    }
+
+    #[test]
+    fn parameters_exposes_the_whole_map_after_an_update() {
+        let mut scene = SceneInstance::new(SceneDescriptor::new(
+            "particles",
+            SceneKind::Particles {
+                emission: 0.5,
+                tone: 0.5,
+                seed: 0,
+            },
+        ));
+        let frame = AnalysisFrame {
+            rms: 0.4,
+            high_band_energy: 0.7,
+            ..AnalysisFrame::silent(0.0)
+        };
+
+        scene.update(&frame, 1.0 / 30.0);
+
+        let parameters = scene.parameters();
+        assert_eq!(parameters.get("particles.emission"), Some(&0.4));
+        assert_eq!(parameters.get("particles.tone"), Some(&0.7));
+    }
+
+    #[test]
+    fn seeded_particle_scenes_fed_identical_frames_produce_identical_trajectories() {
+        let make_scene = || {
+            SceneInstance::new(SceneDescriptor::new(
+                "particles",
+                SceneKind::Particles {
+                    emission: 0.5,
+                    tone: 0.5,
+                    seed: 42,
+                },
+            ))
+        };
+        let mut a = make_scene();
+        let mut b = make_scene();
+
+        for i in 0..30 {
+            let frame = AnalysisFrame {
+                rms: 0.3 + 0.1 * (i as f32 / 30.0),
+                high_band_energy: 0.2,
+                ..AnalysisFrame::silent(i as f64 / 30.0)
+            };
+            a.update(&frame, 1.0 / 30.0);
+            b.update(&frame, 1.0 / 30.0);
+        }
+
+        assert_eq!(a.particle_positions(), b.particle_positions());
+        assert!(
+            a.particle_positions().iter().any(|&(x, y)| x != 0.0 || y != 0.0),
+            "particles should have moved from the origin"
+        );
+
+        let mut different_seed = SceneInstance::new(SceneDescriptor::new(
+            "particles",
+            SceneKind::Particles {
+                emission: 0.5,
+                tone: 0.5,
+                seed: 43,
+            },
+        ));
+        for i in 0..30 {
+            let frame = AnalysisFrame {
+                rms: 0.3 + 0.1 * (i as f32 / 30.0),
+                high_band_energy: 0.2,
+                ..AnalysisFrame::silent(i as f64 / 30.0)
+            };
+            different_seed.update(&frame, 1.0 / 30.0);
+        }
+        assert_ne!(a.particle_positions(), different_seed.particle_positions());
+    }
+
+    #[test]
+    fn snapshot_round_trips_parameter_values() {
+        let mut scene = SceneInstance::new(SceneDescriptor::new(
+            "particles",
+            SceneKind::Particles {
+                emission: 0.5,
+                tone: 0.5,
+                seed: 0,
+            },
+        ));
+        let frame = AnalysisFrame {
+            rms: 0.7,
+            high_band_energy: 0.2,
+            ..AnalysisFrame::silent(1.0)
+        };
+        scene.update(&frame, 1.0 / 30.0);
+
+        let snapshot = scene.snapshot();
+        let restored = SceneInstance::restore(snapshot);
+
+        assert_eq!(restored.parameters, scene.parameters);
+        assert_eq!(restored.last_analysis, scene.last_analysis);
+    }
+
+    #[test]
+    fn tunnel_distance_advances_and_wraps() {
+        let mut scene = SceneInstance::new(SceneDescriptor::new(
+            "tunnel",
+            SceneKind::Tunnel {
+                speed: 2.0,
+                segment_length: 1.0,
+            },
+        ));
+        let frame = AnalysisFrame {
+            rms: 0.5,
+            ..AnalysisFrame::silent(0.0)
+        };
+
+        let mut last = scene.tunnel_distance();
+        let mut wrapped = false;
+        for _ in 0..60 {
+            scene.update(&frame, 1.0 / 30.0);
+            let distance = scene.tunnel_distance();
+            assert!((0.0..1.0).contains(&distance));
+            if distance < last {
+                wrapped = true;
+            }
+            last = distance;
+        }
+        assert!(wrapped, "distance should wrap modulo segment_length");
+    }
+
+    #[test]
+    fn text_scene_opacity_pulses_with_beat_confidence() {
+        let mut scene = SceneInstance::new(SceneDescriptor::new(
+            "lyrics",
+            SceneKind::Text {
+                content: "hello".to_string(),
+                size: 1.0,
+            },
+        ));
+        let frame = AnalysisFrame {
+            beat_confidence: 0.9,
+            ..AnalysisFrame::silent(0.0)
+        };
+
+        scene.update(&frame, 1.0 / 30.0);
+
+        assert_eq!(scene.parameters.get("text.opacity"), Some(&0.9));
+    }
+
+    #[test]
+    fn set_text_overrides_descriptor_content() {
+        let mut scene = SceneInstance::new(SceneDescriptor::new(
+            "lyrics",
+            SceneKind::Text {
+                content: "hello".to_string(),
+                size: 1.0,
+            },
+        ));
+        assert_eq!(scene.current_text(), Some("hello"));
+
+        scene.set_text("goodbye");
+        assert_eq!(scene.current_text(), Some("goodbye"));
+    }
+
+    #[test]
+    fn render_graph_broadcasts_updates_and_advances_every_scene() {
+        let mut graph = RenderGraph::new();
+        graph.push(SceneInstance::new(SceneDescriptor::new(
+            "tunnel",
+            SceneKind::Tunnel {
+                speed: 1.0,
+                segment_length: 4.0,
+            },
+        )));
+        graph.push(SceneInstance::new(SceneDescriptor::new(
+            "particles",
+            SceneKind::Particles {
+                emission: 0.5,
+                tone: 0.5,
+                seed: 0,
+            },
+        )));
+
+        graph.apply_updates(
+            &[ParameterUpdate {
+                target: "tunnel.speed".to_string(),
+                value: 9.0,
+            }],
+            0.0,
+        );
+        let frame = AnalysisFrame {
+            rms: 0.0,
+            ..AnalysisFrame::silent(0.0)
+        };
+        graph.update(&frame, 1.0 / 30.0);
+
+        assert_eq!(graph.scenes[0].scene.parameters.get("tunnel.speed"), Some(&9.0));
+        // The particles scene ignores the tunnel-targeted override entirely.
+        assert_eq!(graph.scenes[1].scene.parameters.get("particles.emission"), Some(&0.0));
+    }
+
+    #[test]
+    fn draw_detailed_names_the_scene_still_missing_analysis() {
+        let mut graph = RenderGraph::new();
+        graph.push(SceneInstance::new(SceneDescriptor::new(
+            "tunnel",
+            SceneKind::Tunnel {
+                speed: 1.0,
+                segment_length: 4.0,
+            },
+        )));
+        graph.push(SceneInstance::new(SceneDescriptor::new(
+            "particles",
+            SceneKind::Particles {
+                emission: 0.5,
+                tone: 0.5,
+                seed: 0,
+            },
+        )));
+
+        // Only "tunnel" receives an analysis frame; "particles" stays stale.
+        graph.scenes[0].scene.update(&AnalysisFrame::silent(0.0), 1.0 / 30.0);
+
+        assert_eq!(graph.draw(), Err(DrawError::MissingAnalysis));
+        assert_eq!(
+            graph.draw_detailed(),
+            Err(vec![StaleScene { index: 1, name: "particles".to_string() }])
+        );
+    }
+
+    #[test]
+    fn draw_succeeds_once_every_scene_has_analysis() {
+        let mut graph = RenderGraph::new();
+        graph.push(SceneInstance::new(SceneDescriptor::new(
+            "tunnel",
+            SceneKind::Tunnel {
+                speed: 1.0,
+                segment_length: 4.0,
+            },
+        )));
+        graph.update(&AnalysisFrame::silent(0.0), 1.0 / 30.0);
+
+        assert_eq!(graph.draw(), Ok(()));
+        assert_eq!(graph.draw_detailed(), Ok(()));
+    }
+
+    #[derive(Default)]
+    struct RecordingBackend {
+        frames_begun: u32,
+        frames_ended: u32,
+        drawn_scene_names: Vec<String>,
+    }
+
+    impl RenderBackend for RecordingBackend {
+        fn begin_frame(&mut self) {
+            self.frames_begun += 1;
+        }
+
+        fn draw_scene(&mut self, descriptor: &SceneDescriptor) {
+            self.drawn_scene_names.push(descriptor.name.clone());
+        }
+
+        fn end_frame(&mut self) {
+            self.frames_ended += 1;
+        }
+    }
+
+    #[test]
+    fn draw_with_backend_records_one_draw_call_per_registered_scene() {
+        let mut graph = RenderGraph::new();
+        graph.push(SceneInstance::new(SceneDescriptor::new(
+            "tunnel",
+            SceneKind::Tunnel {
+                speed: 1.0,
+                segment_length: 4.0,
+            },
+        )));
+        graph.push(SceneInstance::new(SceneDescriptor::new(
+            "particles",
+            SceneKind::Particles {
+                emission: 0.5,
+                tone: 0.5,
+                seed: 0,
+            },
+        )));
+        graph.update(&AnalysisFrame::silent(0.0), 1.0 / 30.0);
+
+        let mut backend = RecordingBackend::default();
+        assert_eq!(graph.draw_with_backend(&mut backend), Ok(()));
+
+        assert_eq!(backend.frames_begun, 1);
+        assert_eq!(backend.frames_ended, 1);
+        assert_eq!(backend.drawn_scene_names, vec!["tunnel".to_string(), "particles".to_string()]);
+    }
+
+    #[test]
+    fn draw_with_backend_reports_missing_analysis_without_drawing() {
+        let mut graph = RenderGraph::new();
+        graph.push(SceneInstance::new(SceneDescriptor::new(
+            "tunnel",
+            SceneKind::Tunnel {
+                speed: 1.0,
+                segment_length: 4.0,
+            },
+        )));
+
+        let mut backend = RecordingBackend::default();
+        assert_eq!(graph.draw_with_backend(&mut backend), Err(DrawError::MissingAnalysis));
+        assert_eq!(backend.frames_begun, 0, "a stale graph must not touch the backend at all");
+    }
+
+    #[test]
+    fn clear_drops_every_scene_and_returns_to_a_fresh_drawable_state() {
+        let mut graph = RenderGraph::new();
+        graph.push(SceneInstance::new(SceneDescriptor::new(
+            "tunnel",
+            SceneKind::Tunnel {
+                speed: 1.0,
+                segment_length: 4.0,
+            },
+        )));
+        graph.push(SceneInstance::new(SceneDescriptor::new(
+            "particles",
+            SceneKind::Particles {
+                emission: 0.5,
+                tone: 0.5,
+                seed: 0,
+            },
+        )));
+        graph.update(&AnalysisFrame::silent(0.0), 1.0 / 30.0);
+        assert_eq!(graph.draw(), Ok(()));
+
+        graph.clear();
+
+        assert!(graph.scenes.is_empty());
+        // An empty graph has no scene missing analysis, so it draws cleanly.
+        assert_eq!(graph.draw(), Ok(()));
+    }
+
+    #[test]
+    fn gradient_scene_keeps_drifting_through_silence() {
+        let mut scene = SceneInstance::new(SceneDescriptor::new("backdrop", SceneKind::Gradient { seed: 7 }));
+
+        let mut hues = Vec::new();
+        let mut brightnesses = Vec::new();
+        for i in 0..60 {
+            scene.update(&AnalysisFrame::silent(i as f64 / 30.0), 1.0 / 30.0);
+            hues.push(*scene.parameters.get("gradient.hue").unwrap());
+            brightnesses.push(*scene.parameters.get("gradient.brightness").unwrap());
+        }
+
+        assert!(hues.windows(2).any(|w| w[0] != w[1]), "hue should keep moving during silence");
+        assert!(
+            brightnesses.windows(2).any(|w| w[0] != w[1]),
+            "brightness should keep moving during silence"
+        );
+    }
+
+    #[test]
+    fn gradient_scenes_with_the_same_seed_drift_identically() {
+        let make_scene = || SceneInstance::new(SceneDescriptor::new("backdrop", SceneKind::Gradient { seed: 11 }));
+        let mut a = make_scene();
+        let mut b = make_scene();
+
+        for i in 0..20 {
+            let frame = AnalysisFrame { rms: 0.2, ..AnalysisFrame::silent(i as f64 / 30.0) };
+            a.update(&frame, 1.0 / 30.0);
+            b.update(&frame, 1.0 / 30.0);
+        }
+
+        assert_eq!(a.parameters.get("gradient.hue"), b.parameters.get("gradient.hue"));
+        assert_eq!(a.parameters.get("gradient.brightness"), b.parameters.get("gradient.brightness"));
+    }
+
+    #[test]
+    fn kaleidoscope_angle_advances_and_wraps_at_tau() {
+        let mut scene = SceneInstance::new(SceneDescriptor::new(
+            "kaleidoscope",
+            SceneKind::Kaleidoscope { order: 6 },
+        ));
+        let frame = AnalysisFrame {
+            rms: 0.5,
+            ..AnalysisFrame::silent(0.0)
+        };
+
+        let mut last = scene.kaleidoscope_angle();
+        let mut wrapped = false;
+        for _ in 0..200 {
+            scene.update(&frame, 1.0 / 30.0);
+            let angle = scene.kaleidoscope_angle();
+            assert!((0.0..std::f32::consts::TAU).contains(&angle));
+            if angle < last {
+                wrapped = true;
+            }
+            last = angle;
+        }
+        assert!(wrapped, "angle should wrap modulo TAU");
+        assert!(last > 0.0, "angle should have advanced from zero");
+
+        assert_eq!(scene.kaleidoscope_segments(), 6);
+        scene.set_parameter("kaleidoscope.order", 9.4);
+        assert_eq!(scene.kaleidoscope_segments(), 9);
+    }
+
+    #[test]
+    fn prefixed_scenes_only_receive_updates_matching_their_prefix() {
+        // Both scenes are Tunnels and would both adopt a "tunnel.speed"
+        // override if it reached them; only the one whose prefix matches
+        // should.
+        let mut graph = RenderGraph::new();
+        graph.push_with_prefix(
+            SceneInstance::new(SceneDescriptor::new(
+                "background",
+                SceneKind::Tunnel {
+                    speed: 1.0,
+                    segment_length: 4.0,
+                },
+            )),
+            "tunnel.",
+        );
+        graph.push_with_prefix(
+            SceneInstance::new(SceneDescriptor::new(
+                "foreground",
+                SceneKind::Tunnel {
+                    speed: 1.0,
+                    segment_length: 4.0,
+                },
+            )),
+            "other.",
+        );
+
+        graph.apply_updates(
+            &[ParameterUpdate {
+                target: "tunnel.speed".to_string(),
+                value: 9.0,
+            }],
+            0.0,
+        );
+        graph.update(&AnalysisFrame::silent(0.0), 1.0 / 30.0);
+
+        assert_eq!(graph.scenes[0].scene.parameters.get("tunnel.speed"), Some(&9.0));
+        assert_eq!(graph.scenes[1].scene.parameters.get("tunnel.speed"), Some(&1.0));
+    }
+
+    #[test]
+    fn switcher_respects_min_dwell_before_switching_on_alternating_energy() {
+        let config = AutoSwitcherConfig {
+            high_threshold: 0.6,
+            low_threshold: 0.3,
+            smoothing: 0.0, // react instantly so the test isolates dwell behaviour
+            min_dwell: 1.0,
+        };
+        let mut switcher = AutoSceneSwitcher::new(config, "tunnel", "particles");
+        assert_eq!(switcher.active_scene(), "tunnel");
+
+        let dt = 0.2;
+        // High energy immediately, but dwell hasn't elapsed yet: no switch.
+        assert_eq!(switcher.update(0.9, dt), "tunnel");
+        assert_eq!(switcher.update(0.9, dt), "tunnel");
+
+        // Energy drops back down before the dwell elapses; still no switch
+        // once dwell does elapse, since the smoothed energy no longer
+        // crosses the high threshold.
+        for _ in 0..3 {
+            switcher.update(0.1, dt);
+        }
+        assert_eq!(switcher.active_scene(), "tunnel");
+
+        // Sustained high energy for long enough to clear the dwell time.
+        let mut switched = false;
+        for _ in 0..10 {
+            if switcher.update(0.9, dt) == "particles" {
+                switched = true;
+                break;
+            }
+        }
+        assert!(switched, "should switch to particles once energy stays high past the dwell time");
+
+        // Switching back respects dwell too: a single low reading right
+        // after switching shouldn't immediately bounce back.
+        assert_eq!(switcher.update(0.1, dt), "particles");
+    }
+
+    #[test]
+    fn unprefixed_scenes_still_receive_every_update() {
+        let mut graph = RenderGraph::new();
+        graph.push(SceneInstance::new(SceneDescriptor::new(
+            "tunnel",
+            SceneKind::Tunnel {
+                speed: 1.0,
+                segment_length: 4.0,
+            },
+        )));
+
+        graph.apply_updates(
+            &[ParameterUpdate {
+                target: "tunnel.speed".to_string(),
+                value: 9.0,
+            }],
+            0.0,
+        );
+        graph.update(&AnalysisFrame::silent(0.0), 1.0 / 30.0);
+
+        assert_eq!(graph.scenes[0].scene.parameters.get("tunnel.speed"), Some(&9.0));
+    }
+
+    #[test]
+    fn update_history_retains_only_the_latest_batches_up_to_its_configured_depth() {
+        let mut graph = RenderGraph::new();
+        graph.history_depth = Some(2);
+
+        let batch = |value: f32| {
+            vec![ParameterUpdate {
+                target: "tunnel.speed".to_string(),
+                value,
+            }]
+        };
+
+        graph.apply_updates(&batch(1.0), 1.0);
+        graph.apply_updates(&batch(2.0), 2.0);
+        graph.apply_updates(&batch(3.0), 3.0);
+
+        let history: Vec<_> = graph.update_history().iter().collect();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].0, 2.0);
+        assert_eq!(history[0].1[0].value, 2.0);
+        assert_eq!(history[1].0, 3.0);
+        assert_eq!(history[1].1[0].value, 3.0);
+    }
+
+    #[test]
+    fn orphan_mapping_targets_reports_a_target_no_registered_scene_publishes() {
+        let mut graph = RenderGraph::new();
+        graph.push(SceneInstance::new(SceneDescriptor::new(
+            "kaleidoscope",
+            SceneKind::Kaleidoscope { order: 6 },
+        )));
+
+        let mut mappings = MappingMatrix::new();
+        mappings.push(crate::mapping::MappingDescriptor::new("rms", "kaleidoscope.rotation"));
+        mappings.push(crate::mapping::MappingDescriptor::new("rms", "tunnel.energy"));
+
+        let orphans = graph.orphan_mapping_targets(&mappings);
+        assert_eq!(orphans, vec!["tunnel.energy".to_string()]);
+    }
+
+    #[test]
+    fn fade_in_ramps_the_master_multiplier_from_zero_toward_one() {
+        let mut graph = RenderGraph::new();
+        graph.start_fade_in(FadeEnvelopeConfig {
+            fade_in_secs: 1.0,
+            fade_out_secs: 1.0,
+        });
+        assert_eq!(graph.master_multiplier(), 0.0);
+
+        let frame = AnalysisFrame::silent(0.0);
+        graph.update(&frame, 0.25);
+        assert!((graph.master_multiplier() - 0.25).abs() < 1e-6, "got {}", graph.master_multiplier());
+
+        graph.update(&frame, 0.25);
+        assert!((graph.master_multiplier() - 0.5).abs() < 1e-6, "got {}", graph.master_multiplier());
+
+        graph.update(&frame, 10.0);
+        assert_eq!(graph.master_multiplier(), 1.0);
+    }
+
+    #[test]
+    fn finish_fades_the_multiplier_out_to_zero_and_reports_completion() {
+        let mut graph = RenderGraph::new();
+        graph.start_fade_in(FadeEnvelopeConfig {
+            fade_in_secs: 0.0,
+            fade_out_secs: 1.0,
+        });
+        let frame = AnalysisFrame::silent(0.0);
+        graph.update(&frame, 0.0);
+        assert_eq!(graph.master_multiplier(), 1.0);
+
+        graph.finish();
+        assert!(!graph.fade_out_complete());
+
+        graph.update(&frame, 0.5);
+        assert!((graph.master_multiplier() - 0.5).abs() < 1e-6, "got {}", graph.master_multiplier());
+        assert!(!graph.fade_out_complete());
+
+        graph.update(&frame, 0.5);
+        assert_eq!(graph.master_multiplier(), 0.0);
+        assert!(graph.fade_out_complete());
+    }
+}
@@ -0,0 +1,338 @@
+//! Loads a show's cue timeline from a plain-text cue sheet, so a full set
+//! doesn't have to be programmed as `ScheduledEvent`s in code.
+
+use std::fmt;
+use std::path::Path;
+
+/// What triggers a [`ScheduledEvent`]: an absolute timestamp, or a beat
+/// count for tempo-relative cues.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CueTrigger {
+    Timestamp(f64),
+    Beat(u32),
+}
+
+/// Structured data a fired [`ScheduledEvent`] carries beyond its label, so a
+/// callback can act on it directly instead of pattern-matching a string.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum EventAction {
+    /// No structured payload; the label is the only information.
+    #[default]
+    None,
+    SwitchScene(String),
+    SetParameter { target: String, value: f32 },
+    Custom(serde_json::Value),
+}
+
+/// One cue loaded from a cue sheet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScheduledEvent {
+    pub label: String,
+    pub trigger: CueTrigger,
+    /// Repeat interval in seconds, from a cue sheet line's optional third
+    /// field. [`Scheduler::take_due`] does not re-queue an event: it fires
+    /// once whatever this holds.
+    pub interval: Option<f64>,
+    /// Structured data to act on when the event fires. Empty ([`EventAction::None`])
+    /// for events built from a plain cue sheet line or [`Self::new`].
+    pub payload: EventAction,
+}
+
+impl ScheduledEvent {
+    /// A plain timestamp-triggered event with no interval or payload.
+    pub fn new(timestamp: f64, label: impl Into<String>) -> Self {
+        Self {
+            label: label.into(),
+            trigger: CueTrigger::Timestamp(timestamp),
+            interval: None,
+            payload: EventAction::None,
+        }
+    }
+
+    pub fn with_payload(mut self, payload: EventAction) -> Self {
+        self.payload = payload;
+        self
+    }
+
+    /// Sort key used by [`Scheduler::load_cues`]: a timestamp cue's
+    /// timestamp, or a beat cue's beat number treated as a plain ordinal.
+    /// Mixing the two kinds in one sheet sorts each by its own number line,
+    /// not by wall-clock time.
+    fn order_key(&self) -> f64 {
+        match self.trigger {
+            CueTrigger::Timestamp(seconds) => seconds,
+            CueTrigger::Beat(beat) => beat as f64,
+        }
+    }
+}
+
+/// Error loading or parsing a cue sheet, identifying the offending line so
+/// it can be fixed without re-reading the whole file.
+#[derive(Debug)]
+pub enum CueSheetError {
+    Io(std::io::Error),
+    Malformed { line: usize, reason: String },
+}
+
+impl fmt::Display for CueSheetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CueSheetError::Io(e) => write!(f, "failed to read cue sheet: {e}"),
+            CueSheetError::Malformed { line, reason } => {
+                write!(f, "line {line}: {reason}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CueSheetError {}
+
+impl From<std::io::Error> for CueSheetError {
+    fn from(e: std::io::Error) -> Self {
+        CueSheetError::Io(e)
+    }
+}
+
+/// Holds a show's cues, loaded and sorted from a cue sheet.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Scheduler {
+    events: Vec<ScheduledEvent>,
+    /// When set, [`Self::take_due`] snaps each event's fire time to the
+    /// nearest timestamp in the frame timeline passed to it before comparing
+    /// against `now`, instead of firing the instant its own raw timestamp is
+    /// reached. This lines a cue up with a frame the renderer actually saw,
+    /// rather than an arbitrary sub-frame instant nothing ever observes. Off
+    /// by default.
+    pub quantize_to_frames: bool,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn events(&self) -> &[ScheduledEvent] {
+        &self.events
+    }
+
+    /// The scheduler's events, in trigger order, without consuming them.
+    /// Safe to poll every frame to render an upcoming-cues UI.
+    pub fn pending(&self) -> &[ScheduledEvent] {
+        &self.events
+    }
+
+    /// The earliest pending event's order key (a timestamp in seconds, or a
+    /// beat number for beat-relative cues), for a countdown display. `None`
+    /// once there are no pending events.
+    pub fn next_event_time(&self) -> Option<f32> {
+        self.events.first().map(|event| event.order_key() as f32)
+    }
+
+    /// Pops and returns every pending event whose fire time has been reached
+    /// by `now`, in trigger order. With [`Self::quantize_to_frames`] unset
+    /// (the default), an event fires the instant its own
+    /// [`ScheduledEvent::order_key`] is reached. With it set, the event's
+    /// fire time is first snapped to the nearest entry in
+    /// `frame_timestamps`, so it fires alongside a real emitted analysis
+    /// frame rather than at its unquantized timestamp.
+    pub fn take_due(&mut self, now: f64, frame_timestamps: &[f64]) -> Vec<ScheduledEvent> {
+        let mut due = Vec::new();
+        while let Some(event) = self.events.first() {
+            let fire_at = if self.quantize_to_frames {
+                nearest_timestamp(frame_timestamps, event.order_key()).unwrap_or_else(|| event.order_key())
+            } else {
+                event.order_key()
+            };
+            if fire_at > now {
+                break;
+            }
+            due.push(self.events.remove(0));
+        }
+        due
+    }
+
+    /// Parse a cue sheet of `timestamp,label[,interval]` lines (or
+    /// `beat:N,label[,interval]` for beat-relative cues), skipping blank
+    /// lines and `#`-prefixed comments, and load the resulting events
+    /// sorted by [`ScheduledEvent::order_key`].
+    pub fn load_cues(path: &Path) -> Result<Self, CueSheetError> {
+        let contents = std::fs::read_to_string(path)?;
+
+        let mut events = Vec::new();
+        for (index, raw_line) in contents.lines().enumerate() {
+            let line_number = index + 1;
+            let line = raw_line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            events.push(parse_cue_line(line, line_number)?);
+        }
+
+        events.sort_by(|a, b| a.order_key().partial_cmp(&b.order_key()).unwrap());
+        Ok(Self { events, ..Self::default() })
+    }
+}
+
+/// The entry in `candidates` closest to `target`, or `None` if `candidates`
+/// is empty.
+fn nearest_timestamp(candidates: &[f64], target: f64) -> Option<f64> {
+    candidates
+        .iter()
+        .copied()
+        .min_by(|a, b| (a - target).abs().partial_cmp(&(b - target).abs()).unwrap())
+}
+
+fn parse_cue_line(line: &str, line_number: usize) -> Result<ScheduledEvent, CueSheetError> {
+    let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+    if fields.len() < 2 || fields.len() > 3 {
+        return Err(CueSheetError::Malformed {
+            line: line_number,
+            reason: format!("expected 2 or 3 comma-separated fields, got {}", fields.len()),
+        });
+    }
+
+    let trigger = parse_trigger(fields[0]).ok_or_else(|| CueSheetError::Malformed {
+        line: line_number,
+        reason: format!("invalid timestamp or beat `{}`", fields[0]),
+    })?;
+
+    let label = fields[1].to_string();
+    if label.is_empty() {
+        return Err(CueSheetError::Malformed {
+            line: line_number,
+            reason: "label must not be empty".to_string(),
+        });
+    }
+
+    let interval = match fields.get(2) {
+        Some(raw) => Some(raw.parse::<f64>().map_err(|_| CueSheetError::Malformed {
+            line: line_number,
+            reason: format!("invalid interval `{raw}`"),
+        })?),
+        None => None,
+    };
+
+    Ok(ScheduledEvent {
+        label,
+        trigger,
+        interval,
+        payload: EventAction::None,
+    })
+}
+
+fn parse_trigger(field: &str) -> Option<CueTrigger> {
+    if let Some(rest) = field.strip_prefix("beat:") {
+        rest.parse::<u32>().ok().map(CueTrigger::Beat)
+    } else {
+        field.parse::<f64>().ok().map(CueTrigger::Timestamp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_three_line_cue_sheet_sorted_with_correct_timestamps_and_labels() {
+        let path = std::env::temp_dir().join("music_visualiser_cues_test.cue");
+        std::fs::write(&path, "# show cues\n\n2.0,chorus\n0.5,intro\nbeat:8,drop\n").unwrap();
+
+        let scheduler = Scheduler::load_cues(&path).expect("well-formed cue sheet should load");
+
+        let events = scheduler.events();
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].label, "intro");
+        assert_eq!(events[0].trigger, CueTrigger::Timestamp(0.5));
+        assert_eq!(events[1].label, "chorus");
+        assert_eq!(events[1].trigger, CueTrigger::Timestamp(2.0));
+        assert_eq!(events[2].label, "drop");
+        assert_eq!(events[2].trigger, CueTrigger::Beat(8));
+
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn pending_reflects_sorted_order_and_next_event_time_is_earliest() {
+        let path = std::env::temp_dir().join("music_visualiser_cues_pending_test.cue");
+        std::fs::write(&path, "5.0,outro\n0.2,intro\n2.5,chorus\n").unwrap();
+
+        let scheduler = Scheduler::load_cues(&path).expect("well-formed cue sheet should load");
+
+        let pending = scheduler.pending();
+        assert_eq!(pending.len(), 3);
+        assert_eq!(pending[0].label, "intro");
+        assert_eq!(pending[1].label, "chorus");
+        assert_eq!(pending[2].label, "outro");
+
+        assert_eq!(scheduler.next_event_time(), Some(0.2));
+
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn next_event_time_is_none_when_empty() {
+        assert_eq!(Scheduler::new().next_event_time(), None);
+    }
+
+    #[test]
+    fn scheduled_event_exposes_a_typed_set_parameter_payload() {
+        let event = ScheduledEvent::new(1.0, "flash").with_payload(EventAction::SetParameter {
+            target: "strobe.intensity".to_string(),
+            value: 0.8,
+        });
+
+        match event.payload {
+            EventAction::SetParameter { target, value } => {
+                assert_eq!(target, "strobe.intensity");
+                assert_eq!(value, 0.8);
+            }
+            other => panic!("expected a SetParameter payload, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn malformed_line_errors_with_its_line_number() {
+        let path = std::env::temp_dir().join("music_visualiser_cues_malformed_test.cue");
+        std::fs::write(&path, "0.5,intro\nnot-a-number,oops\n").unwrap();
+
+        let error = Scheduler::load_cues(&path).expect_err("malformed line should error");
+        match error {
+            CueSheetError::Malformed { line, .. } => assert_eq!(line, 2),
+            other => panic!("expected a Malformed error, got {other:?}"),
+        }
+
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn take_due_fires_events_whose_raw_timestamp_has_been_reached() {
+        let mut scheduler = Scheduler {
+            events: vec![ScheduledEvent::new(1.0, "a"), ScheduledEvent::new(2.0, "b")],
+            ..Scheduler::default()
+        };
+
+        assert!(scheduler.take_due(0.5, &[]).is_empty());
+
+        let due = scheduler.take_due(1.5, &[]);
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].label, "a");
+        assert_eq!(scheduler.pending().len(), 1);
+    }
+
+    #[test]
+    fn quantize_to_frames_snaps_fire_time_to_the_nearest_frame_timestamp() {
+        let mut scheduler = Scheduler {
+            events: vec![ScheduledEvent::new(1.03, "cue")],
+            quantize_to_frames: true,
+        };
+        let frames = [0.98, 1.05, 1.12];
+
+        // Nearest frame to 1.03 is 1.05, so the cue isn't due yet at 1.0.
+        assert!(scheduler.take_due(1.0, &frames).is_empty());
+
+        // Once "now" reaches the nearest frame, the cue fires.
+        let due = scheduler.take_due(1.05, &frames);
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].label, "cue");
+    }
+}
@@ -0,0 +1,261 @@
+//! Hand-built JSON Schema descriptors for the config types users hand-edit,
+//! so editors can offer completion and validation instead of users
+//! discovering typos via a cryptic serde error at load time.
+//!
+//! These are written by hand to follow the `Serialize`/`Deserialize` shape
+//! of their types (externally-tagged enums, defaulted fields) rather than
+//! being derived. The tests check that the schema parses, has the top-level
+//! properties, and uses `SceneKind`'s actual serde tag; other drift between
+//! a type and its schema fails no test. The mapping schema does not describe
+//! `smoothing_mode`, `one_euro` or `gradient`, and its `required` list holds
+//! only `source` and `target`, though deserializing a mapping also needs
+//! `min`, `max`, `curve`, `smoothing_mode`, `smoothing`, `one_euro`,
+//! `hold_below_gate` and `smoothing_in_beats`.
+
+use serde_json::{json, Value};
+
+fn scene_kind_schema() -> Value {
+    json!({
+        "oneOf": [
+            {
+                "type": "object",
+                "required": ["Kaleidoscope"],
+                "additionalProperties": false,
+                "properties": {
+                    "Kaleidoscope": {
+                        "type": "object",
+                        "required": ["order"],
+                        "additionalProperties": false,
+                        "properties": {
+                            "order": { "type": "integer", "minimum": 0 }
+                        }
+                    }
+                }
+            },
+            {
+                "type": "object",
+                "required": ["Particles"],
+                "additionalProperties": false,
+                "properties": {
+                    "Particles": {
+                        "type": "object",
+                        "required": ["emission", "tone"],
+                        "additionalProperties": false,
+                        "properties": {
+                            "emission": { "type": "number" },
+                            "tone": { "type": "number" },
+                            "seed": {
+                                "description": "Seeds the scene's particle PRNG for deterministic replay. Defaults to 0.",
+                                "type": "integer",
+                                "minimum": 0,
+                                "default": 0
+                            }
+                        }
+                    }
+                }
+            },
+            {
+                "type": "object",
+                "required": ["Tunnel"],
+                "additionalProperties": false,
+                "properties": {
+                    "Tunnel": {
+                        "type": "object",
+                        "required": ["speed", "segment_length"],
+                        "additionalProperties": false,
+                        "properties": {
+                            "speed": { "type": "number" },
+                            "segment_length": { "type": "number" }
+                        }
+                    }
+                }
+            },
+            {
+                "type": "object",
+                "required": ["Text"],
+                "additionalProperties": false,
+                "properties": {
+                    "Text": {
+                        "type": "object",
+                        "required": ["content", "size"],
+                        "additionalProperties": false,
+                        "properties": {
+                            "content": { "type": "string" },
+                            "size": { "type": "number" }
+                        }
+                    }
+                }
+            },
+            {
+                "type": "object",
+                "required": ["Gradient"],
+                "additionalProperties": false,
+                "properties": {
+                    "Gradient": {
+                        "type": "object",
+                        "additionalProperties": false,
+                        "properties": {
+                            "seed": {
+                                "description": "Seeds the ambient noise field's phase, so two scenes with the same seed drift identically. Defaults to 0.",
+                                "type": "integer",
+                                "minimum": 0,
+                                "default": 0
+                            }
+                        }
+                    }
+                }
+            }
+        ]
+    })
+}
+
+fn scene_descriptor_schema() -> Value {
+    json!({
+        "type": "object",
+        "required": ["name", "kind"],
+        "properties": {
+            "name": { "type": "string" },
+            "kind": scene_kind_schema(),
+            "asset": {
+                "description": "Name of a mesh registered in the config's asset map. Omit or null if this scene doesn't render one.",
+                "type": ["string", "null"],
+                "default": null
+            }
+        }
+    })
+}
+
+fn curve_schema() -> Value {
+    json!({
+        "enum": ["Linear", "Exponential", "Logarithmic"]
+    })
+}
+
+fn mapping_descriptor_schema() -> Value {
+    json!({
+        "type": "object",
+        "required": ["source", "target"],
+        "properties": {
+            "source": {
+                "description": "Canonical analysis feature name, e.g. \"rms\". Ignored when `expression` is set.",
+                "type": "string"
+            },
+            "target": { "type": "string" },
+            "expression": {
+                "description": "Arithmetic expression over analysis feature names; overrides `source` when set.",
+                "type": ["string", "null"],
+                "default": null
+            },
+            "min": { "type": "number", "default": 0.0 },
+            "max": { "type": "number", "default": 1.0 },
+            "curve": curve_schema(),
+            "smoothing": {
+                "description": "Exponential smoothing factor in 0..1; 0.0 disables smoothing.",
+                "type": "number",
+                "minimum": 0.0,
+                "maximum": 1.0,
+                "default": 0.0
+            },
+            "gate": {
+                "description": "Below this raw feature value the mapping is considered inactive.",
+                "type": ["number", "null"],
+                "default": null
+            },
+            "hold_below_gate": { "type": "boolean", "default": false },
+            "max_slew": {
+                "description": "Maximum change in output per second, in output units. null disables slew limiting.",
+                "type": ["number", "null"],
+                "default": null
+            },
+            "smoothing_in_beats": {
+                "description": "When true, `smoothing` is a per-beat retention factor rather than per-frame.",
+                "type": "boolean",
+                "default": false
+            },
+            "sources": {
+                "description": "Weighted [feature, weight] pairs; overrides `source` when set and `expression` is not.",
+                "type": ["array", "null"],
+                "items": { "type": "array", "prefixItems": [{ "type": "string" }, { "type": "number" }] },
+                "default": null
+            },
+            "min_delta": {
+                "description": "Suppress an update whose output moved less than this since the last one emitted. null emits every evaluation.",
+                "type": ["number", "null"],
+                "default": null
+            },
+            "enabled": { "type": "boolean", "default": true }
+        }
+    })
+}
+
+/// JSON Schema (draft 2020-12) for [`crate::config::AppConfig`], capturing
+/// the externally-tagged [`crate::scene::SceneKind`] encoding and every
+/// defaulted field on [`crate::mapping::MappingDescriptor`].
+pub fn app_config_schema() -> Value {
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "AppConfig",
+        "type": "object",
+        "required": ["scenes", "mappings", "assets"],
+        "properties": {
+            "scenes": {
+                "type": "array",
+                "items": scene_descriptor_schema()
+            },
+            "mappings": {
+                "type": "array",
+                "items": mapping_descriptor_schema()
+            },
+            "assets": {
+                "description": "Mesh asset name to filesystem path.",
+                "type": "object",
+                "additionalProperties": { "type": "string" }
+            }
+        }
+    })
+}
+
+/// [`app_config_schema`], serialised for writing to disk or handing to an
+/// editor's JSON Schema support.
+pub fn app_config_schema_string() -> String {
+    serde_json::to_string_pretty(&app_config_schema())
+        .expect("a schema built entirely from JSON-safe values always serialises")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schema_parses_as_valid_json_and_covers_top_level_properties() {
+        let text = app_config_schema_string();
+        let parsed: Value = serde_json::from_str(&text).expect("schema should be valid JSON");
+
+        let properties = parsed
+            .get("properties")
+            .expect("schema should have a properties object");
+        assert!(properties.get("scenes").is_some());
+        assert!(properties.get("mappings").is_some());
+    }
+
+    #[test]
+    fn scene_kind_schema_tag_matches_actual_serde_encoding() {
+        use crate::scene::SceneKind;
+
+        let kind = SceneKind::Particles {
+            emission: 0.5,
+            tone: 0.5,
+            seed: 0,
+        };
+        let encoded = serde_json::to_value(&kind).unwrap();
+        let tag = encoded.as_object().unwrap().keys().next().unwrap();
+
+        let schema = app_config_schema();
+        let variants = schema["properties"]["scenes"]["items"]["properties"]["kind"]["oneOf"]
+            .as_array()
+            .unwrap();
+        assert!(variants
+            .iter()
+            .any(|variant| variant["required"][0] == *tag));
+    }
+}
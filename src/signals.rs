@@ -0,0 +1,125 @@
+//! Deterministic synthetic test signals — sine, square, seeded white noise,
+//! impulse trains, and chirps — shared by analysis tests instead of each one
+//! reinventing its own sine block.
+
+/// Number of samples covering `duration_secs` at `sample_rate`.
+fn sample_count(duration_secs: f32, sample_rate: u32) -> usize {
+    (duration_secs * sample_rate as f32).round().max(0.0) as usize
+}
+
+/// A sine tone at `freq` Hz, peaking at `amplitude`.
+pub fn sine(freq: f32, amplitude: f32, duration_secs: f32, sample_rate: u32) -> Vec<f32> {
+    (0..sample_count(duration_secs, sample_rate))
+        .map(|i| amplitude * (2.0 * std::f32::consts::PI * freq * i as f32 / sample_rate as f32).sin())
+        .collect()
+}
+
+/// A square wave at `freq` Hz, alternating between `amplitude` and
+/// `-amplitude` with a 50% duty cycle.
+pub fn square(freq: f32, amplitude: f32, duration_secs: f32, sample_rate: u32) -> Vec<f32> {
+    (0..sample_count(duration_secs, sample_rate))
+        .map(|i| {
+            let phase = (freq * i as f32 / sample_rate as f32).fract();
+            if phase < 0.5 {
+                amplitude
+            } else {
+                -amplitude
+            }
+        })
+        .collect()
+}
+
+/// Deterministic pseudo-noise via a simple LCG, so a test gets reproducible
+/// white noise without a `rand` dependency. The same `seed` always produces
+/// the same samples.
+pub fn white_noise(amplitude: f32, duration_secs: f32, sample_rate: u32, seed: u32) -> Vec<f32> {
+    let mut state = seed;
+    (0..sample_count(duration_secs, sample_rate))
+        .map(|_| {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            amplitude * ((state as f32 / u32::MAX as f32) * 2.0 - 1.0)
+        })
+        .collect()
+}
+
+/// A single-sample impulse of height `amplitude` repeated every
+/// `period_secs`, starting at sample `0`, for onset/tempo detector tests
+/// that need precisely-spaced transients.
+pub fn impulse_train(amplitude: f32, period_secs: f32, duration_secs: f32, sample_rate: u32) -> Vec<f32> {
+    let period_samples = sample_count(period_secs, sample_rate).max(1);
+    (0..sample_count(duration_secs, sample_rate))
+        .map(|i| if i % period_samples == 0 { amplitude } else { 0.0 })
+        .collect()
+}
+
+/// A linear frequency sweep from `start_hz` to `end_hz` over the signal's
+/// duration, peaking at `amplitude`.
+pub fn chirp(start_hz: f32, end_hz: f32, amplitude: f32, duration_secs: f32, sample_rate: u32) -> Vec<f32> {
+    let duration_secs_safe = duration_secs.max(1e-9);
+    (0..sample_count(duration_secs, sample_rate))
+        .map(|i| {
+            let t = i as f32 / sample_rate as f32;
+            // Phase is the integral of instantaneous frequency over time,
+            // not `freq(t) * t`: for a linear sweep that integral is
+            // `start_hz * t + 0.5 * slope * t^2`.
+            let slope = (end_hz - start_hz) / duration_secs_safe;
+            let phase = start_hz * t + 0.5 * slope * t * t;
+            amplitude * (2.0 * std::f32::consts::PI * phase).sin()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rms(samples: &[f32]) -> f32 {
+        (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn sine_rms_is_amplitude_over_sqrt_two() {
+        let samples = sine(440.0, 1.0, 1.0, 48_000);
+        let expected = 1.0 / std::f32::consts::SQRT_2;
+        assert!((rms(&samples) - expected).abs() < 0.001, "got {}", rms(&samples));
+    }
+
+    #[test]
+    fn impulse_train_spaces_impulses_by_the_configured_period() {
+        let samples = impulse_train(1.0, 0.1, 0.5, 1_000);
+        let impulse_indices: Vec<usize> =
+            samples.iter().enumerate().filter(|(_, &s)| s != 0.0).map(|(i, _)| i).collect();
+
+        assert_eq!(impulse_indices, vec![0, 100, 200, 300, 400]);
+    }
+
+    #[test]
+    fn square_wave_alternates_between_positive_and_negative_amplitude() {
+        let samples = square(10.0, 2.0, 0.1, 1_000);
+        assert!(samples.contains(&2.0));
+        assert!(samples.contains(&-2.0));
+    }
+
+    #[test]
+    fn white_noise_is_deterministic_for_a_given_seed() {
+        let a = white_noise(1.0, 0.01, 48_000, 42);
+        let b = white_noise(1.0, 0.01, 48_000, 42);
+        assert_eq!(a, b);
+
+        let c = white_noise(1.0, 0.01, 48_000, 43);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn chirp_frequency_rises_from_start_to_end() {
+        let low_start = chirp(100.0, 4_000.0, 1.0, 1.0, 48_000);
+        let sign_changes = |samples: &[f32]| samples.windows(2).filter(|w| w[0].signum() != w[1].signum()).count();
+
+        let first_tenth = &low_start[..low_start.len() / 10];
+        let last_tenth = &low_start[low_start.len() * 9 / 10..];
+        assert!(
+            sign_changes(last_tenth) > sign_changes(first_tenth),
+            "the swept end should oscillate faster than the start"
+        );
+    }
+}
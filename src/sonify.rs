@@ -0,0 +1,119 @@
+//! Offline "sonification" of a recorded analysis back into audible cues, so
+//! a beat-detection or pitch-tracking mismatch can be heard rather than only
+//! read off a plot. Purely a debugging aid — it never runs during live
+//! playback.
+
+use crate::analysis::AnalysisFrame;
+use crate::wav::encode_wav_pcm16;
+
+const CLICK_DURATION_SECS: f32 = 0.015;
+const CLICK_TONE_HZ: f32 = 2_000.0;
+const TONE_MIN_HZ: f32 = 220.0;
+const TONE_MAX_HZ: f32 = 1_760.0;
+
+/// Approximates a spectral centroid in Hz from a frame's band energies.
+/// `AnalysisFrame` doesn't retain the true centroid frequency — only the
+/// band energies and a normalised spread — so this weighs high-band energy
+/// over low as a stand-in, mapped onto an audible tone range.
+fn approximate_centroid_hz(frame: &AnalysisFrame) -> f32 {
+    let total = frame.low_band_energy + frame.mid_band_energy + frame.high_band_energy;
+    if total <= 0.0 {
+        return TONE_MIN_HZ;
+    }
+    let balance = (frame.mid_band_energy * 0.5 + frame.high_band_energy) / total;
+    TONE_MIN_HZ + balance.clamp(0.0, 1.0) * (TONE_MAX_HZ - TONE_MIN_HZ)
+}
+
+/// Render a mono PCM16 WAV sonifying `frames`: a short click at each frame
+/// with a detected beat (`beat_confidence > 0.0`, matching the convention
+/// used elsewhere for "a beat landed on this block"), layered on a
+/// continuous tone whose pitch tracks [`approximate_centroid_hz`]. Each
+/// frame occupies a slot spanning to the next frame's timestamp (the last
+/// frame reuses the previous slot's duration).
+pub fn sonify(frames: &[AnalysisFrame], sample_rate: u32) -> Vec<u8> {
+    encode_wav_pcm16(sample_rate, &render_samples(frames, sample_rate))
+}
+
+fn render_samples(frames: &[AnalysisFrame], sample_rate: u32) -> Vec<f32> {
+    if frames.is_empty() {
+        return Vec::new();
+    }
+
+    let mut samples = Vec::new();
+    let mut phase = 0.0f32;
+    let mut previous_slot_secs = 0.0f32;
+
+    for (index, frame) in frames.iter().enumerate() {
+        let slot_secs = match frames.get(index + 1) {
+            Some(next) => (next.timestamp - frame.timestamp).max(0.0) as f32,
+            None => previous_slot_secs,
+        };
+        previous_slot_secs = slot_secs;
+
+        let slot_len = (slot_secs * sample_rate as f32).round() as usize;
+        let click_len = ((CLICK_DURATION_SECS * sample_rate as f32) as usize).min(slot_len);
+        let freq = approximate_centroid_hz(frame);
+        let is_beat = frame.beat_confidence > 0.0;
+
+        for i in 0..slot_len {
+            phase = (phase + freq / sample_rate as f32).fract();
+            let mut sample = (phase * std::f32::consts::TAU).sin() * 0.2;
+
+            if is_beat && i < click_len {
+                let envelope = 1.0 - (i as f32 / click_len.max(1) as f32);
+                let click_phase = i as f32 * CLICK_TONE_HZ / sample_rate as f32;
+                sample += (click_phase * std::f32::consts::TAU).sin() * envelope * 0.8;
+            }
+
+            samples.push(sample.clamp(-1.0, 1.0));
+        }
+    }
+
+    samples
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wav::decode_wav;
+
+    #[test]
+    fn beat_frame_produces_a_click_burst_at_its_timestamp() {
+        let frames = vec![
+            AnalysisFrame {
+                beat_confidence: 1.0,
+                low_band_energy: 1.0,
+                ..AnalysisFrame::silent(0.0)
+            },
+            AnalysisFrame {
+                ..AnalysisFrame::silent(0.1)
+            },
+        ];
+
+        let bytes = sonify(&frames, 8_000);
+        let decoded = decode_wav(&bytes).expect("sonify should emit a well-formed wav");
+        assert!(!decoded.samples.is_empty());
+
+        let click_len = (CLICK_DURATION_SECS * 8_000.0) as usize;
+        let click_window = &decoded.samples[..click_len];
+        assert!(
+            click_window.iter().any(|&s| s.abs() > 0.5),
+            "expected a loud click burst within the beat's click window"
+        );
+    }
+
+    #[test]
+    fn silent_frames_without_a_beat_stay_quiet_relative_to_a_click() {
+        let frames = vec![
+            AnalysisFrame::silent(0.0),
+            AnalysisFrame::silent(0.1),
+        ];
+
+        let bytes = sonify(&frames, 8_000);
+        let decoded = decode_wav(&bytes).expect("sonify should emit a well-formed wav");
+
+        let click_len = (CLICK_DURATION_SECS * 8_000.0) as usize;
+        let window = &decoded.samples[..click_len];
+        assert!(window.iter().all(|&s| s.abs() < 0.5));
+    }
+}
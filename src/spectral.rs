@@ -0,0 +1,463 @@
+//! FFT-backed spectral analysis shared by frequency-domain features.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::Arc;
+
+use realfft::num_complex::Complex32;
+use realfft::{RealFftPlanner, RealToComplex};
+
+/// Error returned by [`SpectralAnalyzer::try_new`] when `fft_size` can't be
+/// planned by the underlying FFT.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpectralError {
+    InvalidFftSize(usize),
+}
+
+impl fmt::Display for SpectralError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpectralError::InvalidFftSize(size) => {
+                write!(f, "fft_size {size} can't be planned; realfft requires a size of at least 2")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SpectralError {}
+
+/// Standard concert pitch: A above middle C at 440 Hz. Used by
+/// [`compute_frequency_features`] to bin frequencies into pitch classes
+/// unless a track is tuned to a different reference, e.g. 432 Hz.
+pub const DEFAULT_TUNING_REFERENCE_HZ: f32 = 440.0;
+
+/// Computes magnitude spectra for fixed-size blocks and derives a
+/// harmonic/percussive decomposition via median filtering across a short
+/// rolling history of frames (à la Fitzgerald's HPSS).
+#[derive(Clone)]
+pub struct SpectralAnalyzer {
+    fft_size: usize,
+    fft: Arc<dyn RealToComplex<f32>>,
+    history: VecDeque<Vec<f32>>,
+    history_len: usize,
+}
+
+impl SpectralAnalyzer {
+    /// Fallible counterpart to [`Self::new`]: `realfft` panics if asked to
+    /// plan a degenerate size, so this validates `fft_size` first and
+    /// reports [`SpectralError::InvalidFftSize`] instead of risking that
+    /// panic. Prefer this over [`Self::new`] whenever `fft_size` comes from
+    /// outside the crate (e.g. [`crate::engine::DualResolutionConfig`])
+    /// rather than a value this crate already validated itself.
+    pub fn try_new(fft_size: usize) -> Result<Self, SpectralError> {
+        if fft_size < 2 {
+            return Err(SpectralError::InvalidFftSize(fft_size));
+        }
+        let mut planner = RealFftPlanner::<f32>::new();
+        let fft = planner.plan_fft_forward(fft_size);
+        Ok(Self {
+            fft_size,
+            fft,
+            history: VecDeque::new(),
+            history_len: 7,
+        })
+    }
+
+    /// Panics if `fft_size` can't be planned. Callers that haven't already
+    /// validated `fft_size` themselves should use [`Self::try_new`] instead.
+    pub fn new(fft_size: usize) -> Self {
+        match Self::try_new(fft_size) {
+            Ok(analyzer) => analyzer,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    pub fn fft_size(&self) -> usize {
+        self.fft_size
+    }
+
+    fn windowed_input(&self, samples: &[f32]) -> Vec<f32> {
+        let mut input = vec![0.0f32; self.fft_size];
+        let n = samples.len().min(self.fft_size);
+        for (i, sample) in samples.iter().take(n).enumerate() {
+            let phase = 2.0 * std::f32::consts::PI * i as f32 / (self.fft_size as f32 - 1.0);
+            let window = 0.5 - 0.5 * phase.cos();
+            input[i] = sample * window;
+        }
+        input
+    }
+
+    /// Magnitude spectrum of a block, zero-padded or truncated to the
+    /// analyzer's FFT size.
+    pub fn magnitude_spectrum(&self, samples: &[f32]) -> Vec<f32> {
+        let mut input = self.windowed_input(samples);
+        let mut output: Vec<Complex32> = self.fft.make_output_vec();
+        let mut scratch = self.fft.make_scratch_vec();
+        self.fft
+            .process_with_scratch(&mut input, &mut output, &mut scratch)
+            .expect("fixed-size FFT plan should always accept its own buffers");
+        output.into_iter().map(|c| c.norm()).collect()
+    }
+
+    /// Compute the block's magnitude spectrum, fold it into the rolling
+    /// history, and return `(harmonic_energy, percussive_energy)`.
+    pub fn hpss(&mut self, samples: &[f32]) -> (f32, f32) {
+        let spectrum = self.magnitude_spectrum(samples);
+        self.history.push_back(spectrum.clone());
+        while self.history.len() > self.history_len {
+            self.history.pop_front();
+        }
+
+        let bins = spectrum.len();
+        let mut harmonic_energy = 0.0f32;
+        let mut percussive_energy = 0.0f32;
+        for bin in 0..bins {
+            let mut temporal: Vec<f32> = self.history.iter().map(|frame| frame[bin]).collect();
+            temporal.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            let harmonic_estimate = temporal[temporal.len() / 2];
+
+            let lo = bin.saturating_sub(2);
+            let hi = (bin + 2).min(bins.saturating_sub(1));
+            let mut spatial = spectrum[lo..=hi].to_vec();
+            spatial.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            let percussive_estimate = spatial[spatial.len() / 2];
+
+            if harmonic_estimate >= percussive_estimate {
+                harmonic_energy += spectrum[bin];
+            } else {
+                percussive_energy += spectrum[bin];
+            }
+        }
+        (harmonic_energy, percussive_energy)
+    }
+
+    /// Chroma vector for the given block at this analyzer's FFT size, binned
+    /// relative to [`DEFAULT_TUNING_REFERENCE_HZ`].
+    pub fn chroma(&self, samples: &[f32], sample_rate: u32) -> [f32; 12] {
+        self.frequency_features(samples, sample_rate).chroma
+    }
+
+    /// Chroma, spectral crest, and spectral spread for the given block at
+    /// this analyzer's FFT size, binning chroma relative to
+    /// [`DEFAULT_TUNING_REFERENCE_HZ`]. See [`compute_frequency_features`]
+    /// and [`Self::frequency_features_tuned`] for a configurable reference.
+    pub fn frequency_features(&self, samples: &[f32], sample_rate: u32) -> FrequencyFeatures {
+        self.frequency_features_tuned(samples, sample_rate, DEFAULT_TUNING_REFERENCE_HZ)
+    }
+
+    /// Like [`Self::frequency_features`], but bins chroma relative to
+    /// `reference_hz` instead of assuming standard A440 tuning. See
+    /// [`compute_frequency_features`].
+    pub fn frequency_features_tuned(&self, samples: &[f32], sample_rate: u32, reference_hz: f32) -> FrequencyFeatures {
+        let spectrum = self.magnitude_spectrum(samples);
+        compute_frequency_features(&spectrum, sample_rate, self.fft_size, reference_hz)
+    }
+}
+
+/// Frequency-domain shape features derived from one magnitude spectrum.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrequencyFeatures {
+    /// 12-bin pitch-class energy distribution. See
+    /// [`compute_frequency_features`].
+    pub chroma: [f32; 12],
+    /// Peak magnitude divided by mean magnitude: high for a few dominant
+    /// tones, close to 1 for broadband noise.
+    pub spectral_crest: f32,
+    /// Magnitude-weighted standard deviation of bin frequencies around the
+    /// spectral centroid, normalised by the Nyquist frequency into `0..1`:
+    /// low for energy concentrated around one frequency, high for energy
+    /// spread across the spectrum.
+    pub spectral_spread: f32,
+}
+
+/// Fold a magnitude spectrum into a 12-bin chroma (pitch-class) vector by
+/// mapping each bin's frequency to the nearest pitch class relative to
+/// `reference_hz` and accumulating its energy there (normalised to sum to
+/// 1), and derive the spectrum's crest and spread. A `reference_hz` that
+/// doesn't match the input's actual tuning (e.g. 440 against a 432 Hz
+/// track) smears energy across adjacent pitch classes instead of
+/// concentrating it in one. See [`FrequencyFeatures`] and
+/// [`DEFAULT_TUNING_REFERENCE_HZ`].
+pub fn compute_frequency_features(spectrum: &[f32], sample_rate: u32, fft_size: usize, reference_hz: f32) -> FrequencyFeatures {
+    let mut chroma = [0.0f32; 12];
+    let bin_hz = sample_rate as f32 / fft_size as f32;
+
+    let mut magnitude_sum = 0.0f32;
+    let mut weighted_freq_sum = 0.0f32;
+    let mut peak_magnitude = 0.0f32;
+
+    for (bin, &magnitude) in spectrum.iter().enumerate() {
+        let freq = bin as f32 * bin_hz;
+        magnitude_sum += magnitude;
+        weighted_freq_sum += freq * magnitude;
+        peak_magnitude = peak_magnitude.max(magnitude);
+
+        if freq < 20.0 {
+            // Too close to DC for a stable pitch-class estimate.
+            continue;
+        }
+        let pitch = 12.0 * (freq / reference_hz).log2() + 69.0;
+        let pitch_class = pitch.rem_euclid(12.0).round() as usize % 12;
+        chroma[pitch_class] += magnitude;
+    }
+
+    let total: f32 = chroma.iter().sum();
+    if total > 0.0 {
+        for bin in chroma.iter_mut() {
+            *bin /= total;
+        }
+    }
+
+    let spectral_crest = if magnitude_sum > 0.0 {
+        let mean_magnitude = magnitude_sum / spectrum.len() as f32;
+        peak_magnitude / mean_magnitude
+    } else {
+        0.0
+    };
+
+    let spectral_spread = if magnitude_sum > 0.0 {
+        let centroid = weighted_freq_sum / magnitude_sum;
+        let variance: f32 = spectrum
+            .iter()
+            .enumerate()
+            .map(|(bin, &magnitude)| magnitude * (bin as f32 * bin_hz - centroid).powi(2))
+            .sum::<f32>()
+            / magnitude_sum;
+        let nyquist = sample_rate as f32 / 2.0;
+        variance.sqrt() / nyquist
+    } else {
+        0.0
+    };
+
+    FrequencyFeatures {
+        chroma,
+        spectral_crest,
+        spectral_spread,
+    }
+}
+
+/// Magnitude-weighted mean bin frequency: where the spectrum's energy is
+/// centred. `0.0` for a silent spectrum.
+pub fn spectral_centroid(spectrum: &[f32], sample_rate: u32, fft_size: usize) -> f32 {
+    let bin_hz = sample_rate as f32 / fft_size as f32;
+    let mut weighted_sum = 0.0f32;
+    let mut magnitude_sum = 0.0f32;
+    for (bin, &magnitude) in spectrum.iter().enumerate() {
+        weighted_sum += bin as f32 * bin_hz * magnitude;
+        magnitude_sum += magnitude;
+    }
+    if magnitude_sum > 0.0 {
+        weighted_sum / magnitude_sum
+    } else {
+        0.0
+    }
+}
+
+/// Sum the magnitude spectrum over `[low_hz, high_hz)`, normalised by
+/// `fft_size` so the result is comparable across spectra taken at different
+/// FFT sizes (a sinusoid's peak bin magnitude scales with `fft_size`).
+pub fn band_energy(spectrum: &[f32], sample_rate: u32, fft_size: usize, low_hz: f32, high_hz: f32) -> f32 {
+    let bin_hz = sample_rate as f32 / fft_size as f32;
+    let mut energy = 0.0f32;
+    for (bin, &magnitude) in spectrum.iter().enumerate() {
+        let freq = bin as f32 * bin_hz;
+        if freq >= low_hz && freq < high_hz {
+            energy += magnitude;
+        }
+    }
+    energy / fft_size as f32
+}
+
+/// Remap a linear-frequency magnitude spectrum onto a log-frequency
+/// (constant-Q-like) grid at `bins_per_octave` resolution starting at
+/// `min_hz`, so downstream centroid/energy features better match musical
+/// perception instead of a linear FFT grid's bass under-resolution and
+/// treble over-resolution. Each log bin sums the linear bins whose frequency
+/// falls within its span; bins below `min_hz` are dropped.
+pub fn log_frequency_spectrum(
+    spectrum: &[f32],
+    sample_rate: u32,
+    fft_size: usize,
+    bins_per_octave: u32,
+    min_hz: f32,
+) -> Vec<f32> {
+    let bin_hz = sample_rate as f32 / fft_size as f32;
+    let nyquist = sample_rate as f32 / 2.0;
+    if min_hz <= 0.0 || nyquist <= min_hz {
+        return Vec::new();
+    }
+
+    let octaves = (nyquist / min_hz).log2();
+    let num_bins = (octaves * bins_per_octave as f32).ceil().max(1.0) as usize;
+    let mut log_bins = vec![0.0f32; num_bins];
+
+    for (bin, &magnitude) in spectrum.iter().enumerate() {
+        let freq = bin as f32 * bin_hz;
+        if freq < min_hz {
+            continue;
+        }
+        let log_bin = ((freq / min_hz).log2() * bins_per_octave as f32) as usize;
+        if let Some(slot) = log_bins.get_mut(log_bin) {
+            *slot += magnitude;
+        }
+    }
+
+    log_bins
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(freq: f32, sample_rate: f32, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| (2.0 * std::f32::consts::PI * freq * i as f32 / sample_rate).sin())
+            .collect()
+    }
+
+    /// A single broadband click: one sharp non-zero sample amid silence,
+    /// which is what actually distinguishes a percussive onset from a
+    /// sustained tone under temporal median filtering.
+    fn click(len: usize) -> Vec<f32> {
+        let mut block = vec![0.0f32; len];
+        block[len / 2] = 1.0;
+        block
+    }
+
+    #[test]
+    fn sustained_tone_is_mostly_harmonic() {
+        let mut analyzer = SpectralAnalyzer::new(512);
+        let block = sine(440.0, 48_000.0, 512);
+        let mut last = (0.0, 0.0);
+        for _ in 0..8 {
+            last = analyzer.hpss(&block);
+        }
+        assert!(last.0 > last.1, "expected harmonic > percussive, got {last:?}");
+    }
+
+    #[test]
+    fn click_train_is_mostly_percussive() {
+        let mut analyzer = SpectralAnalyzer::new(512);
+        let silence = vec![0.0f32; 512];
+        let click_block = click(512);
+
+        // Settle the temporal median filter on silence, then hit it with a
+        // single broadband click: a short burst that is loud in every bin
+        // but absent from its neighbours in time looks percussive, unlike a
+        // tone that stays put.
+        for _ in 0..6 {
+            analyzer.hpss(&silence);
+        }
+        let last = analyzer.hpss(&click_block);
+        assert!(last.1 > last.0, "expected percussive > harmonic, got {last:?}");
+    }
+
+    /// Deterministic pseudo-noise via a simple LCG, so the test doesn't need
+    /// a random-number dependency.
+    fn noise(len: usize, seed: u32) -> Vec<f32> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                (state as f32 / u32::MAX as f32) * 2.0 - 1.0
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sine_has_higher_crest_and_lower_spread_than_noise_at_equal_energy() {
+        let analyzer = SpectralAnalyzer::new(2048);
+        let sine_block = sine(440.0, 48_000.0, 2048);
+        let mut noise_block = noise(2048, 12_345);
+
+        let rms = |block: &[f32]| (block.iter().map(|s| s * s).sum::<f32>() / block.len() as f32).sqrt();
+        let scale = rms(&sine_block) / rms(&noise_block);
+        for sample in noise_block.iter_mut() {
+            *sample *= scale;
+        }
+
+        let sine_features = analyzer.frequency_features(&sine_block, 48_000);
+        let noise_features = analyzer.frequency_features(&noise_block, 48_000);
+
+        assert!(
+            sine_features.spectral_crest > noise_features.spectral_crest,
+            "sine crest {} should exceed noise crest {}",
+            sine_features.spectral_crest,
+            noise_features.spectral_crest
+        );
+        assert!(
+            sine_features.spectral_spread < noise_features.spectral_spread,
+            "sine spread {} should be below noise spread {}",
+            sine_features.spectral_spread,
+            noise_features.spectral_spread
+        );
+    }
+
+    #[test]
+    fn a440_tone_lights_up_the_a_chroma_bin() {
+        let analyzer = SpectralAnalyzer::new(2048);
+        let block = sine(440.0, 48_000.0, 2048);
+        let chroma = analyzer.chroma(&block, 48_000);
+
+        let a_bin = 9; // MIDI pitch class 69 % 12 == A
+        let (dominant, _) = chroma
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.partial_cmp(b.1).unwrap())
+            .unwrap();
+        assert_eq!(dominant, a_bin, "chroma = {chroma:?}");
+    }
+
+    #[test]
+    fn a_configurable_reference_frequency_sharpens_a_detuned_tones_chroma_bin() {
+        let analyzer = SpectralAnalyzer::new(2048);
+        let block = sine(432.0, 48_000.0, 2048);
+        let a_bin = 9; // MIDI pitch class 69 % 12 == A
+
+        let tuned = analyzer.frequency_features_tuned(&block, 48_000, 432.0).chroma;
+        let mistuned = analyzer.frequency_features_tuned(&block, 48_000, DEFAULT_TUNING_REFERENCE_HZ).chroma;
+
+        assert!(
+            tuned[a_bin] > mistuned[a_bin],
+            "binning against the track's actual 432 Hz reference should concentrate more energy \
+             in the A bin than binning against standard A440, tuned = {tuned:?}, mistuned = {mistuned:?}"
+        );
+    }
+
+    #[test]
+    fn an_octave_jump_shifts_the_log_spectrum_peak_by_a_constant_bin_count() {
+        let analyzer = SpectralAnalyzer::new(4096);
+        let sample_rate = 48_000;
+        let bins_per_octave = 12;
+        let min_hz = 27.5;
+
+        let low = sine(220.0, sample_rate as f32, 4096);
+        let high = sine(440.0, sample_rate as f32, 4096); // exactly one octave up
+
+        let low_log = log_frequency_spectrum(
+            &analyzer.magnitude_spectrum(&low),
+            sample_rate,
+            4096,
+            bins_per_octave,
+            min_hz,
+        );
+        let high_log = log_frequency_spectrum(
+            &analyzer.magnitude_spectrum(&high),
+            sample_rate,
+            4096,
+            bins_per_octave,
+            min_hz,
+        );
+
+        let peak_bin = |bins: &[f32]| bins.iter().enumerate().max_by(|a, b| a.1.partial_cmp(b.1).unwrap()).unwrap().0;
+
+        let low_peak = peak_bin(&low_log);
+        let high_peak = peak_bin(&high_log);
+
+        assert_eq!(
+            high_peak as i64 - low_peak as i64,
+            bins_per_octave as i64,
+            "an octave jump should shift the peak by exactly one octave's worth of bins"
+        );
+    }
+}
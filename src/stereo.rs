@@ -0,0 +1,47 @@
+//! Stereo-image analysis. Kept separate from [`crate::engine::AnalysisEngine`],
+//! whose per-block pipeline is otherwise entirely mono: see
+//! [`crate::engine::AnalysisEngine::process_stereo_block`] for the entry
+//! point that ties this back into a regular [`crate::analysis::AnalysisFrame`].
+
+/// Left/right balance in `-1.0..=1.0`, derived from each channel's energy:
+/// `-1.0` when all the energy is in `left`, `0.0` when the two channels are
+/// evenly balanced, `1.0` when all the energy is in `right`. `0.0` (centered)
+/// if both channels are silent.
+pub fn balance(left: &[f32], right: &[f32]) -> f32 {
+    let left_energy: f32 = left.iter().map(|s| s * s).sum();
+    let right_energy: f32 = right.iter().map(|s| s * s).sum();
+    let total = left_energy + right_energy;
+    if total <= 0.0 {
+        0.0
+    } else {
+        ((right_energy - left_energy) / total).clamp(-1.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_left_only_signal_reports_a_balance_near_negative_one() {
+        let left = vec![1.0_f32; 512];
+        let right = vec![0.0_f32; 512];
+
+        assert!((balance(&left, &right) - (-1.0)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn a_centered_signal_reports_a_balance_near_zero() {
+        let left = vec![0.5_f32; 512];
+        let right = vec![0.5_f32; 512];
+
+        assert!(balance(&left, &right).abs() < 1e-6);
+    }
+
+    #[test]
+    fn silence_on_both_channels_reports_a_centered_balance() {
+        let silence = vec![0.0_f32; 512];
+
+        assert_eq!(balance(&silence, &silence), 0.0);
+    }
+}
@@ -0,0 +1,247 @@
+//! Session-level aggregates accumulated incrementally as analysis frames
+//! arrive.
+
+use crate::analysis::AnalysisFrame;
+
+/// Krumhansl-Schmuckler major-key profile, rooted at C.
+const KS_MAJOR: [f32; 12] = [
+    6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88,
+];
+
+/// Krumhansl-Schmuckler minor-key profile, rooted at C.
+const KS_MINOR: [f32; 12] = [
+    6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PitchClass {
+    C,
+    CSharp,
+    D,
+    DSharp,
+    E,
+    F,
+    FSharp,
+    G,
+    GSharp,
+    A,
+    ASharp,
+    B,
+}
+
+impl PitchClass {
+    const ALL: [PitchClass; 12] = [
+        PitchClass::C,
+        PitchClass::CSharp,
+        PitchClass::D,
+        PitchClass::DSharp,
+        PitchClass::E,
+        PitchClass::F,
+        PitchClass::FSharp,
+        PitchClass::G,
+        PitchClass::GSharp,
+        PitchClass::A,
+        PitchClass::ASharp,
+        PitchClass::B,
+    ];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Major,
+    Minor,
+}
+
+fn rotate(profile: &[f32; 12], root: usize) -> [f32; 12] {
+    let mut out = [0.0; 12];
+    for (i, value) in profile.iter().enumerate() {
+        out[(i + root) % 12] = *value;
+    }
+    out
+}
+
+fn correlation(a: &[f32; 12], b: &[f32; 12]) -> f32 {
+    let mean_a = a.iter().sum::<f32>() / 12.0;
+    let mean_b = b.iter().sum::<f32>() / 12.0;
+    let mut num = 0.0;
+    let mut den_a = 0.0;
+    let mut den_b = 0.0;
+    for i in 0..12 {
+        let da = a[i] - mean_a;
+        let db = b[i] - mean_b;
+        num += da * db;
+        den_a += da * da;
+        den_b += db * db;
+    }
+    if den_a == 0.0 || den_b == 0.0 {
+        0.0
+    } else {
+        num / (den_a.sqrt() * den_b.sqrt())
+    }
+}
+
+/// Bounds a tempo estimate to a musically plausible range by folding octave
+/// errors (double- or half-time onset detections) back into range, rather
+/// than reporting them as-is.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TempoBounds {
+    pub min_bpm: f32,
+    pub max_bpm: f32,
+}
+
+impl Default for TempoBounds {
+    fn default() -> Self {
+        Self {
+            min_bpm: 60.0,
+            max_bpm: 180.0,
+        }
+    }
+}
+
+/// Halve or double `bpm` until it falls within `bounds`, or give up once
+/// another halving/doubling would only push it back out of range.
+fn fold_bpm(mut bpm: f32, bounds: TempoBounds) -> f32 {
+    for _ in 0..8 {
+        if bpm > bounds.max_bpm && bpm / 2.0 >= bounds.min_bpm {
+            bpm /= 2.0;
+        } else if bpm < bounds.min_bpm && bpm * 2.0 <= bounds.max_bpm {
+            bpm *= 2.0;
+        } else {
+            break;
+        }
+    }
+    bpm
+}
+
+/// Running aggregates for a whole analysis session, updated incrementally
+/// as frames arrive.
+#[derive(Debug, Clone, Default)]
+pub struct AnalysisSummary {
+    pub frame_count: u64,
+    pub average_chroma: [f32; 12],
+    pub key: Option<PitchClass>,
+    pub mode: Option<Mode>,
+    pub tempo_bpm: Option<f32>,
+    /// Plausible-range bounds applied to [`Self::tempo_bpm`] as beats arrive.
+    pub tempo_bounds: TempoBounds,
+    last_beat_timestamp: Option<f64>,
+}
+
+impl AnalysisSummary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update(&mut self, frame: &AnalysisFrame) {
+        self.frame_count += 1;
+        let n = self.frame_count as f32;
+        for i in 0..12 {
+            self.average_chroma[i] += (frame.chroma[i] - self.average_chroma[i]) / n;
+        }
+        self.estimate_key();
+        self.estimate_tempo(frame);
+    }
+
+    /// Track inter-onset intervals between beats to estimate tempo, folding
+    /// each raw estimate into [`Self::tempo_bounds`] to correct for
+    /// double-time/half-time octave errors.
+    fn estimate_tempo(&mut self, frame: &AnalysisFrame) {
+        if frame.beat_confidence <= 0.0 {
+            return;
+        }
+        if let Some(last) = self.last_beat_timestamp {
+            let interval = frame.timestamp - last;
+            if interval > 0.0 {
+                let raw_bpm = 60.0 / interval as f32;
+                self.tempo_bpm = Some(fold_bpm(raw_bpm, self.tempo_bounds));
+            }
+        }
+        self.last_beat_timestamp = Some(frame.timestamp);
+    }
+
+    fn estimate_key(&mut self) {
+        let mut best_correlation = f32::NEG_INFINITY;
+        let mut best = None;
+        for (root, pitch_class) in PitchClass::ALL.into_iter().enumerate() {
+            let major = correlation(&self.average_chroma, &rotate(&KS_MAJOR, root));
+            if major > best_correlation {
+                best_correlation = major;
+                best = Some((pitch_class, Mode::Major));
+            }
+            let minor = correlation(&self.average_chroma, &rotate(&KS_MINOR, root));
+            if minor > best_correlation {
+                best_correlation = minor;
+                best = Some((pitch_class, Mode::Minor));
+            }
+        }
+        if let Some((pitch_class, mode)) = best {
+            self.key = Some(pitch_class);
+            self.mode = Some(mode);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spectral::SpectralAnalyzer;
+
+    fn sine(freq: f32, sample_rate: f32, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| (2.0 * std::f32::consts::PI * freq * i as f32 / sample_rate).sin())
+            .collect()
+    }
+
+    fn chord(freqs: &[f32], sample_rate: f32, len: usize) -> Vec<f32> {
+        let mut out = vec![0.0f32; len];
+        for &freq in freqs {
+            for (sample, tone) in out.iter_mut().zip(sine(freq, sample_rate, len)) {
+                *sample += tone / freqs.len() as f32;
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn c_major_chord_progression_is_detected_as_c_major() {
+        let analyzer = SpectralAnalyzer::new(2048);
+        let sample_rate = 48_000.0;
+        // C major, F major, G major: each shares the tonic's key signature.
+        let chords = [
+            [261.63, 329.63, 392.00], // C E G
+            [349.23, 440.00, 523.25], // F A C
+            [392.00, 493.88, 587.33], // G B D
+        ];
+
+        let mut summary = AnalysisSummary::new();
+        for chord_freqs in chords {
+            let block = chord(&chord_freqs, sample_rate, 2048);
+            let chroma = analyzer.chroma(&block, 48_000);
+            let frame = AnalysisFrame {
+                chroma,
+                ..AnalysisFrame::silent(0.0)
+            };
+            for _ in 0..4 {
+                summary.update(&frame);
+            }
+        }
+
+        assert_eq!(summary.key, Some(PitchClass::C));
+        assert_eq!(summary.mode, Some(Mode::Major));
+    }
+
+    #[test]
+    fn double_time_onsets_fold_down_to_the_true_tempo() {
+        let mut summary = AnalysisSummary::new();
+        // Beats 0.25s apart naively estimate 240 BPM; with default [60, 180]
+        // bounds that should fold down to 120.
+        let mut frame = AnalysisFrame::silent(0.0);
+        frame.beat_confidence = 1.0;
+        summary.update(&frame);
+
+        frame.timestamp = 0.25;
+        summary.update(&frame);
+
+        assert_eq!(summary.tempo_bpm, Some(120.0));
+    }
+}
@@ -0,0 +1,262 @@
+//! Shared timing utilities so live and precomputed analysis advance on the
+//! same cadence.
+
+use std::cell::Cell;
+use std::time::Instant;
+
+/// Abstracts reading the passage of time, so code that needs wall-clock time
+/// (unlike [`PlaybackClock`], which is advanced explicitly by callers) can be
+/// driven by [`MockTimeSource`] in tests instead of sleeping for real.
+pub trait TimeSource {
+    /// Seconds elapsed since some arbitrary reference point fixed when the
+    /// source was created. Only differences between two calls are
+    /// meaningful.
+    fn now(&self) -> f64;
+}
+
+impl<T: TimeSource + ?Sized> TimeSource for &T {
+    fn now(&self) -> f64 {
+        (**self).now()
+    }
+}
+
+/// The real [`TimeSource`], backed by [`std::time::Instant`].
+#[derive(Debug)]
+pub struct InstantTimeSource {
+    epoch: Instant,
+}
+
+impl InstantTimeSource {
+    pub fn new() -> Self {
+        Self { epoch: Instant::now() }
+    }
+}
+
+impl Default for InstantTimeSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimeSource for InstantTimeSource {
+    fn now(&self) -> f64 {
+        self.epoch.elapsed().as_secs_f64()
+    }
+}
+
+/// A [`TimeSource`] a test can advance by an exact amount instead of
+/// sleeping.
+#[derive(Debug, Default)]
+pub struct MockTimeSource {
+    now: Cell<f64>,
+}
+
+impl MockTimeSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Move mock time forward by `dt` seconds.
+    pub fn advance(&self, dt: f64) {
+        self.now.set(self.now.get() + dt);
+    }
+}
+
+impl TimeSource for MockTimeSource {
+    fn now(&self) -> f64 {
+        self.now.get()
+    }
+}
+
+/// Tracks wall-clock elapsed time via a [`TimeSource`], honouring pause and
+/// resume: paused intervals are banked out of [`Self::elapsed`] rather than
+/// counted. Use the default [`InstantTimeSource`] in production and
+/// [`MockTimeSource`] in tests to verify pause/resume/loop logic without
+/// sleeping.
+pub struct WallClock<S: TimeSource = InstantTimeSource> {
+    source: S,
+    started_at: Option<f64>,
+    accumulated: f64,
+}
+
+impl WallClock<InstantTimeSource> {
+    pub fn new() -> Self {
+        Self::with_source(InstantTimeSource::new())
+    }
+}
+
+impl Default for WallClock<InstantTimeSource> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: TimeSource> WallClock<S> {
+    pub fn with_source(source: S) -> Self {
+        Self {
+            source,
+            started_at: None,
+            accumulated: 0.0,
+        }
+    }
+
+    /// Start (or resume) counting elapsed time from now. A no-op if already
+    /// running.
+    pub fn start(&mut self) {
+        if self.started_at.is_none() {
+            self.started_at = Some(self.source.now());
+        }
+    }
+
+    /// Pause, banking whatever has elapsed since the last `start` into
+    /// [`Self::elapsed`]. A no-op if already paused.
+    pub fn pause(&mut self) {
+        if let Some(started_at) = self.started_at.take() {
+            self.accumulated += self.source.now() - started_at;
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.started_at.is_some()
+    }
+
+    /// Total time spent running (i.e. excluding every paused interval) since
+    /// this clock was created.
+    pub fn elapsed(&self) -> f64 {
+        match self.started_at {
+            Some(started_at) => self.accumulated + (self.source.now() - started_at),
+            None => self.accumulated,
+        }
+    }
+}
+
+/// Tracks elapsed session time, advanced explicitly by callers as blocks of
+/// audio are processed.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PlaybackClock {
+    time: f64,
+    paused: bool,
+}
+
+impl PlaybackClock {
+    pub fn new() -> Self {
+        Self {
+            time: 0.0,
+            paused: false,
+        }
+    }
+
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    /// Advance the clock by `dt` seconds, returning the new timestamp. A
+    /// no-op while [`Self::pause`]d.
+    pub fn advance(&mut self, dt: f64) -> f64 {
+        if !self.paused {
+            self.time += dt;
+        }
+        self.time
+    }
+
+    pub fn reset(&mut self) {
+        self.time = 0.0;
+    }
+}
+
+/// Generates the timestamp sequence a fixed block size and sample rate
+/// produce, so live capture and precomputed playback can share one notion
+/// of "block N happens at time T".
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlockSchedule {
+    block_dt: f64,
+}
+
+impl BlockSchedule {
+    pub fn new(sample_rate: u32, block_size: usize) -> Self {
+        Self {
+            block_dt: block_size as f64 / sample_rate as f64,
+        }
+    }
+
+    /// Duration in seconds of one block under this schedule.
+    pub fn block_duration(&self) -> f64 {
+        self.block_dt
+    }
+
+    /// Timestamp of the `n`th block (0-indexed), without touching a clock.
+    pub fn timestamp(&self, n: u64) -> f64 {
+        self.block_dt * n as f64
+    }
+
+    /// Advance `clock` by one block's duration, returning the resulting
+    /// timestamp.
+    pub fn advance(&self, clock: &mut PlaybackClock) -> f64 {
+        clock.advance(self.block_dt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_five_timestamps_match_block_size_over_sample_rate() {
+        let schedule = BlockSchedule::new(48_000, 1024);
+        for n in 0..5u64 {
+            let expected = 1024.0 / 48_000.0 * n as f64;
+            assert!((schedule.timestamp(n) - expected).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn paused_clock_ignores_advance() {
+        let mut clock = PlaybackClock::new();
+        clock.advance(1.0);
+        clock.pause();
+        clock.advance(1.0);
+        assert_eq!(clock.time(), 1.0);
+
+        clock.resume();
+        clock.advance(1.0);
+        assert_eq!(clock.time(), 2.0);
+    }
+
+    #[test]
+    fn wall_clock_with_mock_source_excludes_paused_interval_from_elapsed() {
+        let source = MockTimeSource::new();
+        let mut clock = WallClock::with_source(&source);
+
+        clock.start();
+        source.advance(1.0);
+        clock.pause();
+        source.advance(5.0); // paused: must not count toward elapsed
+        clock.start();
+        source.advance(1.0);
+
+        assert_eq!(clock.elapsed(), 2.0);
+        assert!(clock.is_running());
+    }
+
+    #[test]
+    fn advance_matches_successive_timestamps() {
+        let schedule = BlockSchedule::new(48_000, 1024);
+        let mut clock = PlaybackClock::new();
+        for n in 1..5u64 {
+            let advanced = schedule.advance(&mut clock);
+            assert!((advanced - schedule.timestamp(n)).abs() < 1e-12);
+        }
+    }
+}
@@ -0,0 +1,203 @@
+//! Minimal PCM WAV decoding, just enough to drive
+//! [`crate::playback::FilePlaybackDriver`] from a file without pulling in a
+//! full audio-container dependency.
+
+use std::fmt;
+
+/// A decoded WAV file, downmixed to mono `f32` samples in `-1.0..=1.0`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedWav {
+    pub sample_rate: u32,
+    pub samples: Vec<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WavError {
+    NotRiff,
+    NotWave,
+    MissingFmtChunk,
+    MissingDataChunk,
+    UnsupportedFormat(u16),
+    UnsupportedBitsPerSample(u16),
+    Truncated,
+}
+
+impl fmt::Display for WavError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WavError::NotRiff => write!(f, "not a RIFF file"),
+            WavError::NotWave => write!(f, "RIFF file is not a WAVE file"),
+            WavError::MissingFmtChunk => write!(f, "WAVE file has no fmt chunk"),
+            WavError::MissingDataChunk => write!(f, "WAVE file has no data chunk"),
+            WavError::UnsupportedFormat(tag) => write!(f, "unsupported WAVE format tag {tag}"),
+            WavError::UnsupportedBitsPerSample(bits) => {
+                write!(f, "unsupported bits-per-sample {bits}")
+            }
+            WavError::Truncated => write!(f, "WAVE file is truncated"),
+        }
+    }
+}
+
+impl std::error::Error for WavError {}
+
+const FORMAT_TAG_PCM: u16 = 1;
+
+/// Decode a canonical PCM16 RIFF/WAVE file, downmixing any channel count to
+/// mono by averaging channels.
+pub fn decode_wav(bytes: &[u8]) -> Result<DecodedWav, WavError> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" {
+        return Err(WavError::NotRiff);
+    }
+    if &bytes[8..12] != b"WAVE" {
+        return Err(WavError::NotWave);
+    }
+
+    let mut channels: Option<u16> = None;
+    let mut sample_rate: Option<u32> = None;
+    let mut bits_per_sample: Option<u16> = None;
+    let mut data: Option<&[u8]> = None;
+
+    let mut offset = 12;
+    while offset + 8 <= bytes.len() {
+        let chunk_id = &bytes[offset..offset + 4];
+        let chunk_len = u32::from_le_bytes(bytes[offset + 4..offset + 8].try_into().unwrap()) as usize;
+        let body_start = offset + 8;
+        let body_end = body_start.checked_add(chunk_len).ok_or(WavError::Truncated)?;
+        if body_end > bytes.len() {
+            return Err(WavError::Truncated);
+        }
+        let body = &bytes[body_start..body_end];
+
+        match chunk_id {
+            b"fmt " => {
+                if body.len() < 16 {
+                    return Err(WavError::Truncated);
+                }
+                let format_tag = u16::from_le_bytes(body[0..2].try_into().unwrap());
+                if format_tag != FORMAT_TAG_PCM {
+                    return Err(WavError::UnsupportedFormat(format_tag));
+                }
+                channels = Some(u16::from_le_bytes(body[2..4].try_into().unwrap()));
+                sample_rate = Some(u32::from_le_bytes(body[4..8].try_into().unwrap()));
+                bits_per_sample = Some(u16::from_le_bytes(body[14..16].try_into().unwrap()));
+            }
+            b"data" => data = Some(body),
+            _ => {}
+        }
+
+        // Chunks are word-aligned; an odd length is followed by a pad byte.
+        offset = body_end + (chunk_len % 2);
+    }
+
+    let channels = channels.ok_or(WavError::MissingFmtChunk)? as usize;
+    let sample_rate = sample_rate.ok_or(WavError::MissingFmtChunk)?;
+    let bits_per_sample = bits_per_sample.ok_or(WavError::MissingFmtChunk)?;
+    let data = data.ok_or(WavError::MissingDataChunk)?;
+
+    if bits_per_sample != 16 {
+        return Err(WavError::UnsupportedBitsPerSample(bits_per_sample));
+    }
+    if channels == 0 {
+        return Err(WavError::MissingFmtChunk);
+    }
+
+    let frame_bytes = 2 * channels;
+    let mut samples = Vec::with_capacity(data.len() / frame_bytes);
+    for frame in data.chunks_exact(frame_bytes) {
+        let mut sum = 0.0f32;
+        for channel in frame.chunks_exact(2) {
+            let raw = i16::from_le_bytes(channel.try_into().unwrap());
+            sum += (raw as f32 / i16::MAX as f32).max(-1.0);
+        }
+        samples.push(sum / channels as f32);
+    }
+
+    Ok(DecodedWav {
+        sample_rate,
+        samples,
+    })
+}
+
+/// Encode mono `f32` samples as a canonical PCM16 RIFF/WAVE file. Used to
+/// build test fixtures for [`decode_wav`] and the playback driver, and by
+/// [`crate::sonify::sonify`] to emit its rendered output.
+pub(crate) fn encode_wav_pcm16(sample_rate: u32, samples: &[f32]) -> Vec<u8> {
+    let data_len = samples.len() * 2;
+    let mut bytes = Vec::with_capacity(44 + data_len);
+
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&((36 + data_len) as u32).to_le_bytes());
+    bytes.extend_from_slice(b"WAVE");
+
+    bytes.extend_from_slice(b"fmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    bytes.extend_from_slice(&FORMAT_TAG_PCM.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes()); // mono
+    bytes.extend_from_slice(&sample_rate.to_le_bytes());
+    let byte_rate = sample_rate * 2;
+    bytes.extend_from_slice(&byte_rate.to_le_bytes());
+    bytes.extend_from_slice(&2u16.to_le_bytes()); // block align
+    bytes.extend_from_slice(&16u16.to_le_bytes()); // bits per sample
+
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&(data_len as u32).to_le_bytes());
+    for &sample in samples {
+        let raw = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+        bytes.extend_from_slice(&raw.to_le_bytes());
+    }
+
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_mono_pcm16_samples() {
+        let samples = vec![0.0, 0.5, -0.5, 1.0, -1.0];
+        let bytes = encode_wav_pcm16(44_100, &samples);
+
+        let decoded = decode_wav(&bytes).expect("well-formed wav should decode");
+        assert_eq!(decoded.sample_rate, 44_100);
+        assert_eq!(decoded.samples.len(), samples.len());
+        for (decoded, original) in decoded.samples.iter().zip(&samples) {
+            assert!((decoded - original).abs() < 1e-3, "{decoded} vs {original}");
+        }
+    }
+
+    #[test]
+    fn downmixes_stereo_by_averaging_channels() {
+        // Two channels, one frame: left = 1.0, right = -1.0 -> mono 0.0.
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(b"RIFF");
+        bytes.extend_from_slice(&(36 + 4u32).to_le_bytes());
+        bytes.extend_from_slice(b"WAVE");
+        bytes.extend_from_slice(b"fmt ");
+        bytes.extend_from_slice(&16u32.to_le_bytes());
+        bytes.extend_from_slice(&FORMAT_TAG_PCM.to_le_bytes());
+        bytes.extend_from_slice(&2u16.to_le_bytes()); // stereo
+        bytes.extend_from_slice(&44_100u32.to_le_bytes());
+        bytes.extend_from_slice(&(44_100u32 * 4).to_le_bytes());
+        bytes.extend_from_slice(&4u16.to_le_bytes());
+        bytes.extend_from_slice(&16u16.to_le_bytes());
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&4u32.to_le_bytes());
+        bytes.extend_from_slice(&i16::MAX.to_le_bytes());
+        bytes.extend_from_slice(&i16::MIN.to_le_bytes());
+
+        let decoded = decode_wav(&bytes).expect("well-formed stereo wav should decode");
+        assert_eq!(decoded.samples.len(), 1);
+        assert!(decoded.samples[0].abs() < 1e-3);
+    }
+
+    #[test]
+    fn rejects_non_pcm_format_tag() {
+        let mut bytes = encode_wav_pcm16(44_100, &[0.0]);
+        // Format tag lives at byte offset 20 (12 RIFF header + 8 fmt header).
+        bytes[20] = 3; // IEEE float, unsupported here
+        bytes[21] = 0;
+
+        assert_eq!(decode_wav(&bytes), Err(WavError::UnsupportedFormat(3)));
+    }
+}